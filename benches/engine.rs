@@ -0,0 +1,43 @@
+// Engine benchmarks, run with `cargo bench`. They drive the game core through the
+// headless API, so nothing here depends on a window or a GPU.
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use learning1::bot::Bot;
+use learning1::game::GameConfig;
+use learning1::headless::{self, Simulation};
+use learning1::mode::GameMode;
+use learning1::replay::Replay;
+
+// Time between two pieces in the recorded game, so gravity gets exercised on playback
+const TICKS_PER_PIECE: u64 = 250;
+
+fn bot_game(c: &mut Criterion) {
+    let bot = Bot::new();
+    c.bench_function("bot game, 500 pieces", |b| {
+        b.iter(|| headless::run_bot(GameConfig::default().with_seed(black_box(1)), GameMode::Endless, &bot, 500))
+    });
+}
+
+fn replay_playback(c: &mut Criterion) {
+    let replay = record_game(200);
+    c.bench_function("replay playback, 200 pieces", |b| b.iter(|| headless::run_replay(black_box(&replay))));
+}
+
+// A bot game played with time passing between pieces, as a replay
+fn record_game(pieces: u32) -> Replay {
+    let mut simulation = Simulation::new(GameConfig::default().with_seed(2), GameMode::Endless);
+    for _ in 0..pieces {
+        simulation.advance(TICKS_PER_PIECE);
+        let Some(&placement) = simulation.placements().first() else { break };
+        simulation.place(placement);
+        if simulation.is_finished() {
+            break;
+        }
+    }
+    simulation.session().to_replay()
+}
+
+criterion_group!(benches, bot_game, replay_playback);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// A single player input, as understood by [`Game::apply`](crate::game::Game::apply).
+///
+/// Frontends, bots, replays and network code all drive the game through actions,
+/// so they stay in sync with what a human at the keyboard can do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    RotateCw,
+    RotateCcw,
+    Rotate180,
+    SoftDrop,
+    SonicDrop,
+    HardDrop,
+    /// Swap the falling piece with the held one, once per piece
+    Hold,
+    /// Rotation applied to each new piece as it spawns, while the key stays down (IRS).
+    /// Sent with `None` when the key is released.
+    InitialRotation(Option<Rotation>),
+    /// Hold each new piece as it spawns, while the key stays down (IHS)
+    InitialHold(bool),
+    /// Not a key press: garbage sent by an opponent, with the column left open.
+    /// It goes through the same path as inputs so replays of versus games stay exact.
+    AddGarbage { lines: u8, hole: u8 },
+}
+
+/// A turn of the falling piece.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Rotation {
+    Cw,
+    Ccw,
+    Half,
+}
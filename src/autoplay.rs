@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rand::Rng;
+use web_time::Instant;
+
+use learning1::action::Action;
+use learning1::bot::{self, Bot};
+use learning1::game::{Game, VISIBLE_HEIGHT};
+
+use crate::config::VersusSettings;
+use crate::instance::GameInstance;
+
+// Skill above which the bot also looks at the next piece
+const LOOKAHEAD_SKILL: f32 = 0.6;
+// Chance to misdrop a piece at skill 0, it goes down linearly to never at skill 1
+const MAX_MISDROP_RATE: f32 = 0.35;
+// A misdrop picks among this many of the best placements (excluding the best one)
+const MISDROP_CHOICES: usize = 6;
+// Score lead that counts as "far ahead" on its own
+const SCORE_SPREAD: f32 = 2000.0;
+
+// Drives a GameInstance with the bot, one input at a time at a human-watchable pace.
+// Inputs go through the instance like keyboard inputs do, so they end up in replays too.
+pub struct BotController {
+    bot: Bot,
+    interval: Duration,
+    last_action: Instant,
+    plan: VecDeque<Action>,
+    // Number of pieces spawned when the plan was made, to notice when it went stale
+    planned_for: u32,
+    difficulty: Option<Difficulty>,
+}
+
+// Makes the bot beatable: a skill level that turns into think depth and misdrops,
+// optionally pulled toward keeping the match close.
+struct Difficulty {
+    base_skill: f32,
+    // 0 keeps the skill fixed
+    curve: f32,
+    // The skill used for the current piece
+    skill: f32,
+    // Running total to report the average skill after the match
+    skill_total: f32,
+    pieces: u32,
+}
+
+impl BotController {
+    pub fn new(actions_per_second: f32, now: Instant) -> Self {
+        BotController {
+            bot: Bot::new(),
+            interval: Duration::from_secs_f32(1.0 / actions_per_second.max(0.1)),
+            last_action: now,
+            plan: VecDeque::new(),
+            planned_for: 0,
+            difficulty: None,
+        }
+    }
+
+    // A versus opponent, the full strength bot is no fun to play against
+    pub fn with_difficulty(mut self, settings: &VersusSettings) -> Self {
+        let skill = settings.cpu_skill.clamp(0.0, 1.0);
+        let curve = if settings.rubber_band { settings.adaptation_curve.clamp(0.0, 1.0) } else { 0.0 };
+        self.difficulty = Some(Difficulty { base_skill: skill, curve, skill, skill_total: 0.0, pieces: 0 });
+        self
+    }
+
+    // Moves the skill according to how the match is going (see match_lead),
+    // called every frame by the versus scene
+    pub fn adapt(&mut self, lead: f32) {
+        if let Some(difficulty) = &mut self.difficulty {
+            difficulty.skill = (difficulty.base_skill - difficulty.curve * lead).clamp(0.0, 1.0);
+        }
+    }
+
+    // Average skill over the pieces played so far, None without a difficulty
+    pub fn effective_skill(&self) -> Option<f32> {
+        self.difficulty.as_ref().map(|difficulty| match difficulty.pieces {
+            0 => difficulty.skill,
+            pieces => difficulty.skill_total / pieces as f32,
+        })
+    }
+
+    pub fn update(&mut self, instance: &mut GameInstance, now: Instant) {
+        let game = instance.game();
+        if game.is_game_over {
+            return;
+        }
+
+        // Gravity may have locked the piece before we finished, plan again for the new one
+        let pieces: u32 = game.piece_stats.iter().sum();
+        if self.plan.is_empty() || pieces != self.planned_for {
+            self.plan = self.plan_piece(game).into();
+            self.planned_for = pieces;
+        }
+
+        if now.duration_since(self.last_action) >= self.interval
+            && let Some(action) = self.plan.pop_front()
+        {
+            instance.apply(action);
+            self.last_action = now;
+        }
+    }
+
+    fn plan_piece(&mut self, game: &Game) -> Vec<Action> {
+        let Some(difficulty) = &mut self.difficulty else {
+            return self.bot.plan(game);
+        };
+
+        difficulty.skill_total += difficulty.skill;
+        difficulty.pieces += 1;
+
+        self.bot.think_depth = if difficulty.skill >= LOOKAHEAD_SKILL { 2 } else { 1 };
+        let placements = self.bot.placements(game);
+
+        let mut rng = rand::rng();
+        let misdrop_rate = (1.0 - difficulty.skill) * MAX_MISDROP_RATE;
+        let index = if placements.len() > 1 && rng.random::<f32>() < misdrop_rate {
+            rng.random_range(1..placements.len().min(MISDROP_CHOICES + 1))
+        } else {
+            0
+        };
+
+        match placements.get(index) {
+            Some(&target) => bot::plan_for(game, target),
+            None => Vec::new(),
+        }
+    }
+}
+
+// How far ahead `own` is, from -1 (far behind) to 1 (far ahead).
+// Half comes from the score, half from how much taller the opponent's stack is.
+pub fn match_lead(own: &Game, opponent: &Game) -> f32 {
+    let score = (own.score as f32 - opponent.score as f32) / SCORE_SPREAD;
+    let stack = (opponent.stack_height() as f32 - own.stack_height() as f32) / (VISIBLE_HEIGHT as f32 / 2.0);
+    ((score.clamp(-1.0, 1.0) + stack.clamp(-1.0, 1.0)) / 2.0).clamp(-1.0, 1.0)
+}
@@ -0,0 +1,66 @@
+use web_time::Instant;
+
+use learning1::game::MAX_LEVEL;
+
+use crate::theme::Theme;
+
+// How long the glow of a line clear takes to fade
+const PULSE_SECONDS: f32 = 0.8;
+// The clock is wrapped so it keeps its precision as an f32, the stars jump once an hour
+const TIME_WRAP: f32 = 3600.0;
+
+// What the animated background reacts to: the level being played and the last line clear.
+// Playing scenes report both, the background keeps the last level between games.
+pub struct Background {
+    start: Instant,
+    level: u32,
+    // When the last clear happened, and how many lines it took
+    last_clear: Option<(Instant, u32)>,
+}
+
+// The background shader's uniform, laid out like `Background` in background.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BackgroundUniform {
+    // The theme's background color, and its UI color for the stars and the glow
+    pub base: [f32; 4],
+    pub accent: [f32; 4],
+    // Seconds since the game started
+    pub time: f32,
+    pub level: f32,
+    // 1 right after a Tetris, fading to 0
+    pub pulse: f32,
+    // Width over height of the window, so the stars stay round
+    pub aspect: f32,
+}
+
+impl Background {
+    pub fn new(now: Instant) -> Self {
+        Background { start: now, level: 1, last_clear: None }
+    }
+
+    pub fn set_level(&mut self, level: u32) {
+        self.level = level;
+    }
+
+    pub fn lines_cleared(&mut self, count: u32, now: Instant) {
+        self.last_clear = Some((now, count));
+    }
+
+    pub fn uniform(&self, theme: &Theme, aspect: f32, now: Instant) -> BackgroundUniform {
+        // Bigger clears glow brighter, a Tetris (or more) the most
+        let pulse = self.last_clear.map_or(0.0, |(at, count)| {
+            let fade = 1.0 - now.duration_since(at).as_secs_f32() / PULSE_SECONDS;
+            fade.max(0.0) * count.min(4) as f32 / 4.0
+        });
+        BackgroundUniform {
+            base: theme.background_color(),
+            accent: theme.ui_color(),
+            time: now.duration_since(self.start).as_secs_f32() % TIME_WRAP,
+            // The stars stop speeding up at the last level
+            level: self.level.min(MAX_LEVEL) as f32,
+            pulse,
+            aspect,
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// Animated background: a gradient in the theme's colors with stars drifting down.
+// The stars fall faster with the level, and the screen glows after a line clear.
+
+// See BackgroundUniform in background.rs
+struct Background {
+    base: vec4<f32>,
+    accent: vec4<f32>,
+    time: f32,
+    level: f32,
+    pulse: f32,
+    aspect: f32,
+};
+@group(0) @binding(0)
+var<uniform> bg: Background;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // From (0, 0) at the top left of the window to (1, 1)
+    @location(0) uv: vec2<f32>,
+};
+
+// One triangle big enough to cover the window, made from the vertex index alone
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.clip_position = vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(corner.x, 1.0 - corner.y);
+    return out;
+}
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+// One layer of stars: at most one per cell of a `density` grid, scrolling down at `speed` cells a second
+fn stars(uv: vec2<f32>, density: f32, speed: f32, seed: f32) -> f32 {
+    let p = vec2<f32>(uv.x * bg.aspect, uv.y) * density - vec2<f32>(0.0, bg.time * speed);
+    let cell = floor(p) + seed;
+    // Only some cells have a star
+    let present = step(0.75, hash(cell));
+    let center = vec2<f32>(hash(cell + 1.3), hash(cell + 7.1)) * 0.8 + 0.1;
+    let d = length(fract(p) - center);
+    let twinkle = 0.6 + 0.4 * sin(bg.time * 2.0 + hash(cell + 3.7) * 6.28);
+    return present * twinkle * (1.0 - smoothstep(0.0, 0.08, d));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let speed = 0.3 + bg.level * 0.16;
+
+    // A little of the accent color at the top, fading out towards the bottom
+    var color = bg.base.rgb + bg.accent.rgb * 0.05 * (1.0 - in.uv.y);
+
+    // Far layers are denser, dimmer and slower
+    let light = stars(in.uv, 24.0, speed * 0.5, 0.0) * 0.25
+        + stars(in.uv, 14.0, speed, 17.0) * 0.45
+        + stars(in.uv, 8.0, speed * 1.6, 41.0) * 0.7;
+    color += bg.accent.rgb * light * (1.0 + bg.pulse);
+
+    // Line clears light up the window from below
+    color += bg.accent.rgb * bg.pulse * 0.2 * (0.4 + 0.6 * in.uv.y);
+
+    return vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+}
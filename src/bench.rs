@@ -0,0 +1,58 @@
+use web_time::Instant;
+
+use learning1::bot::Bot;
+use learning1::game::GameConfig;
+use learning1::headless;
+use learning1::mode::GameMode;
+
+// A good bot can play for a very long time, so every game is capped to keep the run bounded
+pub const MAX_PIECES_PER_GAME: u32 = 5_000;
+
+// Runs the bot headlessly for a number of games and prints throughput and strength numbers.
+// Useful both to measure the speed of the core game logic and to catch regressions
+// after tweaking the bot heuristics.
+pub fn run_bot_benchmark(games: u32) {
+    let bot = Bot::new();
+    let games = games.max(1);
+
+    let mut total_score: u64 = 0;
+    let mut total_lines: u64 = 0;
+    let mut total_pieces: u64 = 0;
+    let mut clear_stats = [0u64; 4];
+    let mut capped_games = 0;
+
+    let start = Instant::now();
+
+    // Seeds are fixed so two runs of the benchmark play the exact same pieces
+    for seed in 0..games {
+        let report = headless::run_bot(GameConfig::default().with_seed(seed as u64), GameMode::Endless, &bot, MAX_PIECES_PER_GAME);
+        if !report.game_over {
+            capped_games += 1;
+        }
+
+        total_score += report.score as u64;
+        total_lines += report.lines as u64;
+        total_pieces += report.pieces as u64;
+        for (total, count) in clear_stats.iter_mut().zip(report.clear_stats) {
+            *total += count as u64;
+        }
+    }
+
+    let elapsed = start.elapsed().as_secs_f64();
+    let n = games as f64;
+
+    println!("Bot benchmark: {} games in {:.2}s", games, elapsed);
+    println!("  games/second:   {:.2}", n / elapsed);
+    println!("  pieces/second:  {:.0}", total_pieces as f64 / elapsed);
+    println!("  average score:  {:.1}", total_score as f64 / n);
+    println!("  average lines:  {:.1}", total_lines as f64 / n);
+    println!("  average pieces: {:.1}", total_pieces as f64 / n);
+    println!("  games capped at {} pieces: {}", MAX_PIECES_PER_GAME, capped_games);
+
+    let total_clears: u64 = clear_stats.iter().sum();
+    println!("  line clears:");
+    for (name, count) in ["single", "double", "triple", "tetris"].iter().zip(clear_stats) {
+        let pct = if total_clears > 0 { count as f64 / total_clears as f64 * 100.0 } else { 0.0 };
+        println!("    {:<7} {:>8} ({:.1}%)", name, count, pct);
+    }
+}
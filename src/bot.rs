@@ -0,0 +1,224 @@
+use crate::action::Action;
+use crate::game::{is_valid_position, Game, WIDTH, HEIGHT, HIDDEN_ROWS};
+use crate::piece_set::PieceSet;
+use crate::tetromino::{Point, TetrominoShape};
+
+type Grid = [[u8; WIDTH]; HEIGHT];
+
+// Weights for the board evaluation.
+// These are the well known values found by genetic search for a one-piece lookahead bot:
+// https://codemyroad.wordpress.com/2013/04/14/tetris-ai-the-near-perfect-player/
+#[derive(Clone, Copy, Debug)]
+pub struct Weights {
+    pub aggregate_height: f32,
+    pub lines: f32,
+    pub holes: f32,
+    pub bumpiness: f32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            aggregate_height: -0.510066,
+            lines: 0.760666,
+            holes: -0.35663,
+            bumpiness: -0.184483,
+        }
+    }
+}
+
+// A target for the current piece: how many clockwise turns, and which column to end up in.
+#[derive(Clone, Copy, Debug)]
+pub struct Placement {
+    pub rotations: u8,
+    pub x: i32,
+    pub score: f32,
+}
+
+pub struct Bot {
+    pub weights: Weights,
+    // 1 only looks at the current piece, 2 also tries every placement of the next piece
+    pub think_depth: u8,
+}
+
+impl Default for Bot {
+    fn default() -> Self {
+        Bot::new()
+    }
+}
+
+impl Bot {
+    pub fn new() -> Self {
+        Bot { weights: Weights::default(), think_depth: 1 }
+    }
+
+    // Tries every rotation and column for the current piece, drops it straight down
+    // and keeps the placement with the best looking board.
+    pub fn best_placement(&self, game: &Game) -> Option<Placement> {
+        self.placements(game).into_iter().next()
+    }
+
+    // Every reachable placement of the current piece, best first
+    pub fn placements(&self, game: &Game) -> Vec<Placement> {
+        let Some(piece) = game.current_piece.as_ref() else {
+            return Vec::new();
+        };
+
+        let pieces = &game.rules.piece_set;
+        let mut placements: Vec<Placement> = drops(&game.grid, pieces, piece.shape, piece.orientation, piece.y)
+            .into_iter()
+            .map(|drop| {
+                let score = match self.place(&game.grid, &drop) {
+                    None => f32::MIN,
+                    Some((board, lines)) if self.think_depth >= 2 => {
+                        // Judge the move by the best board the next piece can make out of it
+                        let (_, spawn_y) = pieces.spawn_position(game.next_piece);
+                        let next_best = drops(&board, pieces, game.next_piece, 0, spawn_y)
+                            .iter()
+                            .filter_map(|next| self.place(&board, next))
+                            .map(|(next_board, next_lines)| self.evaluate(&next_board, next_lines))
+                            .fold(f32::MIN, f32::max);
+                        next_best + self.weights.lines * lines as f32
+                    }
+                    Some((board, lines)) => self.evaluate(&board, lines),
+                };
+                Placement { rotations: drop.rotations, x: drop.x, score }
+            })
+            .collect();
+
+        placements.sort_by(|a, b| b.score.total_cmp(&a.score));
+        placements
+    }
+
+    // The inputs that take the current piece to its best placement:
+    // rotate, slide to the target column, hard drop.
+    // If the path turns out to be blocked the piece is simply dropped wherever it got stuck.
+    pub fn plan(&self, game: &Game) -> Vec<Action> {
+        match self.best_placement(game) {
+            Some(target) => plan_for(game, target),
+            None => Vec::new(),
+        }
+    }
+
+    // Plays one piece right away
+    pub fn play_piece(&self, game: &mut Game) {
+        for action in self.plan(game) {
+            game.apply(action);
+        }
+    }
+
+    // Writes a dropped piece into a copy of the grid and clears lines.
+    // None if the piece would lock entirely in the hidden rows, which means topping out.
+    fn place(&self, grid: &Grid, drop: &Drop) -> Option<(Grid, u32)> {
+        if drop.cells.iter().all(|&(_, y)| drop.y + y < HIDDEN_ROWS as i32) {
+            return None;
+        }
+
+        let mut board = *grid;
+        for &(local_x, local_y) in &drop.cells {
+            let abs_y = drop.y + local_y;
+            if abs_y < 0 {
+                return None;
+            }
+            board[abs_y as usize][(drop.x + local_x) as usize] = 1;
+        }
+
+        let lines = clear_full_rows(&mut board);
+        Some((board, lines))
+    }
+
+    fn evaluate(&self, board: &Grid, lines: u32) -> f32 {
+        let heights = column_heights(board);
+
+        let aggregate_height: u32 = heights.iter().sum();
+        let bumpiness: u32 = heights.windows(2).map(|w| w[0].abs_diff(w[1])).sum();
+        let holes = count_holes(board, &heights);
+
+        self.weights.aggregate_height * aggregate_height as f32
+            + self.weights.lines * lines as f32
+            + self.weights.holes * holes as f32
+            + self.weights.bumpiness * bumpiness as f32
+    }
+}
+
+// The inputs for a specific placement of the current piece
+pub fn plan_for(game: &Game, target: Placement) -> Vec<Action> {
+    let Some(piece) = game.current_piece.as_ref() else {
+        return Vec::new();
+    };
+
+    let mut actions = vec![Action::RotateCw; target.rotations as usize];
+    let shift = if target.x < piece.x { Action::MoveLeft } else { Action::MoveRight };
+    actions.extend(std::iter::repeat_n(shift, target.x.abs_diff(piece.x) as usize));
+    actions.push(Action::HardDrop);
+    actions
+}
+
+// Where a piece ends up when dropped straight down in a given rotation and column
+struct Drop {
+    rotations: u8,
+    x: i32,
+    y: i32,
+    cells: Vec<Point>,
+}
+
+// All the ways to drop a piece starting at row `start_y`, turned clockwise from `orientation`
+fn drops(grid: &Grid, pieces: &PieceSet, shape: TetrominoShape, orientation: u8, start_y: i32) -> Vec<Drop> {
+    let mut result = Vec::new();
+
+    for rotations in 0..4 {
+        let cells = pieces.turned_cells(shape, orientation + rotations);
+        for x in -2..(WIDTH as i32 + 2) {
+            if !is_valid_position(grid, &cells, x, start_y) {
+                continue;
+            }
+
+            let mut y = start_y;
+            while is_valid_position(grid, &cells, x, y + 1) {
+                y += 1;
+            }
+            result.push(Drop { rotations, x, y, cells: cells.clone() });
+        }
+    }
+
+    result
+}
+
+// Removes completed rows and returns how many there were
+fn clear_full_rows(board: &mut Grid) -> u32 {
+    let mut kept = [[0u8; WIDTH]; HEIGHT];
+    let mut write = HEIGHT;
+    let mut cleared = 0;
+
+    for y in (0..HEIGHT).rev() {
+        if board[y].iter().all(|&cell| cell != 0) {
+            cleared += 1;
+        } else {
+            write -= 1;
+            kept[write] = board[y];
+        }
+    }
+
+    *board = kept;
+    cleared
+}
+
+fn column_heights(board: &Grid) -> [u32; WIDTH] {
+    let mut heights = [0; WIDTH];
+    for (x, height) in heights.iter_mut().enumerate() {
+        if let Some(top) = (0..HEIGHT).find(|&y| board[y][x] != 0) {
+            *height = (HEIGHT - top) as u32;
+        }
+    }
+    heights
+}
+
+// A hole is an empty cell with at least one filled cell somewhere above it
+fn count_holes(board: &Grid, heights: &[u32; WIDTH]) -> u32 {
+    let mut holes = 0;
+    for (x, &height) in heights.iter().enumerate() {
+        let top = HEIGHT - height as usize;
+        holes += (top..HEIGHT).filter(|&y| board[y][x] == 0).count() as u32;
+    }
+    holes
+}
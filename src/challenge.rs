@@ -0,0 +1,122 @@
+use crate::game::GameConfig;
+use crate::mode::GameMode;
+use crate::rng::GameRng;
+use crate::rules::{ClearGravity, RuleSet};
+
+use chrono::{NaiveDate, Weekday};
+use rand::RngCore;
+
+/// A challenge: a mode with tweaked rules and a fixed seed, the same for
+/// everyone during one ISO week or one UTC day.
+///
+/// Pick the current one with [`Challenge::for_week`] or [`Challenge::for_day`].
+/// Results go into their own score bucket ([`Challenge::bucket`]) so they never
+/// mix with regular games.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Challenge {
+    /// Stable identifier of the preset, part of the bucket name
+    pub id: &'static str,
+    pub name: &'static str,
+    pub mode: GameMode,
+    pub rules: RuleSet,
+    /// When the challenge runs
+    pub period: ChallengePeriod,
+}
+
+/// The stretch of time a challenge runs for, which also picks its seed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChallengePeriod {
+    /// ISO week-numbering year and week (1-53), in UTC
+    Week { year: i32, week: u32 },
+    /// A calendar day (month 1-12, day 1-31) in UTC, so it changes at the same moment everywhere
+    Day { year: i32, month: u32, day: u32 },
+}
+
+impl Challenge {
+    /// The challenge for an ISO week. Presets rotate in order, one per week.
+    pub fn for_week(year: i32, week: u32) -> Challenge {
+        let presets = presets();
+        let (id, name, mode, rules) = presets[weeks_since_epoch(year, week).rem_euclid(presets.len() as i64) as usize].clone();
+
+        Challenge { id, name, mode, rules, period: ChallengePeriod::Week { year, week } }
+    }
+
+    /// The daily challenge for a UTC date: Marathon with the standard rules, every day.
+    /// Only the pieces change, so scores from different days can be told apart but still compared.
+    pub fn for_day(year: i32, month: u32, day: u32) -> Challenge {
+        Challenge {
+            id: "daily",
+            name: "DAILY",
+            mode: GameMode::Marathon,
+            rules: RuleSet::default(),
+            period: ChallengePeriod::Day { year, month, day },
+        }
+    }
+
+    /// Everyone plays the same pieces during the week or the day
+    pub fn seed(&self) -> u64 {
+        let seed = match self.period {
+            ChallengePeriod::Week { year, week } => ((year as u64) << 8) | week as u64,
+            // Far from any week's, the two never share pieces
+            ChallengePeriod::Day { year, month, day } => DAILY_SEED_BASE | ((year as u64) << 9) | ((month as u64) << 5) | day as u64,
+        };
+        GameRng::seed_from(seed).next_u64()
+    }
+
+    /// Score bucket for this challenge, e.g. `weekly-2026-W42-invisible_sprint` or `daily-2026-10-16`
+    pub fn bucket(&self) -> String {
+        match self.period {
+            ChallengePeriod::Week { year, week } => format!("weekly-{}-W{:02}-{}", year, week, self.id),
+            ChallengePeriod::Day { year, month, day } => format!("daily-{}-{:02}-{:02}", year, month, day),
+        }
+    }
+
+    /// Daily challenges are played once, weekly ones as often as wanted
+    pub fn single_attempt(&self) -> bool {
+        matches!(self.period, ChallengePeriod::Day { .. })
+    }
+
+    pub fn game_config(&self) -> GameConfig {
+        GameConfig::default().with_seed(self.seed()).with_rules(self.rules.clone())
+    }
+}
+
+// Weeks from the one of 1970-01-01 to the given ISO week, so consecutive weeks get consecutive presets
+// whether the year has 52 or 53 weeks. A week past the end of its year counts on into the next one.
+fn weeks_since_epoch(year: i32, week: u32) -> i64 {
+    let first_monday = NaiveDate::from_isoywd_opt(year, 1, Weekday::Mon).unwrap_or_default();
+    let days = first_monday.signed_duration_since(NaiveDate::default()).num_days();
+    days.div_euclid(7) + week.saturating_sub(1) as i64
+}
+
+const DAILY_SEED_BASE: u64 = 0xDA11 << 48;
+
+// The rotation. Appending is fine, but reordering changes which week gets what.
+fn presets() -> Vec<(&'static str, &'static str, GameMode, RuleSet)> {
+    vec![
+        ("invisible_sprint", "INVISIBLE", GameMode::Sprint, RuleSet { invisible: true, ..RuleSet::default() }),
+        ("cheese_100", "CHEESE 100", GameMode::Endless, RuleSet { starting_garbage: 10, line_goal: Some(100), ..RuleSet::default() }),
+        ("fast_sprint", "FAST 40L", GameMode::Sprint, RuleSet { gravity_ticks: 100, ..RuleSet::default() }),
+        ("no_hold_sprint", "NO HOLD", GameMode::Sprint, RuleSet { hold: false, ..RuleSet::default() }),
+        ("cascade_sprint", "CASCADE", GameMode::Sprint, RuleSet { clear_gravity: ClearGravity::Cascade, ..RuleSet::default() }),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consecutive_weeks_get_consecutive_presets_across_years() {
+        let count = presets().len();
+        let index = |year, week| presets().iter().position(|preset| preset.0 == Challenge::for_week(year, week).id).unwrap();
+
+        // 2025 has 52 weeks, 2026 has 53
+        for (last, first) in [((2025, 52), (2026, 1)), ((2026, 53), (2027, 1))] {
+            assert_eq!(index(first.0, first.1), (index(last.0, last.1) + 1) % count);
+        }
+        assert_eq!(weeks_since_epoch(2026, 1) - weeks_since_epoch(2025, 52), 1);
+    }
+}
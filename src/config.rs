@@ -0,0 +1,347 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use learning1::session::TICKS_PER_SECOND;
+
+use crate::recorder::ClipFormat;
+use crate::stats_export::ExportFormat;
+use crate::theme::Palette;
+
+pub const CONFIG_FILE: &str = "config.toml";
+
+// User settings, loaded from `config.toml` next to where the game is started.
+// Every field has a default so a partial (or missing) file is fine.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub handling: Handling,
+    pub rules: RulesSettings,
+    pub bot: BotSettings,
+    pub versus: VersusSettings,
+    pub online: OnlineSettings,
+    pub practice: PracticeSettings,
+    pub display: DisplaySettings,
+    pub pause: PauseSettings,
+    pub stats: StatsSettings,
+    pub leaderboard: LeaderboardSettings,
+    pub recording: RecordingSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Handling {
+    // Delayed auto shift: how long a side move is held before it starts repeating, in milliseconds
+    pub das_ms: u32,
+    // Auto repeat rate: milliseconds between the repeated moves, 0 slides the piece to the wall at once
+    pub arr_ms: u32,
+    // Soft drop speed as a multiple of the current gravity.
+    // Anything below 5 is raised to 5, `inf` drops the piece to the floor instantly (sonic drop).
+    pub soft_drop_factor: f32,
+    // Holding a rotate or hold key while a piece spawns turns or holds it right away (IRS/IHS)
+    pub initial_actions: bool,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das_ms: 167,
+            arr_ms: 33,
+            soft_drop_factor: 20.0,
+            initial_actions: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BotSettings {
+    // How many inputs per second the bot makes when it plays (autoplay and the title screen demo)
+    pub actions_per_second: f32,
+}
+
+impl Default for BotSettings {
+    fn default() -> Self {
+        BotSettings {
+            actions_per_second: 12.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VersusSettings {
+    // How good the CPU opponent is, from 0 (sloppy) to 1 (looks ahead, never misdrops)
+    pub cpu_skill: f32,
+    // Lets the CPU ease off when it is winning and try harder when it is losing
+    pub rubber_band: bool,
+    // How far the rubber band can move the skill away from `cpu_skill`.
+    // 0 never adapts, 1 can swing the CPU across the whole range when one side is far ahead.
+    pub adaptation_curve: f32,
+}
+
+impl Default for VersusSettings {
+    fn default() -> Self {
+        VersusSettings {
+            cpu_skill: 0.5,
+            rubber_band: true,
+            adaptation_curve: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OnlineSettings {
+    // Port "HOST GAME" listens on
+    pub port: u16,
+    // Where "JOIN GAME" connects to, as host:port
+    pub join_address: String,
+    // How many times each player may pause an online match, the host's setting is used
+    pub pauses_per_match: u32,
+}
+
+impl Default for OnlineSettings {
+    fn default() -> Self {
+        OnlineSettings {
+            port: 7878,
+            join_address: "127.0.0.1:7878".to_string(),
+            pauses_per_match: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RulesSettings {
+    // Rules file with the pieces, their spawn positions and their kicks (e.g. assets/rules/srs.json).
+    // Read at startup, unset plays the built-in standard rules.
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PracticeSettings {
+    // Rows of garbage ENDLESS games start with, to practice digging (at most 18)
+    pub starting_garbage: u32,
+    // JSON piece set file ENDLESS games deal from instead of the 7 tetrominoes
+    pub piece_set: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    // Name of the theme to start with, F2 cycles through the others while playing
+    pub theme: String,
+    // Piece colors for color vision deficiencies, applied on top of the theme
+    pub palette: Palette,
+    // A pattern on every block that tells the pieces apart without color
+    pub piece_patterns: bool,
+    // Where the falling piece would land, drawn faintly under it
+    pub ghost_piece: bool,
+    // Faint lines between the cells of the playfield, to judge where a piece will land
+    pub grid_lines: bool,
+    // Fill the playfield with the theme's well color instead of leaving the background
+    pub well_background: bool,
+    // Smooth the edges of outlines and shapes with 4x multisampling, read when the window opens
+    pub anti_aliasing: bool,
+    // How frames are handed to the screen, `--present-mode` overrides it
+    pub present_mode: PresentMode,
+    // Start in borderless fullscreen, F11 or Alt+Enter switch while playing
+    pub fullscreen: bool,
+    // Stars drifting behind the game, faster with the level and lighting up on line clears.
+    // Off leaves the theme's plain background color, for fewer distractions.
+    pub animated_background: bool,
+    // Frames drawn per second at most, 0 for no cap beyond what the present mode does
+    pub fps_limit: u32,
+}
+
+// Trades tearing against input latency. Modes the GPU doesn't offer fall back to Fifo,
+// which is always available.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresentMode {
+    // Vsync: no tearing, but a frame can wait up to a refresh before it shows
+    #[default]
+    #[serde(alias = "vsync")]
+    Fifo,
+    // No tearing and the newest frame is shown at each refresh, lower latency than Fifo
+    Mailbox,
+    // Frames show as soon as they're done, lowest latency but can tear
+    Immediate,
+}
+
+impl PresentMode {
+    pub fn to_wgpu(self) -> wgpu::PresentMode {
+        match self {
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        }
+    }
+}
+
+impl FromStr for PresentMode {
+    type Err = String;
+
+    // The names used in config.toml
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fifo" | "vsync" => Ok(PresentMode::Fifo),
+            "mailbox" => Ok(PresentMode::Mailbox),
+            "immediate" => Ok(PresentMode::Immediate),
+            _ => Err(format!("unknown present mode {} (expected fifo, mailbox or immediate)", s)),
+        }
+    }
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings {
+            theme: "CLASSIC".to_string(),
+            palette: Palette::Standard,
+            piece_patterns: false,
+            ghost_piece: true,
+            grid_lines: false,
+            well_background: false,
+            anti_aliasing: true,
+            present_mode: PresentMode::Fifo,
+            fullscreen: false,
+            animated_background: true,
+            fps_limit: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PauseSettings {
+    // Pause the game when the window loses focus (alt-tab, clicking elsewhere). Online matches never do.
+    pub on_focus_loss: bool,
+    // Resume by itself when the focus comes back, instead of waiting in the pause menu
+    pub resume_on_focus: bool,
+}
+
+impl Default for PauseSettings {
+    fn default() -> Self {
+        PauseSettings {
+            on_focus_loss: true,
+            resume_on_focus: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatsSettings {
+    // json writes one file per game, csv appends a row per game to a single file
+    pub format: ExportFormat,
+    // Export every finished game, instead of only when asked from the results screen
+    pub auto_export: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LeaderboardSettings {
+    // Send finished games to an online leaderboard. Off unless the player opts in.
+    pub enabled: bool,
+    // Base URL of the leaderboard server, see leaderboard.rs for what it must answer
+    pub endpoint: String,
+    // Name the scores are listed under
+    pub name: String,
+}
+
+impl Default for LeaderboardSettings {
+    fn default() -> Self {
+        LeaderboardSettings {
+            enabled: false,
+            endpoint: "http://localhost:8080".to_string(),
+            name: "PLAYER".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordingSettings {
+    // Keep the last `seconds` of play in memory so they can be saved as a clip (F9). Desktop only.
+    pub enabled: bool,
+    pub seconds: u32,
+    // Frames captured per second
+    pub fps: u32,
+    // Frames are scaled down to this width, the height follows the window
+    pub width: u32,
+    // Also save a clip after every Tetris
+    pub on_tetris: bool,
+    pub format: ClipFormat,
+}
+
+impl Default for RecordingSettings {
+    fn default() -> Self {
+        RecordingSettings {
+            enabled: false,
+            seconds: 10,
+            fps: 15,
+            width: 320,
+            on_tetris: true,
+            format: ClipFormat::Gif,
+        }
+    }
+}
+
+impl Handling {
+    pub const MIN_SOFT_DROP_FACTOR: f32 = 5.0;
+
+    pub fn soft_drop_factor(&self) -> f32 {
+        if self.soft_drop_factor.is_nan() {
+            return Handling::default().soft_drop_factor;
+        }
+        self.soft_drop_factor.max(Self::MIN_SOFT_DROP_FACTOR)
+    }
+
+    pub fn is_sonic_drop(&self) -> bool {
+        self.soft_drop_factor().is_infinite()
+    }
+
+    pub fn das_ticks(&self) -> u64 {
+        self.das_ms as u64 * TICKS_PER_SECOND / 1000
+    }
+
+    pub fn arr_ticks(&self) -> u64 {
+        self.arr_ms as u64 * TICKS_PER_SECOND / 1000
+    }
+}
+
+impl Config {
+    // Falls back to defaults when the file is missing or broken, a bad config should never stop the game
+    pub fn load() -> Self {
+        Self::load_from(Path::new(CONFIG_FILE))
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        Self::read_from(path).unwrap_or_else(|e| {
+            tracing::warn!("Could not read {}: {}", path.display(), e);
+            Config::default()
+        })
+    }
+
+    // Like load_from, but a file that can't be read or parsed is an error instead of the defaults,
+    // for callers that write the file back and must not replace it with them. No file is fine.
+    pub fn read_from(path: &Path) -> io::Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(e),
+        };
+        toml::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    // Writes every setting back, for the settings screen. Comments in the file are lost.
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        let contents = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, contents)
+    }
+}
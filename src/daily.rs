@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+
+use learning1::challenge::Challenge;
+
+use crate::storage;
+
+// Daily challenge attempts, one per day, kept in the user data directory next to the bests.
+// Keys are the challenge buckets (daily-2026-10-16), old days are kept to look back on.
+#[derive(Default, Serialize, Deserialize)]
+pub struct DailyLog {
+    pub version: u32,
+    pub days: BTreeMap<String, DailyResult>,
+}
+
+// How the day's attempt went. It's written when the game starts, so leaving it halfway
+// still uses up the day, and updated with the score when the game ends.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyResult {
+    pub score: u32,
+    pub lines: u32,
+    pub finished: bool,
+}
+
+impl DailyLog {
+    pub const VERSION: u32 = 1;
+
+    pub fn path() -> PathBuf {
+        storage::data_dir().join("daily.json")
+    }
+
+    // A missing or unreadable file just means no attempts yet
+    pub fn load() -> Self {
+        let Ok(json) = fs::read_to_string(Self::path()) else {
+            return DailyLog::default();
+        };
+
+        match serde_json::from_str::<DailyLog>(&json) {
+            Ok(log) if log.version == Self::VERSION => log,
+            Ok(_) => DailyLog::default(),
+            Err(e) => {
+                tracing::warn!("Could not parse {}: {}", Self::path().display(), e);
+                DailyLog::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let log = DailyLog { version: Self::VERSION, days: self.days.clone() };
+        let json = serde_json::to_string_pretty(&log).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    pub fn get(&self, challenge: &Challenge) -> Option<&DailyResult> {
+        self.days.get(&challenge.bucket())
+    }
+}
+
+// The challenge of the current UTC day, the same everywhere in the world
+pub fn todays_challenge() -> Challenge {
+    let today = chrono::Utc::now().date_naive();
+    Challenge::for_day(today.year(), today.month(), today.day())
+}
+
+// Writes the day's result, reporting failures: the game goes on either way
+pub fn record(challenge: &Challenge, result: DailyResult) {
+    let mut log = DailyLog::load();
+    log.days.insert(challenge.bucket(), result);
+    if let Err(e) = log.save() {
+        tracing::error!("Could not save the daily challenge to {}: {}", DailyLog::path().display(), e);
+    }
+}
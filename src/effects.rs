@@ -0,0 +1,399 @@
+use web_time::Instant;
+
+use learning1::events::{ClearKind, GameEvent};
+use learning1::game::{HIDDEN_ROWS, VISIBLE_HEIGHT, WIDTH};
+use learning1::piece_set::PieceSet;
+use learning1::snapshot::BoardSnapshot;
+use learning1::tetromino::{Point, TetrominoShape};
+
+use crate::graphic_context::TextEntry;
+use crate::particles::Particles;
+use crate::theme::Theme;
+
+// Combo at which the meter is full
+const COMBO_METER_MAX: u32 = 10;
+// Fill speed in "whole meters per second", filling is snappy and draining is slow enough to notice
+const COMBO_FILL_RATE: f32 = 4.0;
+const COMBO_DRAIN_RATE: f32 = 0.8;
+const COMBO_METER_BACKGROUND: [f32; 4] = [0.3, 0.3, 0.3, 0.5];
+
+// Hard drop trails fade out over this many seconds
+const TRAIL_DURATION: f32 = 0.2;
+const TRAIL_ALPHA: f32 = 0.4;
+// A trail is drawn in slices that get more transparent towards the top
+const TRAIL_SLICES: usize = 4;
+// The board shakes for this many seconds after a hard drop
+const SHAKE_DURATION: f32 = 0.15;
+// Strongest shake in cells, for a drop over the whole board height
+const SHAKE_AMPLITUDE: f32 = 0.2;
+
+// Locked pieces flash white for a few frames, so placements read clearly at high speed
+const FLASH_DURATION: f32 = 0.1;
+const FLASH_ALPHA: f32 = 0.8;
+
+// The danger warning fades in and out over about this many seconds, and pulses while it's on
+const DANGER_FADE_TIME: f32 = 0.3;
+const DANGER_PULSE_HZ: f32 = 1.5;
+
+// Score popups rise this many cells while fading out over their lifetime
+const POPUP_DURATION: f32 = 1.0;
+const POPUP_RISE: f32 = 1.5;
+// Where a level up shows when no clear popup went up with it
+const LEVEL_UP_ROW: f32 = VISIBLE_HEIGHT as f32 / 2.0;
+
+// A colored rectangle in board coordinates (1 unit = 1 cell, y down), drawn over the board
+#[derive(Clone, Copy, Debug)]
+pub struct HudQuad {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+    pub color: [f32; 4],
+}
+
+// Everything the effects layer adds to a board for one frame
+pub struct EffectsFrame {
+    pub quads: Vec<HudQuad>,
+    // Text in board coordinates too
+    pub text: Vec<TextEntry>,
+    // Offset of the whole board in cells, for screen shake
+    pub shake: (f32, f32),
+    // How strongly to show that the stack is close to the top, 0 when it isn't. Pulses.
+    pub danger: f32,
+}
+
+// Presentation state that lives next to a game but never affects it: meters, animations...
+// One per board, updated every frame from the game state (or what we know of a remote board),
+// plus short-lived animations started by game events when the board is simulated locally.
+pub struct Effects {
+    last_update: Instant,
+    combo_meter: ComboMeter,
+    // Events seen since the last update, turned into animations there
+    pending: Vec<GameEvent>,
+    trails: Vec<Trail>,
+    shake: Option<Shake>,
+    particles: Particles,
+    flashes: Vec<Flash>,
+    popups: Vec<Popup>,
+    // From 0 to 1, following the board's danger flag, and how long it has been on for the pulse
+    danger: f32,
+    danger_time: f32,
+}
+
+impl Effects {
+    pub fn new(now: Instant) -> Self {
+        Effects {
+            last_update: now,
+            combo_meter: ComboMeter::default(),
+            pending: Vec::new(),
+            trails: Vec::new(),
+            shake: None,
+            particles: Particles::default(),
+            flashes: Vec::new(),
+            popups: Vec::new(),
+            danger: 0.0,
+            danger_time: 0.0,
+        }
+    }
+
+    // Game events can come in at any time (key presses), they are played on the next update
+    pub fn push_event(&mut self, event: &GameEvent) {
+        if let GameEvent::PieceLocked { .. } | GameEvent::HardDropped { .. } | GameEvent::LinesCleared { .. } | GameEvent::LevelUp { .. } = event {
+            self.pending.push(event.clone());
+        }
+    }
+
+    pub fn update(&mut self, board: &BoardSnapshot, now: Instant) {
+        let dt = now.saturating_duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+
+        self.combo_meter.update(board.combo, dt);
+
+        for trail in &mut self.trails {
+            trail.age += dt;
+        }
+        self.trails.retain(|trail| trail.age < TRAIL_DURATION);
+        if let Some(shake) = &mut self.shake {
+            shake.age += dt;
+        }
+        self.shake = self.shake.take().filter(|shake| shake.age < SHAKE_DURATION);
+        self.particles.update(dt);
+        for flash in &mut self.flashes {
+            flash.age += dt;
+        }
+        self.flashes.retain(|flash| flash.age < FLASH_DURATION);
+        for popup in &mut self.popups {
+            popup.age += dt;
+        }
+        self.popups.retain(|popup| popup.age < POPUP_DURATION);
+
+        // Eased so a stack bobbing around the threshold doesn't flicker
+        let step = dt / DANGER_FADE_TIME;
+        self.danger = if board.danger { (self.danger + step).min(1.0) } else { (self.danger - step).max(0.0) };
+        self.danger_time = if self.danger > 0.0 { self.danger_time + dt } else { 0.0 };
+
+        for event in std::mem::take(&mut self.pending) {
+            match event {
+                GameEvent::PieceLocked { cells, .. } => self.flashes.push(Flash { cells, age: 0.0 }),
+                GameEvent::HardDropped { shape, cells, distance } if distance > 0 => {
+                    self.trails.push(Trail::new(shape, &cells, distance));
+                    let strength = SHAKE_AMPLITUDE * (distance as f32 / VISIBLE_HEIGHT as f32).min(1.0);
+                    self.shake = Some(Shake { strength, age: 0.0 });
+                }
+                GameEvent::LinesCleared { kind, rows, points, .. } => {
+                    // Links of a chain stack up above the first clear's popup
+                    let top = rows.iter().map(|&(y, _)| y).min().unwrap_or(0) - HIDDEN_ROWS as i32;
+                    let stacked = self.popups.iter().filter(|popup| popup.age == 0.0).count();
+                    let gold = kind == ClearKind::Tetris;
+                    self.popups.push(Popup::new(kind.name(), format!("+{}", points), gold, top as f32 - 2.0 * stacked as f32));
+                    for (y, cells) in &rows {
+                        self.particles.burst(*y, cells);
+                    }
+                    for flash in &mut self.flashes {
+                        flash.rows_cleared(&rows);
+                    }
+                }
+                // Also after the clears, and named after them
+                GameEvent::TSpin { lines } => {
+                    let top = self.popups.iter().filter(|popup| popup.age == 0.0).map(|popup| popup.y).fold(LEVEL_UP_ROW, f32::min);
+                    let detail = if lines == 0 { String::new() } else { ClearKind::from_count(lines).name().to_string() };
+                    self.popups.push(Popup::new("T-SPIN", detail, true, top - 2.0));
+                }
+                // Comes after the lock's clears, so it goes above their popups
+                GameEvent::LevelUp { level } => {
+                    let top = self.popups.iter().filter(|popup| popup.age == 0.0).map(|popup| popup.y).fold(LEVEL_UP_ROW, f32::min);
+                    self.popups.push(Popup::new("LEVEL UP", format!("LEVEL {}", level), true, top - 2.0));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Whether anything is still moving or fading, or will start to on the next update.
+    // While it is, every frame looks different.
+    pub fn is_animating(&self) -> bool {
+        !self.pending.is_empty()
+            || !self.trails.is_empty()
+            || self.shake.is_some()
+            || !self.particles.is_empty()
+            || !self.flashes.is_empty()
+            || !self.popups.is_empty()
+            || !self.combo_meter.is_settled()
+            || self.danger > 0.0
+    }
+
+    // Forget the time spent paused, or the meter would jump when the game resumes
+    pub fn resume(&mut self, now: Instant) {
+        self.last_update = now;
+    }
+
+    // Colors are picked here rather than when the animations start, so they follow theme changes
+    pub fn frame(&self, theme: &Theme, pieces: &PieceSet) -> EffectsFrame {
+        let mut quads = Vec::new();
+        self.combo_meter.draw(&mut quads);
+        for trail in &self.trails {
+            trail.draw(&mut quads, theme.piece_color(pieces, trail.shape));
+        }
+        self.particles.draw(&mut quads, theme, pieces);
+        for flash in &self.flashes {
+            flash.draw(&mut quads);
+        }
+        let text = self.popups.iter().flat_map(Popup::text).collect();
+
+        EffectsFrame {
+            quads,
+            text,
+            shake: self.shake.as_ref().map_or((0.0, 0.0), Shake::offset),
+            danger: self.danger * (0.6 + 0.4 * (self.danger_time * DANGER_PULSE_HZ * std::f32::consts::TAU).cos()),
+        }
+    }
+}
+
+// Streaks in the columns a hard dropped piece fell through
+struct Trail {
+    shape: TetrominoShape,
+    // Column, and the top and bottom of the streak in board rows
+    columns: Vec<(i32, f32, f32)>,
+    age: f32,
+}
+
+impl Trail {
+    fn new(shape: TetrominoShape, cells: &[Point], distance: u32) -> Self {
+        let mut columns: Vec<(i32, f32, f32)> = Vec::new();
+        for &(x, y) in cells {
+            // From where the top cell of the column started to where it landed
+            let bottom = (y - HIDDEN_ROWS as i32) as f32;
+            match columns.iter_mut().find(|(column, _, _)| *column == x) {
+                Some(column) => column.2 = column.2.min(bottom),
+                None => columns.push((x, 0.0, bottom)),
+            }
+        }
+        for column in &mut columns {
+            column.1 = (column.2 - distance as f32).max(0.0);
+        }
+
+        Trail { shape, columns, age: 0.0 }
+    }
+
+    fn draw(&self, quads: &mut Vec<HudQuad>, color: [f32; 4]) {
+        let fade = 1.0 - self.age / TRAIL_DURATION;
+        for &(x, top, bottom) in &self.columns {
+            let slice = (bottom - top) / TRAIL_SLICES as f32;
+            for i in 0..TRAIL_SLICES {
+                let alpha = TRAIL_ALPHA * fade * (i + 1) as f32 / TRAIL_SLICES as f32;
+                let [r, g, b, _] = color;
+                quads.push(HudQuad {
+                    x: x as f32 + 0.15,
+                    y: top + slice * i as f32,
+                    w: 0.7,
+                    h: slice,
+                    color: [r, g, b, alpha],
+                });
+            }
+        }
+    }
+}
+
+// White overlay on the cells of a piece that just locked, fading to the piece's own color
+struct Flash {
+    // Absolute grid positions, as in the PieceLocked event
+    cells: Vec<Point>,
+    age: f32,
+}
+
+impl Flash {
+    // The cells that were cleared are gone (the particles take over), the ones above move down with the stack
+    fn rows_cleared(&mut self, rows: &[(i32, [u8; WIDTH])]) {
+        self.cells.retain(|&(_, y)| rows.iter().all(|&(row, _)| row != y));
+        for (_, y) in &mut self.cells {
+            *y += rows.iter().filter(|&&(row, _)| row > *y).count() as i32;
+        }
+    }
+
+    fn draw(&self, quads: &mut Vec<HudQuad>) {
+        let alpha = FLASH_ALPHA * (1.0 - self.age / FLASH_DURATION);
+        for &(x, y) in &self.cells {
+            if y < HIDDEN_ROWS as i32 {
+                continue;
+            }
+            quads.push(HudQuad {
+                x: x as f32,
+                y: (y - HIDDEN_ROWS as i32) as f32,
+                w: 1.0,
+                h: 1.0,
+                color: [1.0, 1.0, 1.0, alpha],
+            });
+        }
+    }
+}
+
+// "TETRIS +800" floating up from the rows that were cleared, or "LEVEL UP" above them
+struct Popup {
+    label: &'static str,
+    detail: String,
+    // Tetrises and level ups stand out in gold, the rest is white
+    gold: bool,
+    // Board row the popup starts on
+    y: f32,
+    age: f32,
+}
+
+impl Popup {
+    fn new(label: &'static str, detail: String, gold: bool, y: f32) -> Self {
+        // Kept on the board even for clears at the very top
+        Popup { label, detail, gold, y: y.max(1.0), age: 0.0 }
+    }
+
+    fn text(&self) -> [TextEntry; 2] {
+        let progress = self.age / POPUP_DURATION;
+        let y = self.y - POPUP_RISE * progress;
+        let alpha = 1.0 - progress * progress;
+        let color = if self.gold { [1.0, 0.85, 0.2, alpha] } else { [1.0, 1.0, 1.0, alpha] };
+
+        // Text is placed by its top left corner. The font is monospaced, a character is
+        // about 1.15 cells wide per unit of scale.
+        let centered = |text: &str, scale: f32| WIDTH as f32 / 2.0 - text.len() as f32 * 1.15 * scale / 2.0;
+        [
+            TextEntry { x: centered(self.label, 0.8), y, color, scale: 0.8, text: self.label.to_string() },
+            TextEntry { x: centered(&self.detail, 0.6), y: y + 0.9, color, scale: 0.6, text: self.detail.clone() },
+        ]
+    }
+}
+
+// A short jolt of the whole board, weaker for short drops
+struct Shake {
+    strength: f32,
+    age: f32,
+}
+
+impl Shake {
+    // Mostly down, like the piece knocked the board, settling back quickly
+    fn offset(&self) -> (f32, f32) {
+        let decay = 1.0 - self.age / SHAKE_DURATION;
+        let wobble = (self.age * 60.0).cos();
+        (self.strength * 0.3 * decay * (self.age * 90.0).sin(), self.strength * decay * wobble)
+    }
+}
+
+// Vertical bar between the board and the side panel.
+// Fills a step with each consecutive clear and drains back to empty when the combo breaks.
+#[derive(Default)]
+struct ComboMeter {
+    // What is drawn, from 0 to 1, chasing the actual combo
+    fill: f32,
+    // Combo the color is picked from. Kept while draining so the bar doesn't change color on the way down.
+    shown_combo: u32,
+    // Where the fill is heading, from the last update
+    target: f32,
+}
+
+impl ComboMeter {
+    const X: f32 = WIDTH as f32 + 0.4;
+    const WIDTH: f32 = 0.7;
+
+    fn update(&mut self, combo: u32, dt: f32) {
+        let target = combo.min(COMBO_METER_MAX) as f32 / COMBO_METER_MAX as f32;
+        self.target = target;
+
+        if target >= self.fill {
+            self.fill = (self.fill + COMBO_FILL_RATE * dt).min(target);
+            self.shown_combo = combo;
+        } else {
+            self.fill = (self.fill - COMBO_DRAIN_RATE * dt).max(target);
+            // A new combo started while the old one was still draining
+            if combo > 0 {
+                self.shown_combo = combo;
+            }
+        }
+    }
+
+    fn is_settled(&self) -> bool {
+        self.fill == self.target
+    }
+
+    fn draw(&self, quads: &mut Vec<HudQuad>) {
+        let height = VISIBLE_HEIGHT as f32;
+        quads.push(HudQuad { x: Self::X, y: 0.0, w: Self::WIDTH, h: height, color: COMBO_METER_BACKGROUND });
+
+        if self.fill > 0.0 {
+            let filled = height * self.fill;
+            quads.push(HudQuad {
+                x: Self::X,
+                y: height - filled,
+                w: Self::WIDTH,
+                h: filled,
+                color: combo_color(self.shown_combo),
+            });
+        }
+    }
+}
+
+// Cooler colors for short combos, escalating to red for long ones
+fn combo_color(combo: u32) -> [f32; 4] {
+    match combo {
+        0..=2 => [0.2, 0.8, 0.3, 0.9],
+        3..=4 => [0.9, 0.9, 0.2, 0.9],
+        5..=7 => [1.0, 0.55, 0.1, 0.9],
+        _ => [1.0, 0.15, 0.15, 0.9],
+    }
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::WIDTH;
+use crate::tetromino::{Point, TetrominoShape};
+
+/// Something noteworthy that happened inside a [`Game`](crate::game::Game).
+///
+/// Events are queued by the game and collected with
+/// [`Game::drain_events`](crate::game::Game::drain_events), so effects, audio or
+/// networking can react without diffing the game state.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum GameEvent {
+    /// A piece was written into the grid. `cells` are absolute grid positions.
+    PieceLocked { shape: TetrominoShape, cells: Vec<Point> },
+    /// The falling piece was hard dropped `distance` rows. `cells` are where it landed, in absolute grid positions.
+    HardDropped { shape: TetrominoShape, cells: Vec<Point>, distance: u32 },
+    /// One or more lines were cleared by the last lock (or by one link of a chain).
+    /// `rows` are the grid rows that were cleared, with the cells they held, and `points` what the clear scored.
+    LinesCleared { count: u32, kind: ClearKind, rows: Vec<(i32, [u8; WIDTH])>, points: u32 },
+    /// The lines cleared so far reached a new level. Sent once per lock, after its clears.
+    LevelUp { level: u32 },
+    /// The falling piece went into the hold slot. `released` is the piece that came out of it,
+    /// `None` when the slot was empty and the next piece was dealt instead.
+    Hold { held: TetrominoShape, released: Option<TetrominoShape> },
+    /// A T piece was turned into a slot and locked there: its last move was a rotation, and three of
+    /// the four cells diagonal to its center were walls, floor or blocks. Sent after the lock's clears,
+    /// `lines` is how many they took (0 for a T-spin that cleared nothing).
+    TSpin { lines: u32 },
+    /// The game is over, [`Game::top_out`](crate::game::Game::top_out) says which rule ended it.
+    /// Sent once, even when a lock breaks several rules.
+    TopOut,
+}
+
+/// The guideline rule that ended a game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum TopOutKind {
+    /// A new piece spawned overlapping the stack
+    BlockOut,
+    /// A piece locked with all of its cells above the visible field
+    LockOut,
+    /// Rising garbage pushed blocks out of the top of the board
+    GarbageOut,
+}
+
+impl TopOutKind {
+    /// Upper case name, as shown on screen
+    pub fn name(self) -> &'static str {
+        match self {
+            TopOutKind::BlockOut => "BLOCK OUT",
+            TopOutKind::LockOut => "LOCK OUT",
+            TopOutKind::GarbageOut => "GARBAGE OUT",
+        }
+    }
+}
+
+/// How many lines a single clear took, as scored by the rule set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ClearKind {
+    Single,
+    Double,
+    Triple,
+    /// Four lines, or more with pieces taller than a tetromino
+    Tetris,
+}
+
+impl ClearKind {
+    /// The kind of a clear of `count` lines. Clears of more than four lines count as a Tetris.
+    pub fn from_count(count: u32) -> Self {
+        match count {
+            0 | 1 => ClearKind::Single,
+            2 => ClearKind::Double,
+            3 => ClearKind::Triple,
+            _ => ClearKind::Tetris,
+        }
+    }
+
+    /// Position in per-kind tables, such as [`RuleSet::line_clear_points`](crate::rules::RuleSet::line_clear_points)
+    /// and [`Game::clear_stats`](crate::game::Game::clear_stats).
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Upper case name, as shown on screen
+    pub fn name(self) -> &'static str {
+        match self {
+            ClearKind::Single => "SINGLE",
+            ClearKind::Double => "DOUBLE",
+            ClearKind::Triple => "TRIPLE",
+            ClearKind::Tetris => "TETRIS",
+        }
+    }
+}
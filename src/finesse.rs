@@ -0,0 +1,148 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::action::Action;
+use crate::game::{Game, GameConfig};
+use crate::rules::RuleSet;
+use crate::tetromino::{Point, TetrominoShape};
+
+// Placements needing more inputs than this are not judged, no real placement is that far
+const MAX_INPUTS: u32 = 8;
+
+/// Counts the inputs used for each piece and compares them with the fewest that reach
+/// the same placement ("finesse").
+///
+/// Feed it every key press with [`record_input`](Self::record_input) (key repeats don't count,
+/// holding a key down to slide a piece to the wall is one input) and every lock with
+/// [`piece_locked`](Self::piece_locked). Pieces that were soft dropped are not judged,
+/// they may have been tucked or spun somewhere a straight drop can't reach.
+#[derive(Clone, Debug, Default)]
+pub struct FinesseTracker {
+    inputs: u32,
+    soft_dropped: bool,
+    /// Pieces judged so far
+    pub pieces: u32,
+    /// Pieces placed with more inputs than needed
+    pub faults: u32,
+    /// Inputs used beyond the optimal ones, over the whole game
+    pub extra_inputs: u32,
+    /// Inputs wasted on the last judged piece
+    pub last_extra: Option<u32>,
+}
+
+impl FinesseTracker {
+    pub fn new() -> Self {
+        FinesseTracker::default()
+    }
+
+    pub fn record_input(&mut self, action: Action) {
+        match action {
+            Action::MoveLeft | Action::MoveRight | Action::RotateCw | Action::RotateCcw | Action::Rotate180 => self.inputs += 1,
+            Action::SoftDrop | Action::SonicDrop => self.soft_dropped = true,
+            // A new piece comes in, it starts from scratch
+            Action::Hold => self.reset(),
+            _ => {}
+        }
+    }
+
+    /// Judges the piece that just locked, returns how many inputs were wasted on it.
+    /// `cells` are absolute grid positions, as in [`GameEvent::PieceLocked`](crate::events::GameEvent::PieceLocked).
+    pub fn piece_locked(&mut self, rules: &RuleSet, shape: TetrominoShape, cells: &[Point]) -> Option<u32> {
+        let inputs = self.inputs;
+        let judged = !self.soft_dropped;
+        self.reset();
+        if !judged {
+            return None;
+        }
+
+        let optimal = optimal_inputs(rules, shape, cells)?;
+        let extra = inputs.saturating_sub(optimal);
+        self.pieces += 1;
+        self.last_extra = Some(extra);
+        if extra > 0 {
+            self.faults += 1;
+            self.extra_inputs += extra;
+        }
+        Some(extra)
+    }
+
+    /// Share of judged pieces placed without a fault, in percent
+    pub fn accuracy(&self) -> f32 {
+        if self.pieces == 0 {
+            return 100.0;
+        }
+        (self.pieces - self.faults) as f32 * 100.0 / self.pieces as f32
+    }
+
+    /// Forgets the inputs counted for the falling piece, e.g. when it's taken back
+    pub fn reset(&mut self) {
+        self.inputs = 0;
+        self.soft_dropped = false;
+    }
+}
+
+/// The fewest inputs that take a freshly spawned piece to where `cells` are, dropping straight
+/// down on an empty board: taps and turns count one each, and so does sliding all the way to a
+/// wall by holding a key. None if no sequence of up to a few inputs gets there.
+pub fn optimal_inputs(rules: &RuleSet, shape: TetrominoShape, cells: &[Point]) -> Option<u32> {
+    let target = footprint(cells);
+
+    // An empty board with only this piece, moved around by the real game so the kicks are the same
+    let mut rules = rules.clone();
+    rules.starting_garbage = 0;
+    let start = Game::with_config(GameConfig::default().with_seed(0).with_rules(rules).with_pieces(vec![shape]));
+
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([(start, 0)]);
+    while let Some((game, inputs)) = queue.pop_front() {
+        let ghost = game.get_ghost_piece_position()?;
+        if footprint(&ghost.absolute_cells()) == target {
+            return Some(inputs);
+        }
+        if inputs == MAX_INPUTS {
+            continue;
+        }
+
+        for next in moves(&game) {
+            let Some(piece) = &next.current_piece else { continue };
+            if seen.insert((piece.x, piece.y, piece.cells.clone())) {
+                queue.push_back((next, inputs + 1));
+            }
+        }
+    }
+
+    None
+}
+
+// Everything one input can do to the piece: tap, slide to the wall, turn
+fn moves(game: &Game) -> Vec<Game> {
+    let mut result = Vec::new();
+
+    for action in [Action::MoveLeft, Action::MoveRight, Action::RotateCw, Action::RotateCcw, Action::Rotate180] {
+        let mut next = game.clone();
+        next.apply(action);
+        result.push(next);
+    }
+
+    for action in [Action::MoveLeft, Action::MoveRight] {
+        let mut next = game.clone();
+        loop {
+            let x = next.current_piece.as_ref().map(|piece| piece.x);
+            next.apply(action);
+            if next.current_piece.as_ref().map(|piece| piece.x) == x {
+                break;
+            }
+        }
+        result.push(next);
+    }
+
+    result
+}
+
+// Where a piece ended up, regardless of how high the stack under it was:
+// the columns it covers and its shape, so placements that look the same compare equal
+fn footprint(cells: &[Point]) -> Vec<Point> {
+    let top = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+    let mut footprint: Vec<Point> = cells.iter().map(|&(x, y)| (x, y - top)).collect();
+    footprint.sort();
+    footprint
+}
@@ -1,40 +1,208 @@
 // consts are compile-time constants, similar to const in C#
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::action::{Action, Rotation};
+use crate::events::{ClearKind, GameEvent, TopOutKind};
+use crate::piece_set::PieceSet;
+use crate::rng::GameRng;
+use crate::rules::{ClearGravity, RuleSet};
+use crate::snapshot::BoardSnapshot;
 use crate::tetromino::{TetrominoShape, Point};
 
 pub const WIDTH: usize = 10;
-pub const HEIGHT: usize = 20;
+// The part of the board the player sees
+pub const VISIBLE_HEIGHT: usize = 20;
+// Rows above the visible board where pieces spawn (the "vanish zone").
+// Blocks up there still count, they just aren't drawn.
+pub const HIDDEN_ROWS: usize = 2;
+// Rows in the grid, hidden ones first: row HIDDEN_ROWS is the top visible row
+pub const HEIGHT: usize = VISIBLE_HEIGHT + HIDDEN_ROWS;
+// The game is in danger once the stack (with the garbage about to rise) is this high
+pub const DANGER_HEIGHT: u32 = VISIBLE_HEIGHT as u32 - 4;
 
 // Helper struct to group piece data
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ActivePiece {
     pub shape: TetrominoShape,
     pub x: i32,
     pub y: i32,
-    pub cells: [Point; 4],
+    pub cells: Vec<Point>,
+    // Clockwise turns from the spawn orientation, 0 to 3. Picks the cells and kicks of the next turn.
+    #[serde(default)]
+    pub orientation: u8,
 }
 
+// The column new pieces appear in (the pivot cell), before the shape's spawn offset.
+// The row depends on the piece set, see PieceSet::spawn_position.
+pub const SPAWN_X: i32 = (WIDTH / 2) as i32;
+
+// Grid value of a garbage cell, after the colors of every shape a set can have
+pub const GARBAGE_CELL: u8 = u8::MAX;
+// Levels go up every 10 lines until this one
+pub const MAX_LEVEL: u32 = 15;
+pub const LINES_PER_LEVEL: u32 = 10;
+
+// Mixed into the seed for the garbage hole generator
+const GARBAGE_SEED_MIX: u64 = 0x6A09_E667_F3BC_C908;
+
 impl ActivePiece {
-    pub fn new(shape: TetrominoShape) -> Self {
+    pub fn new(shape: TetrominoShape, pieces: &PieceSet) -> Self {
+        let (x, y) = pieces.spawn_position(shape);
         ActivePiece {
             shape,
-            x: (WIDTH / 2) as i32,
-            y: 0,
-            cells: shape.cells(),
+            x,
+            y,
+            cells: pieces.cells(shape).to_vec(),
+            orientation: 0,
+        }
+    }
+
+    // The cells of the piece in grid coordinates
+    pub fn absolute_cells(&self) -> Vec<Point> {
+        self.cells.iter().map(|&(x, y)| (self.x + x, self.y + y)).collect()
+    }
+}
+
+/// Everything needed to start a [`Game`].
+///
+/// Construct it with `GameConfig::default()` and the `with_*` methods, new options
+/// will be added without breaking existing code.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct GameConfig {
+    /// Seed for the piece sequence, a random one is picked when `None`
+    pub seed: Option<u64>,
+    pub rules: RuleSet,
+    /// Level the game starts at, it only goes up from there
+    pub start_level: u32,
+    /// Blocks already on the board when the game starts (puzzles)
+    pub board: [[u8; WIDTH]; HEIGHT],
+    /// Pieces dealt first, in this order, before the random sequence takes over
+    pub pieces: Vec<TetrominoShape>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            seed: None,
+            rules: RuleSet::default(),
+            start_level: 1,
+            board: [[0; WIDTH]; HEIGHT],
+            pieces: Vec::new(),
         }
     }
 }
 
+impl GameConfig {
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_rules(mut self, rules: RuleSet) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn with_start_level(mut self, level: u32) -> Self {
+        self.start_level = level.clamp(1, MAX_LEVEL);
+        self
+    }
+
+    pub fn with_board(mut self, board: [[u8; WIDTH]; HEIGHT]) -> Self {
+        self.board = board;
+        self
+    }
+
+    pub fn with_pieces(mut self, pieces: Vec<TetrominoShape>) -> Self {
+        self.pieces = pieces;
+        self
+    }
+}
+
 // This struct holds the "state" of our game.
 // It is comparable to a Class in C# with only fields.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Game {
     // 2D array: [row][col]
     // u8 is an unsigned 8-bit integer (byte).
-    // 0 will represent empty, 1-7 will represent colors/shapes later.
+    // 0 is empty, then the shape index + 1 (1-7 with the standard pieces), GARBAGE_CELL is garbage.
     pub grid: [[u8; WIDTH]; HEIGHT],
     pub current_piece: Option<ActivePiece>, // The piece currently falling
     pub next_piece: TetrominoShape, // The upcoming piece
     pub score: u32,
     pub is_game_over: bool,
-    pub piece_stats: [u32; 7],
+    // Why the game ended, set with is_game_over
+    #[serde(default)]
+    pub top_out: Option<TopOutKind>,
+    // How many of each shape were dealt, one entry per shape of the piece set
+    pub piece_stats: Vec<u32>,
+    pub lines_cleared: u32,
+    // How many singles, doubles, triples and tetrises were scored
+    pub clear_stats: [u32; 4],
+    // Pieces locked with a T-spin, whether or not they cleared lines
+    #[serde(default)]
+    pub t_spins: u32,
+    // How many pieces in a row cleared at least one line, 0 once a piece locks without clearing
+    #[serde(default)]
+    pub combo: u32,
+    // Starts at the configured start level, then follows the cleared lines
+    #[serde(default = "default_level")]
+    pub level: u32,
+    #[serde(default = "default_level", deserialize_with = "deserialize_level")]
+    pub start_level: u32,
+    // Garbage received but not in the grid yet, as (lines, hole column)
+    #[serde(default, deserialize_with = "deserialize_garbage")]
+    pub pending_garbage: Vec<(u32, usize)>,
+    // Cleared rows that had garbage in them
+    #[serde(default)]
+    pub garbage_cleared: u32,
+    // Pieces locked into the stack, and player inputs (presses, not garbage), for the play rates
+    #[serde(default)]
+    pub pieces_locked: u32,
+    #[serde(default)]
+    pub actions: u32,
+    // Holes for the garbage rows the game makes itself (cheese).
+    // A generator of its own, so cheese doesn't change the piece sequence.
+    #[serde(default)]
+    garbage_rng: GameRng,
+    // Fixed pieces still to be dealt before the random ones
+    #[serde(default)]
+    piece_queue: VecDeque<TetrominoShape>,
+    // The piece put aside with Hold
+    #[serde(default)]
+    pub hold_piece: Option<TetrominoShape>,
+    // Hold works once per piece, it's allowed again after the next lock
+    #[serde(default = "default_can_hold")]
+    pub can_hold: bool,
+    // Applied to each new piece as it spawns (IRS / IHS), set while the keys are down
+    #[serde(default)]
+    initial_rotation: Option<Rotation>,
+    #[serde(default)]
+    initial_hold: bool,
+    // The falling piece's last successful move was a rotation, for T-spin detection
+    #[serde(default)]
+    last_move_rotated: bool,
+    // The seed the piece sequence was generated from, same seed = same pieces
+    pub seed: u64,
+    pub rules: RuleSet,
+    rng: GameRng,
+    // Transient, a restored game starts with an empty queue
+    #[serde(skip)]
+    events: Vec<GameEvent>,
+    // Changes whenever the game might have, see generation()
+    #[serde(skip, default = "next_generation")]
+    generation: u64,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
 }
 
 // The 'impl' block is where we define methods for the struct.
@@ -42,19 +210,116 @@ impl Game {
     // There are no "constructors" in Rust. 
     // The convention is a static function named `new` that returns Self.
     pub fn new() -> Self {
-        let start_piece = TetrominoShape::random();
-        let next_piece = TetrominoShape::random();
+        Game::with_config(GameConfig::default())
+    }
+
+    // Creates a game whose piece sequence is fully determined by `seed`
+    pub fn with_seed(seed: u64) -> Self {
+        Game::with_config(GameConfig::default().with_seed(seed))
+    }
+
+    pub fn with_config(config: GameConfig) -> Self {
+        let seed = config.seed.unwrap_or_else(rand::random);
+        let mut rng = GameRng::seed_from(seed);
+        let mut piece_queue: VecDeque<TetrominoShape> = config.pieces.into();
+        let pieces = &config.rules.piece_set;
+        let start_piece = piece_queue.pop_front().unwrap_or_else(|| pieces.random(&mut rng));
+        let next_piece = piece_queue.pop_front().unwrap_or_else(|| pieces.random(&mut rng));
         
-        let mut stats = [0; 7];
-        stats[start_piece.to_index()] += 1;
+        // The field can be set without with_start_level
+        let start_level = config.start_level.clamp(1, MAX_LEVEL);
+        let mut stats = vec![0; pieces.pieces.len()];
+        if let Some(count) = stats.get_mut(start_piece.to_index()) {
+            *count += 1;
+        }
 
-        Game {
-            grid: [[0; WIDTH]; HEIGHT], // Initialize entire array with 0
-            current_piece: Some(ActivePiece::new(start_piece)),
+        let mut game = Game {
+            grid: config.board,
+            current_piece: Some(ActivePiece::new(start_piece, pieces)),
             next_piece,
             score: 0,
             is_game_over: false,
+            top_out: None,
             piece_stats: stats,
+            lines_cleared: 0,
+            clear_stats: [0; 4],
+            t_spins: 0,
+            combo: 0,
+            level: start_level,
+            start_level,
+            pending_garbage: Vec::new(),
+            garbage_cleared: 0,
+            pieces_locked: 0,
+            actions: 0,
+            garbage_rng: GameRng::seed_from(seed ^ GARBAGE_SEED_MIX),
+            piece_queue,
+            hold_piece: None,
+            can_hold: true,
+            initial_rotation: None,
+            initial_hold: false,
+            last_move_rotated: false,
+            seed,
+            rules: config.rules,
+            rng,
+            events: Vec::new(),
+            generation: next_generation(),
+        };
+
+        game.add_cheese_rows(game.rules.starting_garbage.min(VISIBLE_HEIGHT as u32 - 2));
+        game
+    }
+
+    // Single entry point for inputs, used by bots, replays and the network code
+    pub fn apply(&mut self, action: Action) {
+        self.generation = next_generation();
+        // Initial rotation and hold only flag the key as held, the press is counted with its own action
+        if !matches!(action, Action::AddGarbage { .. } | Action::InitialRotation(_) | Action::InitialHold(_)) {
+            self.actions += 1;
+        }
+        match action {
+            Action::MoveLeft => self.move_left(),
+            Action::MoveRight => self.move_right(),
+            Action::RotateCw => self.rotate(),
+            Action::RotateCcw => self.rotate_ccw(),
+            Action::Rotate180 => self.rotate_180(),
+            Action::SoftDrop => self.soft_drop(),
+            Action::SonicDrop => self.sonic_drop(),
+            Action::HardDrop => self.hard_drop(),
+            Action::Hold => self.hold(),
+            Action::InitialRotation(rotation) => self.initial_rotation = rotation,
+            Action::InitialHold(hold) => self.initial_hold = hold,
+            Action::AddGarbage { lines, hole } => self.queue_garbage(lines as u32, hole as usize),
+        }
+    }
+
+    // Hands over (and forgets) everything that happened since the last call
+    pub fn drain_events(&mut self) -> impl Iterator<Item = GameEvent> + '_ {
+        self.events.drain(..)
+    }
+
+    // A number that changes every time the game might have changed (a gravity step or an input),
+    // so whoever draws it can skip frames where nothing happened. Unique across all games,
+    // so a different game (or an older copy of this one) never has the same one.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn snapshot(&self) -> BoardSnapshot {
+        BoardSnapshot {
+            grid: self.grid,
+            current_piece: self.current_piece.as_ref().map(|piece| (piece.shape, piece.absolute_cells())),
+            next_piece: self.next_piece,
+            score: self.score,
+            lines_cleared: self.lines_cleared,
+            piece_stats: self.piece_stats.clone(),
+            piece_set: self.rules.piece_set.clone(),
+            hold_piece: self.hold_piece,
+            combo: self.combo,
+            level: self.level,
+            is_game_over: self.is_game_over,
+            invisible: self.rules.invisible,
+            danger: self.in_danger(),
+            metrics: None,
         }
     }
 
@@ -66,6 +331,7 @@ impl Game {
         if self.is_game_over {
             return;
         }
+        self.generation = next_generation();
 
         let mut should_lock = false;
         
@@ -76,6 +342,7 @@ impl Game {
              // Check validity
              if is_valid_position(&self.grid, &piece.cells, piece.x, new_y) {
                  piece.y = new_y;
+                 self.last_move_rotated = false;
              } else {
                  should_lock = true;
              }
@@ -88,67 +355,125 @@ impl Game {
 
     pub fn move_left(&mut self) {
         if self.is_game_over { return; }
-        if let Some(ref mut piece) = self.current_piece {
-             if is_valid_position(&self.grid, &piece.cells, piece.x - 1, piece.y) {
-                 piece.x -= 1;
-             }
+        if let Some(ref mut piece) = self.current_piece
+            && is_valid_position(&self.grid, &piece.cells, piece.x - 1, piece.y) {
+            piece.x -= 1;
+            self.last_move_rotated = false;
         }
     }
 
     pub fn move_right(&mut self) {
         if self.is_game_over { return; }
-        if let Some(ref mut piece) = self.current_piece {
-             if is_valid_position(&self.grid, &piece.cells, piece.x + 1, piece.y) {
-                 piece.x += 1;
-             }
+        if let Some(ref mut piece) = self.current_piece
+            && is_valid_position(&self.grid, &piece.cells, piece.x + 1, piece.y) {
+            piece.x += 1;
+            self.last_move_rotated = false;
         }
     }
 
+    // Clockwise
     pub fn rotate(&mut self) {
+        self.try_rotate(Rotation::Cw);
+    }
+
+    pub fn rotate_ccw(&mut self) {
+        self.try_rotate(Rotation::Ccw);
+    }
+
+    pub fn rotate_180(&mut self) {
+        self.try_rotate(Rotation::Half);
+    }
+
+    fn rotate_by(&mut self, rotation: Rotation) {
+        match rotation {
+            Rotation::Cw => self.rotate(),
+            Rotation::Ccw => self.rotate_ccw(),
+            Rotation::Half => self.rotate_180(),
+        }
+    }
+
+    // Puts the falling piece aside and brings back the held one, or the next piece the first time
+    pub fn hold(&mut self) {
+        if self.is_game_over || !self.rules.hold || !self.can_hold { return; }
+        let Some(piece) = self.current_piece.take() else { return; };
+
+        let released = self.hold_piece.replace(piece.shape);
+        self.events.push(GameEvent::Hold { held: piece.shape, released });
+        let shape = match released {
+            Some(held) => held,
+            None => self.deal(),
+        };
+        self.can_hold = false;
+        self.spawn(shape);
+    }
+
+    // Rotates the piece if it fits at one of the kick offsets, the first one that fits wins.
+    // The cells and the kicks come from the piece set.
+    fn try_rotate(&mut self, rotation: Rotation) {
         if self.is_game_over { return; }
+        let pieces = &self.rules.piece_set;
         if let Some(ref mut piece) = self.current_piece {
-            // Clone current cells to test rotation
-            let mut temp_cells = piece.cells;
-            
-            // Apply rotation math to temp
-            for cell in &mut temp_cells {
-                let (x, y) = *cell;
-                *cell = (-y, x);
-            }
+            let turns = match rotation {
+                Rotation::Cw => 1,
+                Rotation::Half => 2,
+                Rotation::Ccw => 3,
+            };
+            let orientation = (piece.orientation + turns) % 4;
+            let temp_cells = pieces.turned_cells(piece.shape, orientation);
 
             // Check if valid
-            if is_valid_position(&self.grid, &temp_cells, piece.x, piece.y) {
+            let kicks = pieces.kicks(piece.shape, rotation, piece.orientation);
+            if let Some((dx, dy)) = kicks.iter().find(|(dx, dy)| is_valid_position(&self.grid, &temp_cells, piece.x + dx, piece.y + dy)) {
                 piece.cells = temp_cells; // Commit rotation
+                piece.orientation = orientation;
+                piece.x += dx;
+                piece.y += dy;
+                self.last_move_rotated = true;
             }
         }
     }
 
     pub fn soft_drop(&mut self) {
+        if self.is_game_over { return; }
+        if let Some(ref mut piece) = self.current_piece
+            && is_valid_position(&self.grid, &piece.cells, piece.x, piece.y + 1) {
+            piece.y += 1;
+            self.score += self.rules.soft_drop_points;
+            self.last_move_rotated = false;
+        }
+        // Note: We don't lock here. Soft drop just moves faster. 
+    }
+
+    // Moves the piece straight to the floor without locking it (an "infinite" soft drop).
+    // Scores the same 1 point per cell as a regular soft drop.
+    pub fn sonic_drop(&mut self) {
         if self.is_game_over { return; }
         if let Some(ref mut piece) = self.current_piece {
-            if is_valid_position(&self.grid, &piece.cells, piece.x, piece.y + 1) {
+            while is_valid_position(&self.grid, &piece.cells, piece.x, piece.y + 1) {
                 piece.y += 1;
-                self.score += 1; // 1 point per soft drop unit
+                self.score += self.rules.soft_drop_points;
+                self.last_move_rotated = false;
             }
-            // Note: We don't lock here. Soft drop just moves faster. 
         }
     }
 
     pub fn hard_drop(&mut self) {
         if self.is_game_over { return; }
-        let mut dropped = false;
+        let mut distance = 0;
         while let Some(ref mut piece) = self.current_piece {
             if is_valid_position(&self.grid, &piece.cells, piece.x, piece.y + 1) {
                 piece.y += 1;
-                self.score += 2; // 2 points per hard drop unit
-                dropped = true;
+                self.score += self.rules.hard_drop_points;
+                distance += 1;
+                self.last_move_rotated = false;
             } else {
                 break;
             }
         }
         
-        if dropped || self.current_piece.is_some() {
-             self.lock_piece();
+        if let Some(ref piece) = self.current_piece {
+            self.events.push(GameEvent::HardDropped { shape: piece.shape, cells: piece.absolute_cells(), distance });
+            self.lock_piece();
         }
     }
 
@@ -158,7 +483,8 @@ impl Game {
                 shape: piece.shape,
                 x: piece.x,
                 y: piece.y,
-                cells: piece.cells,
+                cells: piece.cells.clone(),
+                orientation: piece.orientation,
             };
 
             while is_valid_position(&self.grid, &ghost.cells, ghost.x, ghost.y + 1) {
@@ -169,79 +495,406 @@ impl Game {
         None
     }
 
+    // Height of the tallest column, 0 for an empty board. Above VISIBLE_HEIGHT when blocks are in the hidden rows.
+    pub fn stack_height(&self) -> u32 {
+        match self.grid.iter().position(|row| row.iter().any(|&cell| cell != 0)) {
+            Some(top) => (HEIGHT - top) as u32,
+            None => 0,
+        }
+    }
+
+    // The stack is close to the top, counting the garbage that rises with the next lock
+    pub fn in_danger(&self) -> bool {
+        let incoming: u32 = self.pending_garbage.iter().map(|&(lines, _)| lines).sum();
+        !self.is_game_over && self.stack_height() + incoming >= DANGER_HEIGHT
+    }
+
     fn lock_piece(&mut self) {
+        // Judged on the board the piece locks into, before its own cells are written
+        let t_spin = self.is_t_spin();
+        // Lock out: the whole piece stayed in the hidden rows
+        let locked_out = self.current_piece.as_ref()
+            .is_some_and(|piece| piece.absolute_cells().iter().all(|&(_, y)| y < HIDDEN_ROWS as i32));
         if let Some(ref piece) = self.current_piece {
-            for (local_x, local_y) in piece.cells {
+            self.events.push(GameEvent::PieceLocked { shape: piece.shape, cells: piece.absolute_cells() });
+            self.pieces_locked += 1;
+
+            for &(local_x, local_y) in &piece.cells {
                 let abs_x = piece.x + local_x;
                 let abs_y = piece.y + local_y;
 
                 // Write to grid if within bounds
                 if abs_x >= 0 && abs_x < WIDTH as i32 && abs_y >= 0 && abs_y < HEIGHT as i32 {
-                    self.grid[abs_y as usize][abs_x as usize] = piece.shape.to_index() as u8 + 1; // Mark with shape index (1-7)
+                    self.grid[abs_y as usize][abs_x as usize] = piece.shape.to_index() as u8 + 1; // Mark with shape index + 1
                 }
             }
         }
+        if locked_out {
+            self.top_out(TopOutKind::LockOut);
+        }
 
-        self.check_lines();
+        // Garbage only rises once a piece locks without clearing anything,
+        // while cheese is refilled right after a clear
+        let lines = self.check_lines();
+        if t_spin {
+            self.t_spins += 1;
+            self.events.push(GameEvent::TSpin { lines });
+        }
+        if lines == 0 {
+            self.insert_pending_garbage();
+        } else if self.rules.cheese_rows > 0 {
+            self.refill_cheese();
+        }
 
         // Respawn a new piece from the 'next' queue
-        let next_shape = self.next_piece;
-        
-        // Generate a new next piece
-        self.next_piece = TetrominoShape::random();
+        let next_shape = self.deal();
+        self.can_hold = true;
+        self.last_move_rotated = false;
+        self.current_piece = Some(ActivePiece::new(next_shape, &self.rules.piece_set));
+
+        // Initial hold and rotation happen before the spawn check, so they can save a block out
+        if self.initial_hold {
+            self.hold();
+        }
+        if let Some(rotation) = self.initial_rotation {
+            self.rotate_by(rotation);
+        }
+
+        let piece = self.current_piece.as_ref().expect("a piece was just spawned");
+        // Block out: the new piece overlaps the stack where it spawns
+        if !is_valid_position(&self.grid, &piece.cells, piece.x, piece.y) {
+            self.top_out(TopOutKind::BlockOut);
+        }
+    }
+
+    // Ends the game. Only the first rule broken counts, the others follow from it.
+    fn top_out(&mut self, kind: TopOutKind) {
+        if self.is_game_over {
+            return;
+        }
+        self.is_game_over = true;
+        self.top_out = Some(kind);
+        self.events.push(GameEvent::TopOut);
+    }
+
+    // The three-corner rule: a T whose last move was a turn, with at least three of the four
+    // cells diagonal to its center (the pivot) taken. Walls and the floor count as taken.
+    // The piece set says which shape is that T, a piece named T in another set may not turn around its center.
+    fn is_t_spin(&self) -> bool {
+        let Some(piece) = &self.current_piece else { return false };
+        if !self.last_move_rotated || !self.rules.piece_set.has_t_spins(piece.shape) {
+            return false;
+        }
+        let taken = [(-1, -1), (1, -1), (-1, 1), (1, 1)]
+            .iter()
+            .filter(|&&(dx, dy)| !is_valid_position(&self.grid, &[(0, 0)], piece.x + dx, piece.y + dy))
+            .count();
+        taken >= 3
+    }
+
+    // Takes the next piece and queues up a new one, from the fixed pieces while there are any
+    fn deal(&mut self) -> TetrominoShape {
+        let shape = self.next_piece;
+        self.next_piece = match self.piece_queue.pop_front() {
+            Some(shape) => shape,
+            None => self.rules.piece_set.random(&mut self.rng),
+        };
 
         // Update stats for the piece that just entered the board
-        self.piece_stats[next_shape.to_index()] += 1;
+        if let Some(count) = self.piece_stats.get_mut(shape.to_index()) {
+            *count += 1;
+        }
+        shape
+    }
 
-        let new_piece = ActivePiece::new(next_shape);
-        
-        // Game Over Check: Is the spawn position valid?
-        if !is_valid_position(&self.grid, &new_piece.cells, new_piece.x, new_piece.y) {
-            self.is_game_over = true;
+    // Puts a new piece at the top, ending the game if there's no room (after a hold)
+    fn spawn(&mut self, shape: TetrominoShape) {
+        let piece = ActivePiece::new(shape, &self.rules.piece_set);
+        if !is_valid_position(&self.grid, &piece.cells, piece.x, piece.y) {
+            self.top_out(TopOutKind::BlockOut);
+        }
+        self.current_piece = Some(piece);
+        self.last_move_rotated = false;
+    }
+
+    // Queues garbage from an opponent, it rises when the next piece locks
+    pub fn queue_garbage(&mut self, lines: u32, hole: usize) {
+        if lines > 0 {
+            self.pending_garbage.push((lines, hole.min(WIDTH - 1)));
         }
-        
-        self.current_piece = Some(new_piece);
     }
 
-    fn check_lines(&mut self) {
+    // Pushes the stack up with garbage rows from the game's own generator, each with its own hole
+    pub fn add_cheese_rows(&mut self, rows: u32) {
+        for _ in 0..rows {
+            let hole = (self.garbage_rng.next_u64() % WIDTH as u64) as usize;
+            self.queue_garbage(1, hole);
+        }
+        self.insert_pending_garbage();
+    }
+
+    // Rows with at least one garbage cell
+    pub fn garbage_rows(&self) -> u32 {
+        self.grid.iter().filter(|row| row.contains(&GARBAGE_CELL)).count() as u32
+    }
+
+    // Tops the cheese back up to `cheese_rows`, but never past what's left of the dig goal
+    fn refill_cheese(&mut self) {
+        let left_to_dig = match self.rules.dig_goal {
+            Some(goal) => goal.saturating_sub(self.garbage_cleared),
+            None => u32::MAX,
+        };
+        let wanted = self.rules.cheese_rows.min(left_to_dig);
+        self.add_cheese_rows(wanted.saturating_sub(self.garbage_rows()));
+    }
+
+    // Pushes the stack up and fills the bottom with garbage rows, one hole per batch.
+    // Blocks pushed out of the top end the game.
+    fn insert_pending_garbage(&mut self) {
+        for (lines, hole) in std::mem::take(&mut self.pending_garbage) {
+            let lines = (lines as usize).min(HEIGHT);
+            if self.grid[..lines].iter().any(|row| row.iter().any(|&cell| cell != 0)) {
+                self.top_out(TopOutKind::GarbageOut);
+            }
+
+            self.grid.rotate_left(lines);
+            for row in &mut self.grid[HEIGHT - lines..] {
+                *row = [GARBAGE_CELL; WIDTH];
+                row[hole] = 0;
+            }
+
+            if self.is_game_over {
+                return;
+            }
+        }
+    }
+
+    // Returns how many lines were cleared, chain clears included
+    fn check_lines(&mut self) -> u32 {
+        let mut total = 0;
+
+        loop {
+            let rows = self.clear_full_rows();
+            if rows.is_empty() {
+                break;
+            }
+            let lines_cleared = rows.len() as u32;
+
+            // Score comes from the rule set, by default 100/300/500/800.
+            // Each link of a chain scores on its own.
+            let kind = ClearKind::from_count(lines_cleared);
+            self.lines_cleared += lines_cleared;
+            self.clear_stats[kind.index()] += 1;
+            let points = self.rules.line_clear_points[kind.index()];
+            self.score += points;
+            self.events.push(GameEvent::LinesCleared { count: lines_cleared, kind, rows, points });
+            total += lines_cleared;
+
+            // Blocks falling into the cleared rows can complete new ones
+            if !self.settle() {
+                break;
+            }
+        }
+
+        if total > 0 {
+            self.combo += 1;
+            let level = (self.lines_cleared / LINES_PER_LEVEL + 1).clamp(self.start_level, MAX_LEVEL);
+            if level > self.level {
+                self.events.push(GameEvent::LevelUp { level });
+            }
+            self.level = level;
+        } else {
+            self.combo = 0;
+        }
+
+        total
+    }
+
+    // Removes the full rows and returns them (row index and contents), bottom first.
+    // With naive gravity the rows above move down right away, otherwise settle() brings them down.
+    fn clear_full_rows(&mut self) -> Vec<(i32, [u8; WIDTH])> {
+        let naive = self.rules.clear_gravity == ClearGravity::Naive;
         let mut new_grid = [[0u8; WIDTH]; HEIGHT];
         let mut new_y = HEIGHT - 1; // Start from bottom of new grid
-        let mut lines_cleared = 0;
+        let mut cleared = Vec::new();
 
         // Iterate old grid from bottom to top
         for y in (0..HEIGHT).rev() {
             let is_full = self.grid[y].iter().all(|&cell| cell != 0);
 
+            if is_full && self.grid[y].contains(&GARBAGE_CELL) {
+                self.garbage_cleared += 1;
+            }
+
             if !is_full {
-                // Copy this row to new_grid
-                if new_y <= HEIGHT - 1 { // Bounds check though loop handles it
-                    new_grid[new_y] = self.grid[y];
-                }
-                if new_y > 0 {
-                    new_y -= 1;
-                }
+                // Copy this row to new_grid, into the same place unless rows shift down
+                let target = if naive { new_y } else { y };
+                new_grid[target] = self.grid[y];
+                new_y = new_y.saturating_sub(1);
             } else {
-                lines_cleared += 1;
+                cleared.push((y as i32, self.grid[y]));
             }
         }
         
         self.grid = new_grid;
+        cleared
+    }
+
+    // Brings blocks down after a clear, returns whether anything moved
+    fn settle(&mut self) -> bool {
+        match self.rules.clear_gravity {
+            ClearGravity::Sticky => settle_groups(&mut self.grid),
+            ClearGravity::Cascade => settle_cells(&mut self.grid),
+            _ => false,
+        }
+    }
+}
+
+// Drops every connected group of blocks as one piece until it lands, over and over until
+// nothing moves anymore (a group landing on another one can let a third one fall further).
+fn settle_groups(grid: &mut [[u8; WIDTH]; HEIGHT]) -> bool {
+    let mut moved_any = false;
 
-        // Simple scoring: 100 * 2^(lines-1)
-        if lines_cleared > 0 {
-            self.score += match lines_cleared {
-                1 => 100,
-                2 => 300,
-                3 => 500,
-                4 => 800, // Tetris!
-                _ => 100,
+    loop {
+        let mut moved = false;
+        for group in connected_groups(grid) {
+            let mut in_group = [[false; WIDTH]; HEIGHT];
+            for &(x, y) in &group {
+                in_group[y][x] = true;
+            }
+
+            // Cells below the group are free if they are empty or part of the group itself
+            let can_fall = |distance: usize| {
+                group.iter().all(|&(x, y)| y + distance < HEIGHT && (grid[y + distance][x] == 0 || in_group[y + distance][x]))
             };
+            let mut fall = 0;
+            while can_fall(fall + 1) {
+                fall += 1;
+            }
+            if fall == 0 {
+                continue;
+            }
+
+            let cells: Vec<u8> = group.iter().map(|&(x, y)| grid[y][x]).collect();
+            for &(x, y) in &group {
+                grid[y][x] = 0;
+            }
+            for (&(x, y), cell) in group.iter().zip(cells) {
+                grid[y + fall][x] = cell;
+            }
+            moved = true;
+        }
+
+        if !moved {
+            return moved_any;
+        }
+        moved_any = true;
+    }
+}
+
+// Every group of blocks touching each other side by side (flood fill), the lowest groups first
+fn connected_groups(grid: &[[u8; WIDTH]; HEIGHT]) -> Vec<Vec<(usize, usize)>> {
+    let mut seen = [[false; WIDTH]; HEIGHT];
+    let mut groups = Vec::new();
+
+    for y in (0..HEIGHT).rev() {
+        for x in 0..WIDTH {
+            if grid[y][x] == 0 || seen[y][x] {
+                continue;
+            }
+
+            let mut group = Vec::new();
+            let mut stack = vec![(x, y)];
+            seen[y][x] = true;
+            while let Some((cx, cy)) = stack.pop() {
+                group.push((cx, cy));
+                let neighbours = [
+                    (cx.wrapping_sub(1), cy),
+                    (cx + 1, cy),
+                    (cx, cy.wrapping_sub(1)),
+                    (cx, cy + 1),
+                ];
+                for (nx, ny) in neighbours {
+                    if nx < WIDTH && ny < HEIGHT && grid[ny][nx] != 0 && !seen[ny][nx] {
+                        seen[ny][nx] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+            groups.push(group);
+        }
+    }
+
+    groups
+}
+
+// Drops every block straight down on its own, so each column ends up without holes
+fn settle_cells(grid: &mut [[u8; WIDTH]; HEIGHT]) -> bool {
+    let mut moved = false;
+
+    // The row each column's next block lands on, filled from the bottom up
+    let mut write = [HEIGHT; WIDTH];
+    for y in (0..HEIGHT).rev() {
+        for (x, target) in write.iter_mut().enumerate() {
+            if grid[y][x] == 0 {
+                continue;
+            }
+            *target -= 1;
+            if *target != y {
+                grid[*target][x] = grid[y][x];
+                grid[y][x] = 0;
+                moved = true;
+            }
         }
     }
+
+    moved
+}
+
+fn next_generation() -> u64 {
+    static GENERATION: AtomicU64 = AtomicU64::new(0);
+    GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+fn default_level() -> u32 {
+    1
+}
+
+// Saved games come from a file anyone can edit, the level curve needs a start level it can reach
+fn deserialize_level<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    Ok(u32::deserialize(deserializer)?.clamp(1, MAX_LEVEL))
+}
+
+// Same for garbage, whose hole has to be a column of the board
+fn deserialize_garbage<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<(u32, usize)>, D::Error> {
+    let garbage = Vec::<(u32, usize)>::deserialize(deserializer)?;
+    Ok(garbage.into_iter().map(|(lines, hole)| (lines, hole.min(WIDTH - 1))).collect())
+}
+
+fn default_can_hold() -> bool {
+    true
+}
+
+// The smallest rectangle covering every cell of every shape of the set at its spawn position,
+// returned as inclusive (top left, bottom right) corners in grid coordinates.
+// If this area fills up, the next piece can't spawn.
+pub fn spawn_zone(pieces: &PieceSet) -> (Point, Point) {
+    let mut top_left = (i32::MAX, i32::MAX);
+    let mut bottom_right = (i32::MIN, i32::MIN);
+
+    for (i, piece) in pieces.pieces.iter().enumerate() {
+        let (spawn_x, spawn_y) = pieces.spawn_position(TetrominoShape::from_index(i));
+        for &(x, y) in &piece.cells {
+            top_left = (top_left.0.min(spawn_x + x), top_left.1.min(spawn_y + y));
+            bottom_right = (bottom_right.0.max(spawn_x + x), bottom_right.1.max(spawn_y + y));
+        }
+    }
+
+    (top_left, bottom_right)
 }
 
 // Helper function, separated from struct to avoid borrowing issues
-fn is_valid_position(grid: &[[u8; WIDTH]; HEIGHT], cells: &[Point; 4], x: i32, y: i32) -> bool {
+pub fn is_valid_position(grid: &[[u8; WIDTH]; HEIGHT], cells: &[Point], x: i32, y: i32) -> bool {
     for (local_x, local_y) in cells {
         let abs_x = x + local_x;
         let abs_y = y + local_y;
@@ -254,12 +907,267 @@ fn is_valid_position(grid: &[[u8; WIDTH]; HEIGHT], cells: &[Point; 4], x: i32, y
 
         // Check against existing blocks in the grid
         // (We assume y >= 0 for array indexing, though technically pieces can exist above board)
-        if abs_y >= 0 {
-            if grid[abs_y as usize][abs_x as usize] != 0 {
-                return false;
-            }
+        if abs_y >= 0 && grid[abs_y as usize][abs_x as usize] != 0 {
+            return false;
         }
     }
     true
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A game with the standard rules that deals `pieces` first, on `board`
+    fn game_with(pieces: &[TetrominoShape], board: [[u8; WIDTH]; HEIGHT]) -> Game {
+        Game::with_config(GameConfig::default().with_seed(1).with_pieces(pieces.to_vec()).with_board(board))
+    }
+
+    fn apply_all(game: &mut Game, actions: &[Action]) {
+        for &action in actions {
+            game.apply(action);
+        }
+    }
+
+    fn piece(game: &Game) -> &ActivePiece {
+        game.current_piece.as_ref().expect("a piece is falling")
+    }
+
+    #[test]
+    fn turn_against_the_wall_kicks_the_piece_away_from_it() {
+        let mut game = game_with(&[TetrominoShape::T, TetrominoShape::O], [[0; WIDTH]; HEIGHT]);
+        apply_all(&mut game, &[Action::SoftDrop, Action::SoftDrop, Action::SoftDrop, Action::RotateCw]);
+        // Pointing left, the pivot column is the rightmost one
+        while piece(&game).x < WIDTH as i32 - 1 {
+            game.apply(Action::MoveRight);
+        }
+
+        // Flat side down would stick out of the right wall, the first kick that fits is one cell left
+        game.apply(Action::RotateCw);
+        let piece = piece(&game);
+        assert_eq!(piece.orientation, 2);
+        assert_eq!(piece.x, WIDTH as i32 - 2);
+        assert!(is_valid_position(&game.grid, &piece.cells, piece.x, piece.y));
+    }
+
+    #[test]
+    fn turn_that_fits_nowhere_leaves_the_piece_as_it_was() {
+        // An I standing in a one cell wide well can't lie down
+        let mut board = [[0; WIDTH]; HEIGHT];
+        for row in &mut board[6..] {
+            *row = [GARBAGE_CELL; WIDTH];
+            row[0] = 0;
+        }
+        let mut game = game_with(&[TetrominoShape::I, TetrominoShape::O], board);
+        apply_all(&mut game, &[Action::SoftDrop, Action::SoftDrop, Action::RotateCw]);
+        while piece(&game).x > 0 {
+            game.apply(Action::MoveLeft);
+        }
+        game.apply(Action::SonicDrop);
+        let before = piece(&game).clone();
+
+        game.apply(Action::RotateCw);
+        let after = piece(&game);
+        assert_eq!((after.x, after.y, after.orientation), (before.x, before.y, before.orientation));
+        assert_eq!(after.cells, before.cells);
+    }
+
+    #[test]
+    fn half_turn_flips_the_piece_in_one_go() {
+        let mut game = game_with(&[TetrominoShape::T, TetrominoShape::O], [[0; WIDTH]; HEIGHT]);
+        apply_all(&mut game, &[Action::SoftDrop, Action::SoftDrop, Action::SoftDrop]);
+        let (x, y) = (piece(&game).x, piece(&game).y);
+
+        game.apply(Action::Rotate180);
+        let piece = piece(&game);
+        assert_eq!(piece.orientation, 2);
+        assert_eq!((piece.x, piece.y), (x, y));
+        assert_eq!(piece.cells, crate::tetromino::rotate_180(game.rules.piece_set.cells(TetrominoShape::T)));
+    }
+
+    // The O that comes next can't spawn on this board, the I after it can
+    fn board_blocking_the_o() -> [[u8; WIDTH]; HEIGHT] {
+        let mut board = [[0; WIDTH]; HEIGHT];
+        board[1][SPAWN_X as usize + 1] = GARBAGE_CELL;
+        board
+    }
+
+    // Moves the T away from the block and drops it, so the next piece spawns
+    const PLACE_T: [Action; 4] = [Action::MoveLeft, Action::MoveLeft, Action::MoveLeft, Action::HardDrop];
+
+    #[test]
+    fn initial_hold_saves_a_block_out() {
+        let pieces = [TetrominoShape::T, TetrominoShape::O, TetrominoShape::I];
+        let mut game = game_with(&pieces, board_blocking_the_o());
+        game.apply(Action::InitialHold(true));
+        apply_all(&mut game, &PLACE_T);
+
+        assert!(!game.is_game_over);
+        assert_eq!(game.hold_piece, Some(TetrominoShape::O));
+        assert_eq!(piece(&game).shape, TetrominoShape::I);
+        // Held on spawn, so the I can't be swapped out again
+        assert!(!game.can_hold);
+    }
+
+    #[test]
+    fn initial_rotation_turns_the_new_piece_as_it_spawns() {
+        let mut game = game_with(&[TetrominoShape::T, TetrominoShape::J], [[0; WIDTH]; HEIGHT]);
+        game.apply(Action::InitialRotation(Some(Rotation::Ccw)));
+        game.apply(Action::HardDrop);
+
+        let piece = piece(&game);
+        assert_eq!(piece.shape, TetrominoShape::J);
+        assert_eq!(piece.orientation, 3);
+    }
+
+    // Row 20 misses its right column and row 21 its left one. A loose block above row 20
+    // in the left column has nothing holding it up once row 20 is gone.
+    fn chain_board() -> [[u8; WIDTH]; HEIGHT] {
+        let mut board = [[0; WIDTH]; HEIGHT];
+        board[HEIGHT - 2] = [GARBAGE_CELL; WIDTH];
+        board[HEIGHT - 2][WIDTH - 1] = 0;
+        board[HEIGHT - 1] = [GARBAGE_CELL; WIDTH];
+        board[HEIGHT - 1][0] = 0;
+        board[HEIGHT - 3][0] = GARBAGE_CELL;
+        board
+    }
+
+    // Stands an I up and drops it into the right column, completing row 20
+    fn drop_i_on_the_right(clear_gravity: ClearGravity) -> Game {
+        let rules = RuleSet { clear_gravity, ..RuleSet::default() };
+        let config = GameConfig::default().with_seed(1).with_rules(rules).with_board(chain_board())
+            .with_pieces(vec![TetrominoShape::I, TetrominoShape::O]);
+        let mut game = Game::with_config(config);
+        apply_all(&mut game, &[Action::SoftDrop, Action::SoftDrop, Action::RotateCw]);
+        while piece(&game).x < WIDTH as i32 - 1 {
+            game.apply(Action::MoveRight);
+        }
+        game.drain_events().for_each(drop);
+        game.apply(Action::HardDrop);
+        game
+    }
+
+    fn clears(game: &mut Game) -> Vec<u32> {
+        game.drain_events()
+            .filter_map(|event| match event {
+                GameEvent::LinesCleared { count, .. } => Some(count),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sticky_gravity_drops_loose_blocks_into_a_chain_clear() {
+        let mut game = drop_i_on_the_right(ClearGravity::Sticky);
+
+        // The loose block fills row 21's hole, which clears as the second link
+        assert_eq!(clears(&mut game), vec![1, 1]);
+        assert_eq!(game.lines_cleared, 2);
+        assert_eq!(game.combo, 1);
+        // What's left of the I lands on the floor, nothing else stays
+        let blocks: Vec<(usize, usize)> = (0..HEIGHT)
+            .flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+            .filter(|&(x, y)| game.grid[y][x] != 0)
+            .collect();
+        assert_eq!(blocks, vec![(WIDTH - 1, HEIGHT - 3), (WIDTH - 1, HEIGHT - 2), (WIDTH - 1, HEIGHT - 1)]);
+    }
+
+    #[test]
+    fn naive_gravity_leaves_the_hole_under_the_loose_block() {
+        let mut game = drop_i_on_the_right(ClearGravity::Naive);
+
+        assert_eq!(clears(&mut game), vec![1]);
+        assert_eq!(game.grid[HEIGHT - 2][0], GARBAGE_CELL);
+        assert_eq!(game.grid[HEIGHT - 1][0], 0);
+    }
+
+    #[test]
+    fn cascade_gravity_drops_every_block_on_its_own() {
+        let mut board = [[0; WIDTH]; HEIGHT];
+        board[HEIGHT - 1][0] = GARBAGE_CELL;
+        // Two touching blocks, one over the hole and one over the floor block
+        board[HEIGHT - 4][0] = GARBAGE_CELL;
+        board[HEIGHT - 4][1] = GARBAGE_CELL;
+
+        assert!(settle_cells(&mut board));
+        assert_eq!(board[HEIGHT - 2][0], GARBAGE_CELL);
+        assert_eq!(board[HEIGHT - 1][1], GARBAGE_CELL);
+        // Sticky gravity keeps them together, resting on the floor block
+        let mut sticky = [[0; WIDTH]; HEIGHT];
+        sticky[HEIGHT - 1][0] = GARBAGE_CELL;
+        sticky[HEIGHT - 4][0] = GARBAGE_CELL;
+        sticky[HEIGHT - 4][1] = GARBAGE_CELL;
+        assert!(settle_groups(&mut sticky));
+        assert_eq!((sticky[HEIGHT - 2][0], sticky[HEIGHT - 2][1]), (GARBAGE_CELL, GARBAGE_CELL));
+    }
+
+    #[test]
+    fn piece_that_cant_spawn_is_a_block_out() {
+        let pieces = [TetrominoShape::T, TetrominoShape::O, TetrominoShape::I];
+        let mut game = game_with(&pieces, board_blocking_the_o());
+        apply_all(&mut game, &PLACE_T);
+
+        assert!(game.is_game_over);
+        assert_eq!(game.top_out, Some(TopOutKind::BlockOut));
+    }
+
+    #[test]
+    fn piece_locked_in_the_hidden_rows_is_a_lock_out() {
+        // The top visible row is taken under the spawn, the T locks where it appears
+        let mut board = [[0; WIDTH]; HEIGHT];
+        board[HIDDEN_ROWS][3..8].fill(GARBAGE_CELL);
+        let mut game = game_with(&[TetrominoShape::T, TetrominoShape::O], board);
+        game.apply(Action::HardDrop);
+
+        assert!(game.is_game_over);
+        assert_eq!(game.top_out, Some(TopOutKind::LockOut));
+    }
+
+    #[test]
+    fn garbage_pushing_blocks_out_of_the_top_is_a_garbage_out() {
+        let mut board = [[0; WIDTH]; HEIGHT];
+        board[0][0] = GARBAGE_CELL;
+        let mut game = game_with(&[TetrominoShape::T, TetrominoShape::O], board);
+        game.apply(Action::AddGarbage { lines: 1, hole: 5 });
+        assert!(!game.is_game_over, "garbage only rises when a piece locks");
+        apply_all(&mut game, &PLACE_T);
+
+        assert!(game.is_game_over);
+        assert_eq!(game.top_out, Some(TopOutKind::GarbageOut));
+        assert_eq!(game.drain_events().filter(|event| *event == GameEvent::TopOut).count(), 1);
+    }
+
+    #[test]
+    fn start_level_past_the_last_level_is_clamped() {
+        // Set on the field, which skips with_start_level
+        let mut config = GameConfig::default().with_seed(1);
+        config.start_level = MAX_LEVEL + 5;
+        let mut game = Game::with_config(config);
+        assert_eq!((game.start_level, game.level), (MAX_LEVEL, MAX_LEVEL));
+
+        let mut saved = serde_json::to_value(&game).expect("game serializes");
+        saved["start_level"] = (MAX_LEVEL + 5).into();
+        let restored: Game = serde_json::from_value(saved).expect("game deserializes");
+        assert_eq!(restored.start_level, MAX_LEVEL);
+
+        // Clearing a line works the level out again from the start level
+        let mut board = [[0; WIDTH]; HEIGHT];
+        board[HEIGHT - 1][..WIDTH - 1].fill(GARBAGE_CELL);
+        game.grid = board;
+        game.current_piece = Some(ActivePiece::new(TetrominoShape::I, &game.rules.piece_set));
+        apply_all(&mut game, &[Action::RotateCw, Action::MoveRight, Action::MoveRight, Action::MoveRight, Action::MoveRight, Action::MoveRight, Action::HardDrop]);
+        assert_eq!(game.lines_cleared, 1);
+        assert_eq!(game.level, MAX_LEVEL);
+    }
+
+    #[test]
+    fn press_that_also_sets_an_initial_action_counts_once() {
+        let mut game = game_with(&[TetrominoShape::T, TetrominoShape::O], [[0; WIDTH]; HEIGHT]);
+        // What the scenes apply for a rotate and a hold key going down and up again
+        apply_all(&mut game, &[Action::RotateCw, Action::InitialRotation(Some(Rotation::Cw)), Action::InitialRotation(None)]);
+        assert_eq!(game.actions, 1);
+        apply_all(&mut game, &[Action::Hold, Action::InitialHold(true), Action::InitialHold(false)]);
+        assert_eq!(game.actions, 2);
+    }
+}
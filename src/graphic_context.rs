@@ -1,15 +1,113 @@
+use std::fmt;
+use std::fs;
 use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
 use winit::window::Window;
 use wgpu::util::DeviceExt;
 use glyphon::{Attrs, Buffer, Cache, Color as TextColor, Family, FontSystem, Metrics, Resolution, Shaping, SwashCache, TextArea, TextAtlas, TextRenderer, Viewport};
 
+use crate::background::BackgroundUniform;
+use crate::config::DisplaySettings;
+use crate::layout::ScreenLayout;
+use crate::texture::Texture;
+use crate::theme::Theme;
+
+// The block tiles used when the theme doesn't bring its own, embedded like the font
+const DEFAULT_BLOCKS: &[u8] = include_bytes!("../assets/blocks.png");
+// Samples per pixel with anti-aliasing on, 4 is supported everywhere wgpu runs
+const MSAA_SAMPLES: u32 = 4;
+
+// Why the GPU couldn't be set up, or a frame couldn't be drawn.
+// The messages are meant for players, who may not know what an adapter is.
+#[derive(Debug)]
+pub enum GraphicsError {
+    Window(winit::error::OsError),
+    Surface(wgpu::CreateSurfaceError),
+    Adapter(wgpu::RequestAdapterError),
+    Device(wgpu::RequestDeviceError),
+    // The adapter can't present to the window in any format
+    NoSurfaceFormat,
+    Texture(image::ImageError),
+    TextPrepare(glyphon::PrepareError),
+    TextRender(glyphon::RenderError),
+    // Getting the window's next frame failed, which is mostly temporary
+    Frame(wgpu::SurfaceError),
+}
+
+impl fmt::Display for GraphicsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphicsError::Window(e) => write!(f, "could not open a window: {}", e),
+            GraphicsError::Surface(e) => write!(f, "could not draw into the window: {}", e),
+            GraphicsError::Adapter(e) => write!(f, "no graphics card or driver can draw this game ({}), updating the graphics drivers may help", e),
+            GraphicsError::Device(e) => write!(f, "the graphics card could not be set up: {}", e),
+            GraphicsError::NoSurfaceFormat => write!(f, "the graphics card can't draw into this window"),
+            GraphicsError::Texture(e) => write!(f, "could not load the block tiles: {}", e),
+            GraphicsError::TextPrepare(e) => write!(f, "could not lay out text: {}", e),
+            GraphicsError::TextRender(e) => write!(f, "could not draw text: {}", e),
+            GraphicsError::Frame(e) => write!(f, "could not get the next frame: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for GraphicsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GraphicsError::Window(e) => Some(e),
+            GraphicsError::Surface(e) => Some(e),
+            GraphicsError::Adapter(e) => Some(e),
+            GraphicsError::Device(e) => Some(e),
+            GraphicsError::NoSurfaceFormat => None,
+            GraphicsError::Texture(e) => Some(e),
+            GraphicsError::TextPrepare(e) => Some(e),
+            GraphicsError::TextRender(e) => Some(e),
+            GraphicsError::Frame(e) => Some(e),
+        }
+    }
+}
+
+// The one quad every instance is drawn from, as corners from (0, 0) at the top left to (1, 1)
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
-    pub position: [f32; 3],
+    pub corner: [f32; 2],
+}
+
+// Top left, bottom left, bottom right, top right: counter-clockwise on screen, like the quads were
+const UNIT_QUAD: [Vertex; 4] = [
+    Vertex { corner: [0.0, 0.0] },
+    Vertex { corner: [0.0, 1.0] },
+    Vertex { corner: [1.0, 1.0] },
+    Vertex { corner: [1.0, 0.0] },
+];
+const UNIT_QUAD_INDICES: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+// Everything on screen that isn't text is a quad: a block, a line, a particle...
+// Each one is an instance of the unit quad, stretched over `rect`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct QuadInstance {
+    // Left, right, top and bottom, in logical units (see ScreenLayout)
+    pub rect: [f32; 4],
     pub color: [f32; 4],
+    // Where in the block atlas the color is read from (see vertex_data), the color tints it.
+    // Left, right, top and bottom like `rect`.
+    pub uv: [f32; 4],
+    // How the fragment shader shades the quad, one of the QUAD_STYLE constants
+    pub style: u32,
 }
 
+// Quad styles, matching the ones in shader.wgsl. Everything but textured shades a plain color
+// from the quad's own corners, so it looks the same at any size and with any block tiles.
+pub const QUAD_STYLE_TEXTURED: u32 = 0;
+// Lighter top and left edges, darker bottom and right ones, and a highlight on the upper half
+pub const QUAD_STYLE_BEVEL: u32 = 1;
+// Rounded corners and the same highlight
+pub const QUAD_STYLE_ROUNDED: u32 = 2;
+// Fades out from the middle to the edges, for a soft light behind a block
+pub const QUAD_STYLE_GLOW: u32 = 3;
+
+#[derive(Clone)]
 pub struct TextEntry {
     pub text: String,
     pub x: f32, // Logical X
@@ -18,6 +116,24 @@ pub struct TextEntry {
     pub scale: f32,
 }
 
+// A frame read back from the screen: rows of 4 bytes per pixel, without padding
+pub struct CapturedFrame {
+    pub width: u32,
+    pub height: u32,
+    // Blue, green, red, alpha instead of red first
+    pub bgra: bool,
+    pub pixels: Vec<u8>,
+}
+
+// A frame copied out of the surface texture, until the GPU is done and the buffer can be read
+struct PendingCapture {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    bytes_per_row: u32,
+    mapped: Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
 pub struct TextSystem {
     pub font_system: FontSystem,
     pub swash_cache: SwashCache,
@@ -35,12 +151,39 @@ impl Vertex {
                 wgpu::VertexAttribute {
                     offset: 0,
                     shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
+                    format: wgpu::VertexFormat::Float32x2,
                 },
+            ]
+        }
+    }
+}
+
+impl QuadInstance {
+    // Advanced once per instance rather than per vertex, after the unit quad's location
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<QuadInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
                 wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    offset: 0,
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Uint32,
                 }
             ]
         }
@@ -53,25 +196,52 @@ pub struct GraphicContext {
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
     pub size: winit::dpi::PhysicalSize<u32>,
+    // DPI scaling of the display the window is on, 2.0 on a 200% display
+    pub scale_factor: f64,
     pub render_pipeline: wgpu::RenderPipeline,
+    pub texture_bind_group_layout: wgpu::BindGroupLayout,
+    pub block_atlas_bind_group: wgpu::BindGroup,
+    pub projection_buffer: wgpu::Buffer,
+    pub projection_bind_group: wgpu::BindGroup,
+    pub clear_color: wgpu::Color,
+    // Drawn over the clear color before the quads, see set_background
+    pub background_pipeline: wgpu::RenderPipeline,
+    pub background_buffer: wgpu::Buffer,
+    pub background_bind_group: wgpu::BindGroup,
+    pub draw_background: bool,
+    // 1 without anti-aliasing. Otherwise everything is drawn into `msaa_view` first,
+    // which is resolved into the surface texture at the end of the pass.
+    pub sample_count: u32,
+    pub msaa_view: Option<wgpu::TextureView>,
+    // The unit quad, two triangles made from 4 corners
     pub vertex_buffer: wgpu::Buffer,
-    pub num_vertices: u32,
+    pub index_buffer: wgpu::Buffer,
+    // One QuadInstance per quad, rewritten every frame
+    pub instance_buffer: wgpu::Buffer,
+    pub num_instances: u32,
     pub text_system: TextSystem,
+    // Frames can be copied out of the surface (for recording), not every platform allows it
+    pub can_capture: bool,
+    capture_requested: bool,
+    capture: Option<PendingCapture>,
 }
 
 impl GraphicContext {
-    pub async fn new(window: Arc<Window>) -> Self {
+    pub async fn new(window: Arc<Window>, display: &DisplaySettings) -> Result<Self, GraphicsError> {
         let size = window.inner_size();
+        let scale_factor = window.scale_factor();
 
         // The instance is a handle to our GPU
+        // Browsers without WebGPU fall back to WebGL 2
+        let backends = if cfg!(target_arch = "wasm32") { wgpu::Backends::BROWSER_WEBGPU | wgpu::Backends::GL } else { wgpu::Backends::PRIMARY };
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::PRIMARY,
+            backends,
             ..Default::default()
         });
         
         // The surface is the part of the window that we draw to
         // Using Arc<Window> allows the surface into be 'static
-        let surface = instance.create_surface(window.clone()).unwrap();
+        let surface = instance.create_surface(window.clone()).map_err(GraphicsError::Surface)?;
 
         // The adapter is a handle to our actual graphics card.
         let adapter = instance.request_adapter(
@@ -80,42 +250,119 @@ impl GraphicContext {
                 compatible_surface: Some(&surface),
                 force_fallback_adapter: false,
             },
-        ).await.unwrap();
+        ).await.map_err(GraphicsError::Adapter)?;
 
         let (device, queue) = adapter.request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
                 required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
+                // WebGL can't do everything the defaults ask for, and nothing here needs more
+                required_limits: if cfg!(target_arch = "wasm32") {
+                    wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits())
+                } else {
+                    wgpu::Limits::default()
+                },
                 memory_hints: Default::default(),
                 ..Default::default()
             },
-        ).await.unwrap();
+        ).await.map_err(GraphicsError::Device)?;
 
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps.formats.iter()
             .copied()
             .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
-        
+            .or(surface_caps.formats.first().copied())
+            .ok_or(GraphicsError::NoSurfaceFormat)?;
+
+        let wanted_mode = display.present_mode.to_wgpu();
+        let present_mode = if surface_caps.present_modes.contains(&wanted_mode) {
+            wanted_mode
+        } else {
+            tracing::warn!("Present mode {:?} is not supported, using vsync (Fifo)", wanted_mode);
+            wgpu::PresentMode::Fifo
+        };
+
+        // Recording reads frames back as 8-bit RGBA or BGRA
+        let can_capture = surface_caps.usages.contains(wgpu::TextureUsages::COPY_SRC)
+            && matches!(
+                surface_format,
+                wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb | wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+            );
+        let usage = if can_capture { wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC } else { wgpu::TextureUsages::RENDER_ATTACHMENT };
+
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
+            // A canvas can have no size yet when the page is still being laid out
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode,
+            alpha_mode: surface_caps.alpha_modes.first().copied().unwrap_or(wgpu::CompositeAlphaMode::Auto),
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
         surface.configure(&device, &config);
 
+        let sample_count = if !display.anti_aliasing {
+            1
+        } else if adapter.get_texture_format_features(config.format).flags.sample_count_supported(MSAA_SAMPLES) {
+            MSAA_SAMPLES
+        } else {
+            tracing::warn!("{}x anti-aliasing is not supported for {:?}, drawing without it", MSAA_SAMPLES, config.format);
+            1
+        };
+        let msaa_view = create_msaa_view(&device, &config, sample_count);
+        let multisample = wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        };
+
         // Load shader
         let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
 
+        // Block tiles (and a plain white tile for everything else), until a theme is set
+        let block_atlas = Texture::from_png(&device, &queue, DEFAULT_BLOCKS, "Block Atlas").map_err(GraphicsError::Texture)?;
+        let texture_bind_group_layout = Texture::bind_group_layout(&device);
+        let block_atlas_bind_group = block_atlas.bind_group(&device, &texture_bind_group_layout);
+
+        // Quads come in logical units, the projection fits them into the window
+        let projection_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Projection Buffer"),
+                contents: bytemuck::cast_slice(&ScreenLayout::new(size.width, size.height, scale_factor).projection()),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let projection_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Projection Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let projection_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Projection Bind Group"),
+            layout: &projection_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: projection_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
         let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[],
+            bind_group_layouts: &[&texture_bind_group_layout, &projection_bind_group_layout],
             immediate_size: 0,
         });
 
@@ -125,7 +372,7 @@ impl GraphicContext {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: Some("vs_main"), // 1.
-                buffers: &[Vertex::desc()], // 2.
+                buffers: &[Vertex::desc(), QuadInstance::desc()], // 2.
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
             },
             fragment: Some(wgpu::FragmentState { // 3.
@@ -148,21 +395,99 @@ impl GraphicContext {
                 conservative: false,
             },
             depth_stencil: None, 
-            multisample: wgpu::MultisampleState {
-                count: 1, 
-                mask: !0, 
-                alpha_to_coverage_enabled: false, 
+            multisample,
+            cache: None,
+            multiview_mask: None,
+        });
+
+        // The animated background has its own pipeline: one triangle over the whole window,
+        // with no vertex buffer and a uniform for its colors and clock
+        let background_shader = device.create_shader_module(wgpu::include_wgsl!("background.wgsl"));
+        let background_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Background Buffer"),
+                contents: bytemuck::bytes_of::<BackgroundUniform>(&bytemuck::Zeroable::zeroed()),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let background_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Background Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let background_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Background Bind Group"),
+            layout: &background_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: background_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let background_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Background Pipeline Layout"),
+            bind_group_layouts: &[&background_bind_group_layout],
+            immediate_size: 0,
+        });
+        let background_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Background Pipeline"),
+            layout: Some(&background_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &background_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
             },
+            fragment: Some(wgpu::FragmentState {
+                module: &background_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample,
             cache: None,
             multiview_mask: None,
         });
 
-        // Initialize with a dummy triangle so we don't crash before first update
         let vertex_buffer = device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("Vertex Buffer"),
+                contents: bytemuck::cast_slice(&UNIT_QUAD),
+                usage: wgpu::BufferUsages::VERTEX,
+            }
+        );
+
+        let index_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Index Buffer"),
+                contents: bytemuck::cast_slice(&UNIT_QUAD_INDICES),
+                usage: wgpu::BufferUsages::INDEX,
+            }
+        );
+
+        // Empty until the first update, so nothing is drawn before then
+        let instance_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Instance Buffer"),
                 contents: &[],
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST, 
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             }
         );
 
@@ -175,7 +500,7 @@ impl GraphicContext {
         let swash_cache = SwashCache::new();
         let cache = Cache::new(&device);
         let mut atlas = TextAtlas::new(&device, &queue, &cache, config.format);
-        let text_renderer = TextRenderer::new(&mut atlas, &device, wgpu::MultisampleState::default(), None);
+        let text_renderer = TextRenderer::new(&mut atlas, &device, multisample, None);
         let viewport = Viewport::new(&device, &cache);
 
         let text_system = TextSystem {
@@ -186,17 +511,34 @@ impl GraphicContext {
             text_renderer,
         };
 
-        Self {
+        Ok(Self {
             surface,
             device,
             queue,
             config,
             size,
+            scale_factor,
             render_pipeline,
+            texture_bind_group_layout,
+            block_atlas_bind_group,
+            projection_buffer,
+            projection_bind_group,
+            clear_color: wgpu::Color { r: 0.1, g: 0.1, b: 0.1, a: 1.0 },
+            background_pipeline,
+            background_buffer,
+            background_bind_group,
+            draw_background: false,
+            sample_count,
+            msaa_view,
             vertex_buffer,
-            num_vertices: 0,
+            index_buffer,
+            instance_buffer,
+            num_instances: 0,
             text_system,
-        }
+            can_capture,
+            capture_requested: false,
+            capture: None,
+        })
     }
 
 
@@ -206,96 +548,90 @@ impl GraphicContext {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
+            self.msaa_view = create_msaa_view(&self.device, &self.config, self.sample_count);
+
+            // The quads stay the same, only the way they're fitted into the window changes
+            let projection = ScreenLayout::new(new_size.width, new_size.height, self.scale_factor).projection();
+            self.queue.write_buffer(&self.projection_buffer, 0, bytemuck::cast_slice(&projection));
         }
     }
 
-    pub fn update_buffers(&mut self, vertices: &[Vertex]) {
-        self.num_vertices = vertices.len() as u32;
-        
+    // The window moved to a display with different DPI scaling. Its new size comes in a resize.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    // Background color and block tiles come from the theme, the rest of its colors go into the quads
+    pub fn set_theme(&mut self, theme: &Theme) {
+        let [r, g, b, a] = theme.background_color();
+        self.clear_color = wgpu::Color { r: r as f64, g: g as f64, b: b as f64, a: a as f64 };
+
+        let custom = theme.blocks.as_ref().and_then(|path| {
+            let loaded = fs::read(path)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| Texture::from_png(&self.device, &self.queue, &bytes, "Block Atlas").map_err(|e| e.to_string()));
+            loaded.map_err(|e| tracing::warn!("Could not load block tiles {}: {}", path.display(), e)).ok()
+        });
+        // The default tiles already loaded once, in new(). Should they fail now, the old ones stay.
+        let atlas = match custom.map(Ok).unwrap_or_else(|| Texture::from_png(&self.device, &self.queue, DEFAULT_BLOCKS, "Block Atlas")) {
+            Ok(atlas) => atlas,
+            Err(e) => {
+                tracing::warn!("{}", GraphicsError::Texture(e));
+                return;
+            }
+        };
+        self.block_atlas_bind_group = atlas.bind_group(&self.device, &self.texture_bind_group_layout);
+    }
+
+    // The animated background for the next frames, or None for the plain background color
+    pub fn set_background(&mut self, background: Option<BackgroundUniform>) {
+        self.draw_background = background.is_some();
+        if let Some(uniform) = background {
+            self.queue.write_buffer(&self.background_buffer, 0, bytemuck::bytes_of(&uniform));
+        }
+    }
+
+    pub fn update_buffers(&mut self, instances: &[QuadInstance]) {
+        self.num_instances = instances.len() as u32;
+
         // Recreate buffer if it's too small or just create new one every time (simple but inefficient)
-        // For Tetris, vertex count is low, so recreating is fine or writing to existing if mapped.
+        // For Tetris, instance count is low, so recreating is fine or writing to existing if mapped.
         // COPY_DST allows write_buffer.
-        
-        self.vertex_buffer = self.device.create_buffer_init(
+
+        self.instance_buffer = self.device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: bytemuck::cast_slice(vertices),
+                label: Some("Instance Buffer"),
+                contents: bytemuck::cast_slice(instances),
                 usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             }
         );
     }
 
-    pub fn render(&mut self, text_entries: &[TextEntry]) -> Result<(), wgpu::SurfaceError> {
+    pub fn render(&mut self, text_entries: &[TextEntry]) -> Result<(), GraphicsError> {
         // --- 1. Prepare Text ---
-        // We use a buffer to hold the text area
-        let _buffer = Buffer::new(&mut self.text_system.font_system, Metrics::new(30.0, 42.0));
         let width = self.size.width as f32;
         let height = self.size.height as f32;
         
         self.text_system.viewport.update(&self.queue, Resolution { width: self.size.width, height: self.size.height });
 
-        // We will create a TextArea for each entry (inefficient but simple)
-        // Actually processing all text this way every frame is okay for this complexity.
-        // glyphon works best with 'Buffer's.
-        
-        // For multiple independent text blocks, we create a generic text processor or just loop.
-        // glyphon Prepare step takes a slice of TextAreas.
-        
+        // One glyphon Buffer per entry, rebuilt every frame (simple, and cheap enough for a few dozen labels).
+        // TextAreas borrow their Buffer, so the buffers are kept in their own Vec first.
         let mut text_areas = Vec::new();
-        // Since Buffer cannot be easily moved into the Vec because of lifetimes/borrowing within the loop,
-        // we might need to recreate them or have a different strategy. 
-        // glyphon::TextArea stores reference to buffer.
-        // So we need a Vec<Buffer> that lives long enough.
-        
         let mut buffers = Vec::new();
+        let layout = ScreenLayout::new(self.size.width, self.size.height, self.scale_factor);
 
         for entry in text_entries {
-             let physical_font_size = entry.scale * 30.0; // Base size multiplier
-             let mut buff = Buffer::new(&mut self.text_system.font_system, Metrics::new(physical_font_size, physical_font_size * 1.2));
+             // Base size multiplier, growing and shrinking with the window like the blocks.
+             // In logical pixels, the text area's scale turns them into physical ones.
+             let font_size = entry.scale * 30.0 * layout.text_scale();
+             let mut buff = Buffer::new(&mut self.text_system.font_system, Metrics::new(font_size, font_size * 1.2));
              
-             // Convert Logical X/Y to Physical
-             // VertexData Logical mapping:
-             // WIDTH=800, HEIGHT=800.
-             // logical_width = 10 + 16 = 26.
-             // logical_height = 29.
-             // unit_size_y = 1.9 / 29.0
-             // unit_size_x = unit_size_y / aspect.
-             
-             // We need to inverse the projection in VertexData to get Pixel Coords,
-             // OR simplest way: Just pass Pixel Coords from VertexData?
-             // VertexData doesn't know Screen Size properly without querying.
-             // Let's rely on stored conversion in VertexData or main.
-             // Just assuming entry.x/y are PRE-CALCULATED PIXEL COORDINATES?
-             // No, "Logical X" in VertexData is like "Grid Cell 5".
-             // We need to convert Grid Cell 5 -> Pixel.
-             
-             // Let's do the conversion here.
-             let logical_w = crate::game::WIDTH as f32 + 16.0;
-             let logical_h = 29.0;
-             
-             let aspect = width / height;
-             let unit_scale_y = 1.9 / logical_h;
-             let unit_scale_x = unit_scale_y / aspect;
-             
-             let total_ndc_w = unit_scale_x * logical_w; 
-             let start_ndc_x = -total_ndc_w / 2.0;
-
-             let total_ndc_h = unit_scale_y * logical_h; 
-             let start_ndc_y = total_ndc_h / 2.0;
-             
-             // NDC X = start_ndc_x + (entry.x * unit_scale_x)
-             // NDC Y = start_ndc_y - (entry.y * unit_scale_y)
-             
-             let ndc_x = start_ndc_x + (entry.x * unit_scale_x);
-             let ndc_y = start_ndc_y - (entry.y * unit_scale_y);
-             
-             // Pixel X = (ndc_x + 1.0) * 0.5 * width
-             // Pixel Y = (1.0 - ndc_y) * 0.5 * height
-             
-             let screen_x = (ndc_x + 1.0) * 0.5 * width;
-             let screen_y = (1.0 - ndc_y) * 0.5 * height;
+             // Convert Logical X/Y (grid cells) to Physical pixels, whole ones to keep the glyphs sharp
+             let (screen_x, screen_y) = layout.to_pixels(entry.x, entry.y);
+             let (screen_x, screen_y) = (screen_x.round(), screen_y.round());
 
-             buff.set_size(&mut self.text_system.font_system, Some(width), Some(height));
+             // The buffer is laid out in logical pixels too
+             buff.set_size(&mut self.text_system.font_system, Some(width / layout.scale_factor()), Some(height / layout.scale_factor()));
              buff.set_text(&mut self.text_system.font_system, &entry.text, &Attrs::new().family(Family::Name("Press Start 2P")), Shaping::Advanced, None);
              buffers.push((buff, screen_x, screen_y, entry.color));
         }
@@ -306,7 +642,7 @@ impl GraphicContext {
                  buffer: buff,
                  left: *x,
                  top: *y,
-                 scale: 1.0,
+                 scale: layout.scale_factor(),
                  bounds: glyphon::TextBounds {
                      left: 0,
                      top: 0,
@@ -326,10 +662,10 @@ impl GraphicContext {
             &self.text_system.viewport,
             text_areas,
             &mut self.text_system.swash_cache,
-        ).unwrap();
+        ).map_err(GraphicsError::TextPrepare)?;
 
 
-        let output = self.surface.get_current_texture()?;
+        let output = self.surface.get_current_texture().map_err(GraphicsError::Frame)?;
         let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -340,16 +676,12 @@ impl GraphicContext {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
+                    view: self.msaa_view.as_ref().unwrap_or(&view),
+                    resolve_target: self.msaa_view.as_ref().map(|_| &view),
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.1,
-                            b: 0.1,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
+                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        // Only the resolved image is needed after the pass
+                        store: if self.msaa_view.is_some() { wgpu::StoreOp::Discard } else { wgpu::StoreOp::Store },
                     },
                     depth_slice: None,
                 })],
@@ -359,11 +691,28 @@ impl GraphicContext {
                 multiview_mask: None,
             });
 
+            if self.draw_background {
+                render_pass.set_pipeline(&self.background_pipeline);
+                render_pass.set_bind_group(0, &self.background_bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+
             render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(0, &self.block_atlas_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.projection_bind_group, &[]);
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.draw(0..self.num_vertices, 0..1);
+            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            render_pass.draw_indexed(0..UNIT_QUAD_INDICES.len() as u32, 0, 0..self.num_instances);
             
-            self.text_system.text_renderer.render(&self.text_system.atlas, &self.text_system.viewport, &mut render_pass).unwrap();
+            self.text_system.text_renderer.render(&self.text_system.atlas, &self.text_system.viewport, &mut render_pass)
+                .map_err(GraphicsError::TextRender)?;
+        }
+
+        // Copied before presenting, the texture is gone after that. One capture at a time, a frame
+        // asked for while the last one is still on its way is skipped.
+        if std::mem::take(&mut self.capture_requested) && self.can_capture && self.capture.is_none() {
+            self.capture = Some(self.copy_frame(&mut encoder, &output.texture));
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
@@ -374,4 +723,87 @@ impl GraphicContext {
 
         Ok(())
     }
+
+    // The next frame drawn is copied for take_capture
+    pub fn request_capture(&mut self) {
+        self.capture_requested = true;
+    }
+
+    // The last captured frame once the GPU has finished it, without waiting for it
+    pub fn take_capture(&mut self) -> Option<CapturedFrame> {
+        let pending = self.capture.as_ref()?;
+        let _ = self.device.poll(wgpu::PollType::Poll);
+        match pending.mapped.try_recv() {
+            Err(TryRecvError::Empty) => return None,
+            Ok(Ok(())) => {}
+            Ok(Err(_)) | Err(TryRecvError::Disconnected) => {
+                self.capture = None;
+                return None;
+            }
+        }
+
+        let pending = self.capture.take()?;
+        let row_length = pending.width as usize * 4;
+        let mut pixels = Vec::with_capacity(row_length * pending.height as usize);
+        {
+            let data = pending.buffer.slice(..).get_mapped_range();
+            for row in data.chunks(pending.bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..row_length]);
+            }
+        }
+        pending.buffer.unmap();
+
+        Some(CapturedFrame {
+            width: pending.width,
+            height: pending.height,
+            bgra: matches!(self.config.format, wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb),
+            pixels,
+        })
+    }
+
+    // Records a copy of the surface texture into a new buffer, mapped for reading once the commands ran.
+    // Rows are padded to the copy alignment.
+    fn copy_frame(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) -> PendingCapture {
+        let (width, height) = (texture.width(), texture.height());
+        let bytes_per_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture Buffer"),
+            size: bytes_per_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout { offset: 0, bytes_per_row: Some(bytes_per_row), rows_per_image: None },
+            },
+            texture.size(),
+        );
+
+        let (sender, mapped) = mpsc::channel();
+        encoder.map_buffer_on_submit(&buffer, wgpu::MapMode::Read, .., move |result| {
+            let _ = sender.send(result);
+        });
+        PendingCapture { buffer, width, height, bytes_per_row, mapped }
+    }
+}
+
+// The multisampled texture drawn into before resolving, sized like the surface. None without anti-aliasing.
+fn create_msaa_view(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) -> Option<wgpu::TextureView> {
+    if sample_count == 1 {
+        return None;
+    }
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("MSAA Texture"),
+        size: wgpu::Extent3d { width: config.width.max(1), height: config.height.max(1), depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
 }
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+
+use crate::action::Action;
+use crate::bot::{self, Bot, Placement};
+use crate::events::{GameEvent, TopOutKind};
+use crate::game::{Game, GameConfig};
+use crate::mode::GameMode;
+use crate::replay::Replay;
+use crate::session::Session;
+use crate::snapshot::BoardSnapshot;
+
+/// The outcome of a game run without a window, see [`run_bot`], [`run_replay`] and [`Simulation`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SimulationReport {
+    pub seed: u64,
+    pub score: u32,
+    pub lines: u32,
+    pub level: u32,
+    pub pieces: u32,
+    /// Simulation ticks played, see [`TICKS_PER_SECOND`](crate::session::TICKS_PER_SECOND)
+    pub ticks: u64,
+    /// Singles, doubles, triples and tetrises
+    pub clear_stats: [u32; 4],
+    pub game_over: bool,
+    /// The rule that ended the game, when it topped out
+    pub top_out: Option<TopOutKind>,
+    /// Reached the mode's goal (lines, garbage or puzzle)
+    pub complete: bool,
+}
+
+impl SimulationReport {
+    pub fn from_session(session: &Session) -> Self {
+        let game = &session.game;
+        SimulationReport {
+            seed: game.seed,
+            score: game.score,
+            lines: game.lines_cleared,
+            level: game.level,
+            pieces: game.pieces_locked,
+            ticks: session.tick,
+            clear_stats: game.clear_stats,
+            game_over: game.is_game_over,
+            top_out: game.top_out,
+            complete: session.is_complete(),
+        }
+    }
+}
+
+/// A game stepped by the caller instead of a clock, as fast as it is driven.
+///
+/// Meant for bots and learning experiments: apply single inputs, let time pass with
+/// [`advance`](Self::advance), or pick one of the [`placements`](Self::placements)
+/// of the current piece and [`place`](Self::place) it. Every call returns the events
+/// it caused, which is where rewards usually come from.
+pub struct Simulation {
+    session: Session,
+    // Only used to list placements, its scores are a hint rather than a rule
+    bot: Bot,
+}
+
+impl Simulation {
+    pub fn new(config: GameConfig, mode: GameMode) -> Self {
+        Simulation::from_session(Session::new(Game::with_config(config), mode))
+    }
+
+    pub fn from_session(session: Session) -> Self {
+        Simulation { session, bot: Bot::new() }
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.session.game
+    }
+
+    pub fn snapshot(&self) -> BoardSnapshot {
+        self.session.game.snapshot()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.session.is_finished()
+    }
+
+    pub fn report(&self) -> SimulationReport {
+        SimulationReport::from_session(&self.session)
+    }
+
+    /// Applies an input at the current tick, like a key press
+    pub fn apply(&mut self, action: Action) -> Vec<GameEvent> {
+        self.session.apply(action);
+        self.take_events()
+    }
+
+    /// Lets `ticks` ticks pass: gravity pulls the piece down, and locks it once it can't fall further. Inputs don't act.
+    pub fn advance(&mut self, ticks: u64) -> Vec<GameEvent> {
+        for _ in 0..ticks {
+            self.session.step();
+        }
+        self.take_events()
+    }
+
+    /// Every placement of the current piece reachable by rotating, sliding and hard dropping,
+    /// best first by the bot's judgement (in [`Placement::score`])
+    pub fn placements(&self) -> Vec<Placement> {
+        self.bot.placements(&self.session.game)
+    }
+
+    /// Takes the current piece to a placement and hard drops it.
+    /// The inputs are recorded, so [`Session::to_replay`] replays the game.
+    pub fn place(&mut self, placement: Placement) -> Vec<GameEvent> {
+        for action in bot::plan_for(&self.session.game, placement) {
+            self.session.apply(action);
+        }
+        self.take_events()
+    }
+
+    fn take_events(&mut self) -> Vec<GameEvent> {
+        self.session.game.drain_events().collect()
+    }
+}
+
+/// Lets `bot` play a game until it tops out, finishes the mode or has placed `max_pieces` pieces.
+/// Pieces are placed one after the other with no time passing between them.
+pub fn run_bot(config: GameConfig, mode: GameMode, bot: &Bot, max_pieces: u32) -> SimulationReport {
+    let mut session = Session::new(Game::with_config(config), mode);
+    let mut pieces = 0;
+
+    while !session.is_finished() && pieces < max_pieces {
+        let plan = bot.plan(&session.game);
+        if plan.is_empty() {
+            break;
+        }
+        for action in plan {
+            session.apply(action);
+        }
+        session.game.drain_events().for_each(drop);
+        pieces += 1;
+    }
+
+    SimulationReport::from_session(&session)
+}
+
+/// Plays a replay back at full speed, without waiting for its clock
+pub fn run_replay(replay: &Replay) -> SimulationReport {
+    let mut session = Session::from_replay(replay);
+    while !session.is_playback_finished(replay.end_tick) {
+        session.step();
+        session.game.drain_events().for_each(drop);
+    }
+    SimulationReport::from_session(&session)
+}
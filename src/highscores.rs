@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use learning1::session::{Session, TICKS_PER_SECOND};
+
+use crate::storage;
+
+// Personal bests, one entry per mode, kept in the user data directory.
+// Keys are strings (GameMode::id) so other kinds of runs can get their own bucket later.
+#[derive(Default, Serialize, Deserialize)]
+pub struct HighScores {
+    pub version: u32,
+    pub bests: BTreeMap<String, PersonalBest>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersonalBest {
+    pub score: u32,
+    // Fastest completion in ticks, for modes with a line goal
+    pub time_ticks: Option<u64>,
+}
+
+// What a finished game did to the personal best of its mode
+pub struct BestResult {
+    pub best: PersonalBest,
+    pub is_new_best: bool,
+}
+
+impl HighScores {
+    pub const VERSION: u32 = 1;
+
+    pub fn path() -> PathBuf {
+        storage::data_dir().join("highscores.json")
+    }
+
+    // A missing or unreadable file just means no bests yet
+    pub fn load() -> Self {
+        let Ok(json) = fs::read_to_string(Self::path()) else {
+            return HighScores::default();
+        };
+
+        match serde_json::from_str::<HighScores>(&json) {
+            Ok(scores) if scores.version == Self::VERSION => scores,
+            Ok(_) => HighScores::default(),
+            Err(e) => {
+                tracing::warn!("Could not parse {}: {}", Self::path().display(), e);
+                HighScores::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    // Compares a finished game to the best of its bucket and keeps it if it's better.
+    // Timed modes rank by time and only count completed runs, the others rank by score.
+    pub fn record(&mut self, key: &str, session: &Session) -> BestResult {
+        let best = self.bests.entry(key.to_string()).or_default();
+        let is_new_best = if session.is_timed() {
+            let faster = best.time_ticks.is_none_or(|time| session.tick < time);
+            if session.is_complete() && faster {
+                best.time_ticks = Some(session.tick);
+                best.score = session.game.score;
+            }
+            session.is_complete() && faster
+        } else {
+            let higher = session.game.score > best.score;
+            if higher {
+                best.score = session.game.score;
+            }
+            higher
+        };
+
+        BestResult { best: best.clone(), is_new_best }
+    }
+}
+
+// Loads the file, records the game and writes it back, the usual thing to do when a game ends
+pub fn record_game(key: &str, session: &Session) -> BestResult {
+    let mut scores = HighScores::load();
+    let result = scores.record(key, session);
+    if result.is_new_best
+        && let Err(e) = scores.save()
+    {
+        tracing::error!("Could not save high scores to {}: {}", HighScores::path().display(), e);
+    }
+    result
+}
+
+// m:ss.cc, the way speedrun timers show it
+pub fn format_time(ticks: u64) -> String {
+    let centis = ticks * 100 / TICKS_PER_SECOND;
+    format!("{}:{:02}.{:02}", centis / 6000, centis / 100 % 60, centis % 100)
+}
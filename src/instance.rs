@@ -0,0 +1,269 @@
+use std::time::Duration;
+
+use web_time::Instant;
+
+use learning1::action::Action;
+use learning1::events::GameEvent;
+use learning1::game::{is_valid_position, Game};
+use learning1::mode::GameMode;
+use learning1::replay::Replay;
+use learning1::session::{PlayMetrics, Session, TICKS_PER_SECOND};
+use learning1::snapshot::BoardSnapshot;
+
+use crate::autoplay::BotController;
+use crate::config::Handling;
+use crate::effects::Effects;
+
+const NANOS_PER_TICK: u64 = 1_000_000_000 / TICKS_PER_SECOND;
+
+// One board being played on screen: the player's, a CPU opponent's, the attract demo or a replay.
+// Wraps a game session with what the app needs to run it in real time: the mapping from wall-clock
+// time to simulation ticks, held soft drop and side moves, a queue of events for whoever draws
+// or sends them, and the visual effects drawn with the board.
+pub struct GameInstance {
+    pub session: Session,
+    last_update: Instant,
+    soft_drop_held: bool,
+    next_soft_drop_tick: u64,
+    // The side move whose key is down, repeating on its own after DAS (auto shift)
+    shift_held: Option<Action>,
+    next_shift_tick: u64,
+    events: Vec<GameEvent>,
+    // When set, the bot plays this game instead of the keyboard
+    bot: Option<BotController>,
+    effects: Effects,
+    // The simulation doesn't run past this tick (netplay pauses freeze both sides on the same tick)
+    freeze_tick: Option<u64>,
+    // Play rates shown with the board, refreshed once per second of play so they can be read
+    metrics: PlayMetrics,
+    metrics_second: u64,
+}
+
+impl GameInstance {
+    pub fn new(game: Game, mode: GameMode, now: Instant) -> Self {
+        Self::from_session(Session::new(game, mode), now)
+    }
+
+    pub fn from_replay(replay: &Replay, now: Instant) -> Self {
+        Self::from_session(Session::from_replay(replay), now)
+    }
+
+    pub fn from_session(session: Session, now: Instant) -> Self {
+        GameInstance {
+            session,
+            last_update: now,
+            soft_drop_held: false,
+            next_soft_drop_tick: 0,
+            shift_held: None,
+            next_shift_tick: 0,
+            events: Vec::new(),
+            bot: None,
+            effects: Effects::new(now),
+            freeze_tick: None,
+            metrics: PlayMetrics::default(),
+            metrics_second: 0,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.session.game
+    }
+
+    pub fn effects(&self) -> &Effects {
+        &self.effects
+    }
+
+    // The board to draw: the game's snapshot with the play rates
+    pub fn snapshot(&self) -> BoardSnapshot {
+        let mut snapshot = self.game().snapshot();
+        snapshot.metrics = Some(self.metrics);
+        snapshot
+    }
+
+    // Changes whenever the board would be drawn differently, None while its effects are
+    // animating and every frame is different (see Scene::frame_key)
+    pub fn frame_key(&self) -> Option<(u64, u64)> {
+        (!self.effects.is_animating()).then(|| (self.game().generation(), self.metrics_second))
+    }
+
+    pub fn set_bot(&mut self, bot: Option<BotController>) {
+        self.bot = bot;
+    }
+
+    pub fn is_bot_controlled(&self) -> bool {
+        self.bot.is_some()
+    }
+
+    pub fn bot(&self) -> Option<&BotController> {
+        self.bot.as_ref()
+    }
+
+    pub fn bot_mut(&mut self) -> Option<&mut BotController> {
+        self.bot.as_mut()
+    }
+
+    // Runs as many simulation ticks as wall-clock time has passed since the last update
+    pub fn update(&mut self, handling: &Handling, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_update).as_nanos() as u64;
+        let ticks = elapsed / NANOS_PER_TICK;
+        self.last_update += Duration::from_nanos(ticks * NANOS_PER_TICK);
+
+        for _ in 0..ticks {
+            if self.is_frozen() {
+                break;
+            }
+            self.session.step();
+            if self.soft_drop_held {
+                self.step_soft_drop(handling);
+            }
+            if let Some(action) = self.shift_held {
+                self.step_shift(handling, action);
+            }
+        }
+
+        // The controller needs the whole instance, so take it out while it plays
+        if let Some(mut bot) = self.bot.take() {
+            bot.update(self, now);
+            self.bot = Some(bot);
+        }
+
+        self.collect_events();
+        self.effects.update(&self.session.game.snapshot(), now);
+
+        let second = self.session.tick / TICKS_PER_SECOND;
+        if second != self.metrics_second {
+            self.metrics_second = second;
+            self.metrics = self.session.metrics();
+        }
+    }
+
+    // When the board next changes on its own, for the App to sleep until then: right away while
+    // effects animate, a key is held or the bot plays, otherwise at the next gravity step or play
+    // rate refresh. None once nothing moves anymore (finished, or frozen until the other side catches up).
+    pub fn next_update(&self, now: Instant) -> Option<Instant> {
+        if self.session.is_finished() || self.is_frozen() {
+            return None;
+        }
+        if self.effects.is_animating() || self.soft_drop_held || self.shift_held.is_some() || self.bot.is_some() {
+            return Some(now);
+        }
+        let next_second = (self.metrics_second + 1) * TICKS_PER_SECOND;
+        let ticks = self.session.next_gravity_tick().min(next_second).saturating_sub(self.session.tick);
+        Some((self.last_update + Duration::from_nanos(ticks * NANOS_PER_TICK)).max(now))
+    }
+
+    pub fn freeze_tick(&self) -> Option<u64> {
+        self.freeze_tick
+    }
+
+    pub fn set_freeze_tick(&mut self, tick: Option<u64>) {
+        self.freeze_tick = tick;
+    }
+
+    // True once the game reached its freeze tick
+    pub fn is_frozen(&self) -> bool {
+        self.freeze_tick.is_some_and(|tick| self.session.tick >= tick)
+    }
+
+    pub fn apply(&mut self, action: Action) {
+        tracing::trace!(?action, tick = self.session.tick, "input");
+        self.session.apply(action);
+        self.collect_events();
+    }
+
+    pub fn set_soft_drop_held(&mut self, handling: &Handling, held: bool) {
+        if held && !self.soft_drop_held {
+            // The first cell drops right away, the rest follow the soft drop interval
+            self.next_soft_drop_tick = self.session.tick;
+            self.step_soft_drop(handling);
+            self.collect_events();
+        }
+        self.soft_drop_held = held;
+    }
+
+    // A side move (MoveLeft or MoveRight) held down: it moves once right away, then repeats every
+    // ARR once it has been held for DAS. Pressing the other direction takes over, releasing stops it.
+    pub fn set_shift_held(&mut self, handling: &Handling, action: Action, held: bool) {
+        if held && self.shift_held != Some(action) {
+            self.apply(action);
+            self.shift_held = Some(action);
+            self.next_shift_tick = self.session.tick + handling.das_ticks();
+        } else if !held && self.shift_held == Some(action) {
+            self.shift_held = None;
+        }
+    }
+
+    // For when the key releases won't come to the game, like when a menu opens over it
+    pub fn release_held_keys(&mut self, handling: &Handling) {
+        self.set_soft_drop_held(handling, false);
+        self.shift_held = None;
+    }
+
+    // Restarts the clock, so time spent paused doesn't count
+    pub fn resume(&mut self, now: Instant) {
+        self.last_update = now;
+        self.effects.resume(now);
+    }
+
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    // Soft drop is gravity sped up by a factor while the key is held
+    fn step_soft_drop(&mut self, handling: &Handling) {
+        if handling.is_sonic_drop() {
+            // Only record a sonic drop when it actually moves the piece
+            let game = &self.session.game;
+            let on_floor = match (&game.current_piece, game.get_ghost_piece_position()) {
+                (Some(piece), Some(ghost)) => piece.y == ghost.y,
+                _ => true,
+            };
+            if !on_floor {
+                self.session.apply(Action::SonicDrop);
+            }
+        } else if self.session.tick >= self.next_soft_drop_tick {
+            self.session.apply(Action::SoftDrop);
+            let interval = (self.session.gravity_interval as f32 / handling.soft_drop_factor()) as u64;
+            self.next_soft_drop_tick = self.session.tick + interval.max(1);
+        }
+    }
+
+    // Auto shift moves the piece only when it can move, so a key held against a wall doesn't
+    // fill the replay with inputs that do nothing
+    fn step_shift(&mut self, handling: &Handling, action: Action) {
+        if self.session.tick < self.next_shift_tick {
+            return;
+        }
+        let dx = if action == Action::MoveLeft { -1 } else { 1 };
+        let can_move = |game: &Game| game.current_piece.as_ref()
+            .is_some_and(|piece| is_valid_position(&game.grid, &piece.cells, piece.x + dx, piece.y));
+        if handling.arr_ticks() == 0 {
+            // A finished session ignores inputs, the piece would never get there
+            while !self.session.is_finished() && can_move(self.game()) {
+                self.session.apply(action);
+            }
+        } else {
+            if can_move(self.game()) {
+                self.session.apply(action);
+            }
+            self.next_shift_tick = self.session.tick + handling.arr_ticks();
+        }
+    }
+
+    // Events are kept for the scene, logged, and the effects get to animate them
+    fn collect_events(&mut self) {
+        for event in self.session.game.drain_events() {
+            match &event {
+                GameEvent::PieceLocked { shape, cells } => tracing::debug!(?shape, ?cells, "piece locked"),
+                GameEvent::LinesCleared { count, points, .. } => tracing::debug!(count, points, "lines cleared"),
+                GameEvent::LevelUp { level } => tracing::debug!(level, "level up"),
+                GameEvent::Hold { held, released } => tracing::debug!(?held, ?released, "hold"),
+                GameEvent::TSpin { lines } => tracing::debug!(lines, "t-spin"),
+                GameEvent::TopOut => tracing::debug!("topped out"),
+                _ => {}
+            }
+            self.effects.push_event(&event);
+            self.events.push(event);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use crate::vertex_data::{LOGICAL_HEIGHT, LOGICAL_WIDTH};
+
+// Everything on screen is positioned in a logical space of LOGICAL_WIDTH x LOGICAL_HEIGHT units
+// (one unit = one grid cell), centered in the window and scaled to fit its height.
+// This converts between that space and window pixels, for text placement and mouse hit-testing,
+// and gives the renderer the matching projection for quads.
+// Pixels are physical ones, `scale_factor` (the display's DPI scaling) converts them to logical pixels.
+#[derive(Clone, Copy, Debug)]
+pub struct ScreenLayout {
+    width: f32,
+    height: f32,
+    scale_factor: f32,
+}
+
+// An axis aligned rectangle in logical units, (x, y) is the top left corner
+#[derive(Clone, Copy, Debug)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Rect {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.w && y >= self.y && y < self.y + self.h
+    }
+}
+
+impl ScreenLayout {
+    pub fn new(window_width: u32, window_height: u32, scale_factor: f64) -> Self {
+        ScreenLayout {
+            width: window_width.max(1) as f32,
+            height: window_height.max(1) as f32,
+            scale_factor: scale_factor.max(0.1) as f32,
+        }
+    }
+
+    // Size of one logical unit in pixels, and the pixel position of the logical origin
+    fn unit_and_origin(self) -> (f32, f32, f32) {
+        // 1.9 NDC units of height (a little margin), centered
+        let unit = 1.9 / LOGICAL_HEIGHT * 0.5 * self.height;
+        // On a whole pixel, so cell edges land on the same pixels at any size and scale factor
+        let origin_x = ((self.width - unit * LOGICAL_WIDTH) / 2.0).round();
+        let origin_y = ((self.height - unit * LOGICAL_HEIGHT) / 2.0).round();
+        (unit, origin_x, origin_y)
+    }
+
+    // Text sizes are given for an 800 pixel tall window, this scales them with the rest of the layout.
+    // In logical pixels: the text renderer applies the scale factor when rasterizing the glyphs.
+    pub fn text_scale(self) -> f32 {
+        let (unit, _, _) = self.unit_and_origin();
+        let (reference_unit, _, _) = ScreenLayout::new(800, 800, 1.0).unit_and_origin();
+        unit / reference_unit / self.scale_factor
+    }
+
+    pub fn scale_factor(self) -> f32 {
+        self.scale_factor
+    }
+
+    pub fn to_pixels(self, x: f32, y: f32) -> (f32, f32) {
+        let (unit, origin_x, origin_y) = self.unit_and_origin();
+        (origin_x + x * unit, origin_y + y * unit)
+    }
+
+    pub fn to_logical(self, x: f32, y: f32) -> (f32, f32) {
+        let (unit, origin_x, origin_y) = self.unit_and_origin();
+        ((x - origin_x) / unit, (y - origin_y) / unit)
+    }
+
+    // Orthographic projection from logical units to normalized device coordinates, column major.
+    // The same fit as to_pixels, with y flipped since NDC point up.
+    pub fn projection(self) -> [[f32; 4]; 4] {
+        let (unit, origin_x, origin_y) = self.unit_and_origin();
+        let scale_x = 2.0 * unit / self.width;
+        let scale_y = -2.0 * unit / self.height;
+        [
+            [scale_x, 0.0, 0.0, 0.0],
+            [0.0, scale_y, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [2.0 * origin_x / self.width - 1.0, 1.0 - 2.0 * origin_y / self.height, 0.0, 1.0],
+        ]
+    }
+}
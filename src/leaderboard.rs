@@ -0,0 +1,138 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::LeaderboardSettings;
+
+// A slow server gives up rather than leaving the results screen waiting forever
+#[cfg(not(target_arch = "wasm32"))]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+#[cfg(not(target_arch = "wasm32"))]
+const TOP_COUNT: usize = 10;
+
+// What is sent for a finished game
+#[derive(Debug, Clone, Serialize)]
+pub struct Submission {
+    pub name: String,
+    pub score: u32,
+    pub mode: String,
+    pub seed: u64,
+}
+
+// One line of the online top 10, best first
+#[derive(Debug, Clone, Deserialize)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub score: u32,
+}
+
+// Talks to the leaderboard server on a background thread, so the window stays responsive meanwhile.
+// The server takes POST <endpoint>/scores with a Submission as JSON, and answers
+// GET <endpoint>/scores?mode=<mode>&limit=10 with the best entries for the mode.
+pub struct LeaderboardRequest {
+    result: Receiver<Result<Vec<LeaderboardEntry>, String>>,
+}
+
+impl LeaderboardRequest {
+    // Sends the score, then fetches the top 10 it may have entered
+    pub fn submit(settings: &LeaderboardSettings, submission: Submission) -> Self {
+        Self::start(Job::Submit { endpoint: settings.endpoint.clone(), submission })
+    }
+
+    pub fn fetch(settings: &LeaderboardSettings, mode: &str) -> Self {
+        Self::start(Job::Fetch { endpoint: settings.endpoint.clone(), mode: mode.to_string() })
+    }
+
+    fn start(job: Job) -> Self {
+        let (sender, result) = mpsc::channel();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        thread::spawn(move || {
+            // The screen may be gone already, nobody to tell then
+            let _ = sender.send(job.run().map_err(|e| e.to_string()));
+        });
+        // No threads or blocking sockets in the browser
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = job;
+            let _ = sender.send(Err("not available in the browser".to_string()));
+        }
+
+        LeaderboardRequest { result }
+    }
+
+    // The top 10 once the server answered, None while waiting
+    pub fn poll(&self) -> Option<Result<Vec<LeaderboardEntry>, String>> {
+        match self.result.try_recv() {
+            Ok(entries) => Some(entries),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("request failed".to_string())),
+        }
+    }
+}
+
+// What a request does on its thread
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+enum Job {
+    Submit { endpoint: String, submission: Submission },
+    Fetch { endpoint: String, mode: String },
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Job {
+    fn run(self) -> Result<Vec<LeaderboardEntry>, ureq::Error> {
+        let agent: ureq::Agent = ureq::Agent::config_builder().timeout_global(Some(REQUEST_TIMEOUT)).build().into();
+        match self {
+            Job::Submit { endpoint, submission } => {
+                agent.post(format!("{}/scores", endpoint)).send_json(&submission)?;
+                fetch_top(&agent, &endpoint, &submission.mode)
+            }
+            Job::Fetch { endpoint, mode } => fetch_top(&agent, &endpoint, &mode),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn fetch_top(agent: &ureq::Agent, endpoint: &str, mode: &str) -> Result<Vec<LeaderboardEntry>, ureq::Error> {
+    let mut entries: Vec<LeaderboardEntry> = agent
+        .get(format!("{}/scores", endpoint))
+        .query("mode", mode)
+        .query("limit", TOP_COUNT.to_string())
+        .call()?
+        .body_mut()
+        .read_json()?;
+    entries.truncate(TOP_COUNT);
+    Ok(entries)
+}
+
+// Where a request stands, for the screens showing it
+pub enum LeaderboardState {
+    Loading(LeaderboardRequest),
+    Loaded(Vec<LeaderboardEntry>),
+    Failed,
+}
+
+impl LeaderboardState {
+    pub fn is_loading(&self) -> bool {
+        matches!(self, LeaderboardState::Loading(_))
+    }
+
+    // Picks up the answer once it arrived
+    pub fn poll(&mut self) {
+        if let LeaderboardState::Loading(request) = self
+            && let Some(result) = request.poll()
+        {
+            *self = match result {
+                Ok(entries) => LeaderboardState::Loaded(entries),
+                Err(e) => {
+                    tracing::warn!("Leaderboard request failed: {}", e);
+                    LeaderboardState::Failed
+                }
+            };
+        }
+    }
+}
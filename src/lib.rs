@@ -0,0 +1,39 @@
+//! The Tetris game core, independent of any window or renderer.
+//!
+//! Frontends, bots and tools should depend on the [`prelude`], which is the
+//! stable part of the API: items in it only change in breaking ways with a
+//! major version bump, and the types that are expected to grow are marked
+//! `#[non_exhaustive]`. The other public modules expose implementation details
+//! used by the bundled game and may change between minor versions.
+
+pub mod action;
+pub mod bot;
+pub mod challenge;
+pub mod events;
+pub mod finesse;
+pub mod game;
+pub mod headless;
+pub mod mission;
+pub mod mode;
+pub mod piece_set;
+pub mod puzzle;
+pub mod replay;
+pub mod rng;
+pub mod rules;
+pub mod session;
+pub mod snapshot;
+pub mod tetromino;
+
+pub mod prelude {
+    //! `use learning1::prelude::*;` brings in everything needed to run and observe a game.
+    pub use crate::action::Action;
+    pub use crate::events::{ClearKind, GameEvent, TopOutKind};
+    pub use crate::game::{Game, GameConfig, HEIGHT, HIDDEN_ROWS, VISIBLE_HEIGHT, WIDTH};
+    pub use crate::mode::GameMode;
+    pub use crate::piece_set::PieceSet;
+    pub use crate::replay::Replay;
+    pub use crate::rules::RuleSet;
+    pub use crate::session::{Session, TICKS_PER_SECOND};
+    pub use crate::snapshot::BoardSnapshot;
+    pub use crate::tetromino::{Point, TetrominoShape};
+}
@@ -1,63 +1,274 @@
 #![windows_subsystem = "windows"]
 
-mod game;
-mod tetromino;
-// mod renderer; // Keep for reference, but unused
+mod autoplay;
+mod background;
+#[cfg(not(target_arch = "wasm32"))]
+mod bench;
+mod config;
+mod daily;
+mod effects;
+mod highscores;
+mod instance;
+mod layout;
+mod leaderboard;
+mod net;
+#[cfg(not(target_arch = "wasm32"))]
+mod parity;
+mod particles;
+mod recorder;
+mod save;
+mod scenes;
+mod stats_export;
+mod storage;
+mod texture;
+mod theme;
+// The terminal frontend, desktop only
+#[cfg(not(target_arch = "wasm32"))]
+mod renderer;
+mod tui;
+mod ui_layer;
 mod graphic_context;
 mod vertex_data;
 
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
+use web_time::Instant;
 use winit::application::ApplicationHandler;
-use winit::event::{ElementState, WindowEvent};
-use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::keyboard::{KeyCode, PhysicalKey};
-use winit::window::{Window, WindowAttributes};
+use winit::event::{ElementState, MouseButton, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy};
+use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
+use winit::window::{Fullscreen, Window, WindowAttributes};
 
-use game::Game;
-use graphic_context::GraphicContext;
+use config::Config;
+use graphic_context::{GraphicContext, GraphicsError, TextEntry};
+use layout::ScreenLayout;
+use recorder::Recorder;
+use scenes::title::TitleScene;
+use scenes::{KeyInput, MouseInput, Scene, SceneContext, Transition};
 
 struct App {
     window: Option<Arc<Window>>,
-    game: Game,
     graphics: Option<GraphicContext>,
-    last_gravity_update: Instant,
-    gravity_interval: Duration,
+    // Why the window or the GPU couldn't be set up. The loop then stops, and main falls back to the terminal.
+    graphics_error: Option<GraphicsError>,
+    // The GPU is set up asynchronously in the browser, the context comes back through here
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    proxy: EventLoopProxy<Result<GraphicContext, GraphicsError>>,
+    // Stack of scenes, the last one is active
+    scenes: Vec<Box<dyn Scene>>,
+    ctx: SceneContext,
+    // Last known mouse position in window pixels
+    cursor_position: (f32, f32),
+    // Keyboard modifiers currently held, for Alt+Enter
+    modifiers: ModifiersState,
+    // The last frame's text and Scene::frame_key, its quads are still in the GPU buffers.
+    // Reused until the key changes or `frame_dirty` is set.
+    frame_text: Vec<TextEntry>,
+    frame_key: Option<u64>,
+    frame_dirty: bool,
+    // Keeps the last seconds on screen for clips, when recording is enabled
+    recorder: Option<Recorder>,
+    // Whether the window has the keyboard focus, the loop sleeps while it doesn't
+    focused: bool,
+    // When the last frame was drawn, for the FPS limit
+    last_frame: Instant,
 }
 
-impl Default for App {
-    fn default() -> Self {
+impl App {
+    fn new(proxy: EventLoopProxy<Result<GraphicContext, GraphicsError>>, ctx: SceneContext, first_scene: Box<dyn Scene>) -> Self {
+        // Frames are kept and encoded on background threads, which the browser doesn't have
+        let recorder = (ctx.config.recording.enabled && !cfg!(target_arch = "wasm32"))
+            .then(|| Recorder::new(&ctx.config.recording, Instant::now()));
+
         Self {
             window: None,
-            game: Game::new(),
             graphics: None,
-            last_gravity_update: Instant::now(),
-            gravity_interval: Duration::from_millis(500),
+            graphics_error: None,
+            proxy,
+            scenes: vec![first_scene],
+            ctx,
+            cursor_position: (0.0, 0.0),
+            modifiers: ModifiersState::empty(),
+            frame_text: Vec::new(),
+            frame_key: None,
+            frame_dirty: true,
+            recorder,
+            focused: true,
+            last_frame: Instant::now(),
+        }
+    }
+
+    fn set_graphics(&mut self, mut graphics: GraphicContext) {
+        graphics.set_theme(self.ctx.theme());
+        if self.recorder.is_some() && !graphics.can_capture {
+            tracing::warn!("This display can't be read back, recording is off");
+            self.recorder = None;
+        }
+        self.graphics = Some(graphics);
+        self.frame_dirty = true;
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    fn graphics_ready(&mut self, event_loop: &ActiveEventLoop, graphics: Result<GraphicContext, GraphicsError>) {
+        match graphics {
+            Ok(graphics) => self.set_graphics(graphics),
+            Err(e) => {
+                tracing::error!("Graphics are not available: {}", e);
+                // The page has no terminal to fall back to, the message goes where the game would have been
+                #[cfg(target_arch = "wasm32")]
+                show_in_page(&format!("The game can't start: {}.", e));
+                self.graphics_error = Some(e);
+                event_loop.exit();
+            }
+        }
+    }
+
+    // Borderless fullscreen on the current monitor, or back to a window.
+    // The window then gets a Resized event, which reconfigures the surface and the layout.
+    fn toggle_fullscreen(&self) {
+        if let Some(window) = &self.window {
+            let fullscreen = match window.fullscreen() {
+                Some(_) => None,
+                None => Some(Fullscreen::Borderless(None)),
+            };
+            window.set_fullscreen(fullscreen);
+        }
+    }
+
+    // Logs the active scene's board as text, one line per row with '.' for empty cells
+    fn dump_board(&self) {
+        let Some(snapshot) = self.scenes.last().and_then(|scene| scene.debug_board()) else {
+            tracing::info!("No board to dump on this screen");
+            return;
+        };
+        tracing::info!(
+            score = snapshot.score,
+            lines = snapshot.lines_cleared,
+            level = snapshot.level,
+            "Board dump:\n{}",
+            tui::board_lines(&snapshot).join("\n")
+        );
+    }
+
+    // Hands the last captured frame to the recorder, and asks for the next one when it's time.
+    // Saves clips that are due and reports the ones that were written.
+    fn update_recorder(&mut self, now: Instant) {
+        let (Some(recorder), Some(graphics)) = (&mut self.recorder, &mut self.graphics) else {
+            return;
+        };
+        if std::mem::take(&mut self.ctx.tetris_scored) {
+            recorder.tetris_scored(now);
+        }
+        if let Some(frame) = graphics.take_capture() {
+            recorder.push_frame(frame, now);
+        }
+        if recorder.wants_frame(now) {
+            graphics.request_capture();
+        }
+
+        recorder.update(now);
+        while let Some(result) = recorder.poll() {
+            match result {
+                Ok(path) => tracing::info!("Saved clip to {}", path.display()),
+                Err(e) => tracing::error!("Could not save clip: {}", e),
+            }
+        }
+    }
+
+    fn send_mouse(&mut self, event_loop: &ActiveEventLoop, clicked: bool) {
+        self.frame_dirty = true;
+        let Some(graphics) = &self.graphics else {
+            return;
+        };
+
+        let layout = ScreenLayout::new(graphics.size.width, graphics.size.height, graphics.scale_factor);
+        let (x, y) = layout.to_logical(self.cursor_position.0, self.cursor_position.1);
+        let input = if clicked { MouseInput::Clicked { x, y } } else { MouseInput::Moved { x, y } };
+
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.handle_mouse(&mut self.ctx, input),
+            None => Transition::None,
+        };
+        self.apply_transition(transition, event_loop);
+    }
+
+    fn apply_transition(&mut self, transition: Transition, event_loop: &ActiveEventLoop) {
+        if !matches!(transition, Transition::None) {
+            self.frame_dirty = true;
+        }
+        match transition {
+            Transition::None => return,
+            Transition::Push(scene) => self.scenes.push(scene),
+            Transition::Pop => {
+                self.scenes.pop();
+            }
+            Transition::Replace(scene) => {
+                self.scenes.pop();
+                self.scenes.push(scene);
+            }
+            Transition::Reset(scene) => {
+                self.scenes.clear();
+                self.scenes.push(scene);
+            }
+            Transition::Quit => {
+                event_loop.exit();
+                return;
+            }
+        }
+
+        match self.scenes.last_mut() {
+            Some(scene) => scene.on_resume(Instant::now()),
+            None => event_loop.exit(),
         }
     }
 }
 
-impl ApplicationHandler for App {
+impl ApplicationHandler<Result<GraphicContext, GraphicsError>> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         if self.window.is_none() {
             let window_attributes = WindowAttributes::default()
                 .with_title("Rust Tetris (WGPU)")
-                .with_inner_size(winit::dpi::LogicalSize::new(800.0, 800.0));
-            
-            let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
+                .with_inner_size(winit::dpi::LogicalSize::new(800.0, 800.0))
+                .with_fullscreen(self.ctx.config.display.fullscreen.then_some(Fullscreen::Borderless(None)));
+
+            // In the browser the window is a canvas, added to the page
+            #[cfg(target_arch = "wasm32")]
+            let window_attributes = {
+                use winit::platform::web::WindowAttributesExtWebSys;
+                window_attributes.with_append(true)
+            };
+
+            let window = match event_loop.create_window(window_attributes) {
+                Ok(window) => Arc::new(window),
+                Err(e) => return self.graphics_ready(event_loop, Err(GraphicsError::Window(e))),
+            };
             self.window = Some(window.clone());
 
-            let mut graphics = pollster::block_on(GraphicContext::new(window.clone()));
-            
-            // Initial mesh build
-            let (vertices, _) = vertex_data::build_mesh(&self.game, graphics.size.width, graphics.size.height);
-            graphics.update_buffers(&vertices);
-            
-            self.graphics = Some(graphics);
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let graphics = pollster::block_on(GraphicContext::new(window, &self.ctx.config.display));
+                self.graphics_ready(event_loop, graphics);
+            }
+            // The browser can't block on the GPU: the context arrives as a user event
+            #[cfg(target_arch = "wasm32")]
+            {
+                let proxy = self.proxy.clone();
+                let display = self.ctx.config.display.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let graphics = GraphicContext::new(window, &display).await;
+                    let _ = proxy.send_event(graphics);
+                });
+            }
         }
     }
 
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, graphics: Result<GraphicContext, GraphicsError>) {
+        self.graphics_ready(event_loop, graphics);
+    }
+
     fn window_event(
         &mut self,
         event_loop: &ActiveEventLoop,
@@ -72,75 +283,378 @@ impl ApplicationHandler for App {
                 if let Some(graphics) = &mut self.graphics {
                     graphics.resize(physical_size);
                 }
+                if let Some(window) = &self.window {
+                    window.request_redraw();
+                }
             },
-            WindowEvent::RedrawRequested => {
+            // Moved to a display with different DPI scaling (or the scaling changed).
+            // winit suggests a size that keeps the window's logical size, and sends a resize for it.
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
                 if let Some(graphics) = &mut self.graphics {
-                    
-                    // Game Loop Logic (Update)
-                    let now = Instant::now();
-                    if now.duration_since(self.last_gravity_update) > self.gravity_interval {
-                        self.game.update();
-                        self.last_gravity_update = now;
-                        
-                        if self.game.is_game_over {
-                             // Handle game over? Reset?
-                             // For now, auto-restart
-                             self.game = Game::new();
-                        }
+                    graphics.set_scale_factor(scale_factor);
+                }
+            },
+            WindowEvent::RedrawRequested => {
+                // Game Loop Logic (Update)
+                let now = Instant::now();
+                self.last_frame = now;
+                let transition = match self.scenes.last_mut() {
+                    Some(scene) => scene.update(&mut self.ctx, now),
+                    None => Transition::None,
+                };
+                self.apply_transition(transition, event_loop);
+
+                if let (Some(graphics), Some(scene)) = (&mut self.graphics, self.scenes.last()) {
+                    // F2 or the settings screen switched themes, which may come with other block tiles
+                    if std::mem::take(&mut self.ctx.theme_changed) {
+                        graphics.set_theme(self.ctx.theme());
+                        self.frame_dirty = true;
                     }
 
-                    // Rebuild Mesh
-                    let (vertices, text) = vertex_data::build_mesh(&self.game, graphics.size.width, graphics.size.height);
-                    graphics.update_buffers(&vertices);
+                    // Rebuild the quads only when something changed. A resize doesn't count:
+                    // the projection and the text layout follow the window on their own.
+                    let key = scene.frame_key(&self.ctx);
+                    if self.frame_dirty || key.is_none() || key != self.frame_key {
+                        let (instances, text) = scene.render(&self.ctx);
+                        graphics.update_buffers(&instances);
+                        self.frame_text = text;
+                        self.frame_key = key;
+                        self.frame_dirty = false;
+                    }
+
+                    // The background animates whether or not the quads changed
+                    let aspect = graphics.size.width as f32 / graphics.size.height.max(1) as f32;
+                    let ctx = &self.ctx;
+                    graphics.set_background(ctx.config.display.animated_background.then(|| ctx.background.uniform(ctx.theme(), aspect, now)));
 
                     // Render
-                    match graphics.render(&text) {
+                    match graphics.render(&self.frame_text) {
                         Ok(_) => {}
-                        Err(wgpu::SurfaceError::Lost) => graphics.resize(graphics.size),
-                        Err(wgpu::SurfaceError::OutOfMemory) => event_loop.exit(),
-                        Err(e) => eprintln!("{:?}", e),
+                        Err(GraphicsError::Frame(wgpu::SurfaceError::Lost)) => {
+                            tracing::warn!("Surface lost, reconfiguring it");
+                            graphics.resize(graphics.size);
+                        }
+                        Err(GraphicsError::Frame(wgpu::SurfaceError::OutOfMemory)) => {
+                            tracing::error!("Out of GPU memory, quitting");
+                            event_loop.exit();
+                        }
+                        // Timeouts, outdated surfaces and a full text atlas go away on their own, the frame is skipped
+                        Err(e) => tracing::warn!("Frame skipped: {}", e),
                     }
                 }
-                
-                // Request next frame
-                if let Some(window) = &self.window {
-                    window.request_redraw();
-                }
+                self.update_recorder(now);
             },
             WindowEvent::KeyboardInput {
                 event: key_event,
                 ..
             } => {
-                if key_event.state == ElementState::Pressed {
-                    if let PhysicalKey::Code(keycode) = key_event.physical_key {
-                        let is_repeat = key_event.repeat;
-                        match keycode {
-                            KeyCode::ArrowLeft => self.game.move_left(),
-                            KeyCode::ArrowRight => self.game.move_right(),
-                            KeyCode::ArrowDown => self.game.soft_drop(),
-                            KeyCode::ArrowUp if !is_repeat => self.game.rotate(),
-                            KeyCode::Space if !is_repeat => self.game.hard_drop(),
-                            KeyCode::Escape => event_loop.exit(),
-                            _ => {}
+                if let PhysicalKey::Code(code) = key_event.physical_key {
+                    let shortcut = key_event.state == ElementState::Pressed && !key_event.repeat;
+                    // Themes can be switched from anywhere
+                    if code == KeyCode::F2 && shortcut {
+                        self.ctx.next_theme();
+                        self.frame_dirty = true;
+                        return;
+                    }
+                    // So can fullscreen. Alt+Enter doesn't reach the scene, where Enter would select.
+                    let alt_enter = self.modifiers.alt_key() && matches!(code, KeyCode::Enter | KeyCode::NumpadEnter);
+                    if code == KeyCode::F11 || alt_enter {
+                        if shortcut {
+                            self.toggle_fullscreen();
+                        }
+                        return;
+                    }
+                    // Saves the last seconds of play as a clip
+                    if code == KeyCode::F9 {
+                        if shortcut {
+                            match &self.recorder {
+                                Some(recorder) => recorder.save(),
+                                None => tracing::info!("Recording is off, see recording.enabled in config.toml"),
+                            }
                         }
-                        // Request immediate redraw on input for responsiveness
-                        if let Some(window) = &self.window {
-                            window.request_redraw();
+                        return;
+                    }
+                    // Writes the board being played to the log, to attach to bug reports
+                    if code == KeyCode::F3 {
+                        if shortcut {
+                            self.dump_board();
                         }
+                        return;
+                    }
+
+                    self.frame_dirty = true;
+                    tracing::trace!(?code, pressed = key_event.state == ElementState::Pressed, repeat = key_event.repeat, "key");
+                    let input = KeyInput {
+                        code,
+                        pressed: key_event.state == ElementState::Pressed,
+                        repeat: key_event.repeat,
+                    };
+
+                    let transition = match self.scenes.last_mut() {
+                        Some(scene) => scene.handle_key(&mut self.ctx, input),
+                        None => Transition::None,
+                    };
+                    self.apply_transition(transition, event_loop);
+
+                    // Request immediate redraw on input for responsiveness
+                    if let Some(window) = &self.window {
+                        window.request_redraw();
                     }
                 }
             },
+            WindowEvent::Focused(focused) => {
+                self.focused = focused;
+                let transition = match self.scenes.last_mut() {
+                    Some(scene) => scene.on_focus_changed(&mut self.ctx, focused),
+                    None => Transition::None,
+                };
+                self.apply_transition(transition, event_loop);
+            },
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = (position.x as f32, position.y as f32);
+                self.send_mouse(event_loop, false);
+            },
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } => {
+                self.send_mouse(event_loop, true);
+            },
             _ => (),
         }
     }
+
+    // Schedules the next frame once the events are handled: right away when something changed or
+    // keeps moving, at the scene's next deadline (e.g. gravity) when nothing happens before it, and
+    // only on input on menus, pause screens and while the window is in the background
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        // Until the GPU is set up there's nothing to draw, set_graphics asks for the first frame
+        let (Some(window), Some(_), Some(scene)) = (&self.window, &self.graphics, self.scenes.last()) else {
+            return;
+        };
+
+        let now = Instant::now();
+        let wake = if self.frame_dirty {
+            Some(now)
+        } else if !self.focused && !scene.runs_unfocused() {
+            None
+        } else {
+            // The starfield moves on every frame, but stands still along with a scene waiting for input
+            let wake = scene.next_update(&self.ctx, now);
+            let wake = if self.ctx.config.display.animated_background { wake.map(|_| now) } else { wake };
+            match &self.recorder {
+                Some(recorder) => wake.map(|wake| wake.min(recorder.next_deadline())),
+                None => wake,
+            }
+        };
+        let fps_limit = self.ctx.config.display.fps_limit;
+        let wake = wake.map(|wake| match fps_limit {
+            0 => wake,
+            fps => wake.max(self.last_frame + Duration::from_secs(1) / fps),
+        });
+
+        match wake {
+            Some(wake) if wake <= now => {
+                window.request_redraw();
+                event_loop.set_control_flow(ControlFlow::Wait);
+            }
+            Some(wake) => event_loop.set_control_flow(ControlFlow::WaitUntil(wake)),
+            None => event_loop.set_control_flow(ControlFlow::Wait),
+        }
+    }
 }
 
+// Launch options. Without any, the window opens on the title screen.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(clap::Parser)]
+#[command(version, about = "Tetris with wgpu")]
+struct Args {
+    /// Start a game of this mode instead of showing the title screen (endless, sprint40, marathon, ...)
+    #[arg(long, value_parser = parse_mode)]
+    mode: Option<learning1::mode::GameMode>,
+    /// Seed for the piece sequence, used by every game started
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Level to start from, for modes with levels (marathon when no mode is given)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=learning1::game::MAX_LEVEL as i64))]
+    level: Option<u32>,
+    /// Read the settings from this file instead of config.toml
+    #[arg(long, value_name = "PATH")]
+    config: Option<std::path::PathBuf>,
+    /// Let the bot play every game
+    #[arg(long)]
+    autoplay: bool,
+    /// Play in the terminal instead of opening a window
+    #[arg(long, conflicts_with_all = ["headless", "replay"])]
+    tui: bool,
+    /// Play REPLAY, or else a bot game, as fast as possible and print the result as JSON
+    #[arg(long, value_name = "REPLAY", conflicts_with = "replay")]
+    headless: Option<Option<std::path::PathBuf>>,
+    /// Watch a recorded game
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["mode", "level"])]
+    replay: Option<std::path::PathBuf>,
+    /// Override display.present_mode from the config: fifo, mailbox or immediate
+    #[arg(long)]
+    present_mode: Option<config::PresentMode>,
+    /// Let the bot play GAMES games without a window and print speed and strength numbers
+    #[arg(long, value_name = "GAMES", num_args = 0..=1, default_missing_value = "100")]
+    bench_bot: Option<u32>,
+    /// Check that the frontends agree with the simulation on every tick of REPLAY (or of a bot game)
+    #[arg(long, value_name = "REPLAY")]
+    parity_check: Option<Option<std::path::PathBuf>>,
+    /// What to log to stderr: a level (warn, info, debug, trace) or filters like learning1=debug.
+    /// Defaults to RUST_LOG, or info.
+    #[arg(long, value_name = "FILTER")]
+    log: Option<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_mode(id: &str) -> Result<learning1::mode::GameMode, String> {
+    use learning1::mode::GameMode;
+
+    GameMode::ALL.into_iter().find(|mode| mode.id() == id).ok_or_else(|| {
+        let ids: Vec<_> = GameMode::ALL.iter().map(|mode| mode.id()).collect();
+        format!("unknown mode {} (expected one of {})", id, ids.join(", "))
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let event_loop = EventLoop::new()?;
-    event_loop.set_control_flow(ControlFlow::Poll); // Poll allows continuous updates for game loop
+    use clap::Parser;
+
+    use learning1::bot::Bot;
+    use learning1::game::GameConfig;
+    use learning1::headless;
+    use learning1::mode::GameMode;
+    use learning1::replay::Replay;
+
+    use scenes::playing::PlayingScene;
+    use scenes::replay::ReplayScene;
+
+    let args = Args::parse();
+
+    // Logs go to stderr, stdout is kept for the reports of --headless and --bench-bot
+    let filter = match &args.log {
+        Some(filter) => tracing_subscriber::EnvFilter::try_new(filter).map_err(|e| format!("invalid --log filter {}: {}", filter, e))?,
+        None => tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+    };
+    tracing_subscriber::fmt().with_env_filter(filter).with_writer(std::io::stderr).init();
 
-    let mut app = App::default();
+    if let Some(games) = args.bench_bot {
+        bench::run_bot_benchmark(games);
+        return Ok(());
+    }
+
+    // Without a replay, the bot records a game to check
+    if let Some(path) = args.parity_check {
+        let replay = match path {
+            Some(path) => Replay::load(&path)?,
+            None => parity::bot_script(0),
+        };
+        if !parity::run_parity_check(&replay) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // A level alone means marathon, the only mode it applies to
+    let mode = match (args.mode, args.level) {
+        (None, Some(_)) => Some(GameMode::Marathon),
+        (mode, _) => mode,
+    };
+    if let (Some(mode), Some(_)) = (mode, args.level)
+        && mode != GameMode::Marathon
+    {
+        return Err(format!("--level doesn't apply to {}", mode.id()).into());
+    }
+    // The terminal and headless runs play a single board with nothing but the keyboard (or the bot)
+    let solo_mode = || match mode.unwrap_or(GameMode::Endless) {
+        mode if renderer::TUI_MODES.contains(&mode) => Ok(mode),
+        mode => Err(format!("{} can't be played without a window", mode.id())),
+    };
+
+    if let Some(path) = args.headless {
+        let report = match path {
+            Some(path) => headless::run_replay(&Replay::load(&path)?),
+            None => {
+                let mode = solo_mode()?;
+                let config = GameConfig::default()
+                    .with_rules(mode.rules())
+                    .with_seed(args.seed.unwrap_or(0))
+                    .with_start_level(args.level.unwrap_or(1));
+                headless::run_bot(config, mode, &Bot::new(), bench::MAX_PIECES_PER_GAME)
+            }
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let config = match &args.config {
+        Some(path) if !path.exists() => return Err(format!("config file {} not found", path.display()).into()),
+        Some(path) => Config::load_from(path),
+        None => Config::load(),
+    };
+    let mut ctx = SceneContext::new(config, args.seed, args.autoplay);
+    if let Some(path) = &args.config {
+        ctx.config_path = path.clone();
+    }
+    if let Some(present_mode) = args.present_mode {
+        ctx.config.display.present_mode = present_mode;
+    }
+
+    if args.tui {
+        renderer::run(&ctx, solo_mode()?, args.level)?;
+        return Ok(());
+    }
+
+    let first_scene: Box<dyn Scene> = match (&args.replay, mode, args.level) {
+        (Some(path), _, _) => Box::new(ReplayScene::new(&Replay::load(path)?)),
+        (None, Some(mode), Some(level)) => Box::new(PlayingScene::with_start_level(&ctx, mode, level)),
+        (None, Some(mode), None) => scenes::start_game(&ctx, mode),
+        (None, None, _) => Box::new(TitleScene::new()),
+    };
+
+    let event_loop = EventLoop::with_user_event().build()?;
+    // App::about_to_wait decides when the next frame is due, the loop sleeps until then
+    event_loop.set_control_flow(ControlFlow::Wait);
+
+    let mut app = App::new(event_loop.create_proxy(), ctx, first_scene);
     event_loop.run_app(&mut app)?;
 
+    // Without a GPU the terminal can still play a single board, the window's other screens can't
+    if let Some(e) = app.graphics_error {
+        let mode = match (&args.replay, solo_mode()) {
+            (None, Ok(mode)) => mode,
+            _ => return Err(format!("{}. Try --tui to play in the terminal", e).into()),
+        };
+        tracing::warn!("Playing in the terminal instead");
+        renderer::run(&app.ctx, mode, args.level)?;
+    }
+
     Ok(())
 }
+
+// In the browser there is no command line: the title screen with the default config.
+// Saves, scores and replays can't be written, which is reported and otherwise ignored.
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use winit::platform::web::EventLoopExtWebSys;
+
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    tracing_wasm::set_as_global_default();
+    let event_loop = EventLoop::with_user_event().build().expect("could not create the event loop");
+    event_loop.set_control_flow(ControlFlow::Wait);
+
+    let ctx = SceneContext::new(Config::load(), None, false);
+    let app = App::new(event_loop.create_proxy(), ctx, Box::new(TitleScene::new()));
+    event_loop.spawn_app(app);
+}
+
+// Adds a line of text at the end of the page
+#[cfg(target_arch = "wasm32")]
+fn show_in_page(message: &str) {
+    let body = web_sys::window().and_then(|window| window.document()).and_then(|document| document.query_selector("body").ok().flatten());
+    if let Some(body) = body {
+        let _ = body.insert_adjacent_text("beforeend", message);
+    }
+}
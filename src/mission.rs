@@ -0,0 +1,201 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::GameEvent;
+use crate::game::{Game, GameConfig};
+use crate::piece_set::PieceSet;
+use crate::session::TICKS_PER_SECOND;
+use crate::tetromino::TetrominoShape;
+
+/// A mission: goals to reach one after the other, in a single game with the standard rules.
+///
+/// Missions are JSON files, see [`Mission::load`]:
+///
+/// ```json
+/// {
+///   "name": "SPIN DOCTOR",
+///   "description": "Turn a T into a slot",
+///   "start_level": 1,
+///   "goals": [
+///     { "clear_lines": { "lines": 2, "piece": "I" } },
+///     { "t_spin": { "lines": 1 } },
+///     { "survive": { "seconds": 60, "level": 10 } }
+///   ]
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Mission {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Level the game starts at, gravity follows the level like in Marathon
+    #[serde(default = "default_start_level")]
+    pub start_level: u32,
+    pub goals: Vec<Goal>,
+}
+
+/// One step of a [`Mission`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Goal {
+    /// Clear this many lines in total, counting only the clears made by `piece` (a letter) when set
+    ClearLines {
+        lines: u32,
+        #[serde(default)]
+        piece: Option<char>,
+    },
+    /// A T-spin clearing exactly this many lines: 0 for one that clears nothing, 1 for a single...
+    TSpin { lines: u32 },
+    /// Stay alive this long, only counting the time at `level` or above when set
+    Survive {
+        seconds: u32,
+        #[serde(default)]
+        level: Option<u32>,
+    },
+}
+
+impl Goal {
+    /// Upper case, as shown on screen, e.g. `CLEAR 2 LINES WITH I`
+    pub fn describe(&self) -> String {
+        match self {
+            Goal::ClearLines { lines, piece: None } => format!("CLEAR {} LINES", lines),
+            Goal::ClearLines { lines, piece: Some(letter) } => format!("CLEAR {} LINES WITH {}", lines, letter),
+            Goal::TSpin { lines: 0 } => "T-SPIN".to_string(),
+            Goal::TSpin { lines: 1 } => "T-SPIN SINGLE".to_string(),
+            Goal::TSpin { lines: 2 } => "T-SPIN DOUBLE".to_string(),
+            Goal::TSpin { lines: 3 } => "T-SPIN TRIPLE".to_string(),
+            Goal::TSpin { lines } => format!("T-SPIN {} LINES", lines),
+            Goal::Survive { seconds, level: None } => format!("SURVIVE {}S", seconds),
+            Goal::Survive { seconds, level: Some(level) } => format!("SURVIVE {}S AT LEVEL {}", seconds, level),
+        }
+    }
+
+    // How much of the goal there is to do, in the unit `MissionTracker::progress` counts in
+    fn target(&self) -> u64 {
+        match *self {
+            Goal::ClearLines { lines, .. } => lines as u64,
+            Goal::TSpin { .. } => 1,
+            Goal::Survive { seconds, .. } => seconds as u64 * TICKS_PER_SECOND,
+        }
+    }
+}
+
+impl Mission {
+    pub fn load(path: &Path) -> io::Result<Mission> {
+        let json = fs::read_to_string(path)?;
+        let mission: Mission = serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if mission.goals.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the mission has no goals"));
+        }
+        let mut letters = mission.goals.iter().filter_map(|goal| match goal {
+            Goal::ClearLines { piece, .. } => *piece,
+            _ => None,
+        });
+        if let Some(letter) = letters.find(|&letter| PieceSet::standard().find(letter).is_none()) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("there is no {} piece", letter)));
+        }
+        Ok(mission)
+    }
+
+    /// The standard rules, so a mission plays the same whatever rules are configured
+    pub fn game_config(&self) -> GameConfig {
+        GameConfig::default().with_start_level(self.start_level)
+    }
+}
+
+/// Follows a game through a [`Mission`]'s goals.
+///
+/// Feed it every event of the game with [`handle_event`](Self::handle_event), and the clock with
+/// [`update`](Self::update) (for the survival goals). Goals are reached in order, the mission is
+/// complete once the last one is.
+#[derive(Clone, Debug)]
+pub struct MissionTracker {
+    pub mission: Mission,
+    /// Index of the goal being played, `goals.len()` once they're all reached
+    pub current: usize,
+    /// Done towards the current goal: lines, T-spins or ticks survived
+    pub progress: u64,
+    /// The tick each reached goal was reached on, in order
+    pub reached_at: Vec<u64>,
+    // The shape of the last piece locked, which made the clears that follow it
+    last_locked: Option<TetrominoShape>,
+    last_tick: u64,
+}
+
+impl MissionTracker {
+    pub fn new(mission: Mission) -> Self {
+        MissionTracker { mission, current: 0, progress: 0, reached_at: Vec::new(), last_locked: None, last_tick: 0 }
+    }
+
+    pub fn current_goal(&self) -> Option<&Goal> {
+        self.mission.goals.get(self.current)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.current >= self.mission.goals.len()
+    }
+
+    /// Progress on the current goal as text, e.g. `1/2` lines or `0:12/1:00`. Empty for T-spins.
+    pub fn progress_text(&self) -> String {
+        match self.current_goal() {
+            Some(Goal::ClearLines { lines, .. }) => format!("{}/{}", self.progress, lines),
+            Some(goal @ Goal::Survive { .. }) => {
+                let seconds = |ticks: u64| ticks / TICKS_PER_SECOND;
+                let clock = |s: u64| format!("{}:{:02}", s / 60, s % 60);
+                format!("{}/{}", clock(seconds(self.progress)), clock(seconds(goal.target())))
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// `tick` is the session's clock when the event came out
+    pub fn handle_event(&mut self, event: &GameEvent, game: &Game, tick: u64) {
+        let Some(goal) = self.current_goal() else { return };
+        let done = match (goal, event) {
+            (_, GameEvent::PieceLocked { shape, .. }) => {
+                self.last_locked = Some(*shape);
+                0
+            }
+            (Goal::ClearLines { piece, .. }, GameEvent::LinesCleared { count, .. }) => {
+                let by = self.last_locked.map(|shape| game.rules.piece_set.letter(shape));
+                if piece.is_none() || *piece == by { *count as u64 } else { 0 }
+            }
+            (Goal::TSpin { lines }, GameEvent::TSpin { lines: cleared }) if lines == cleared => 1,
+            _ => 0,
+        };
+        self.advance(done, tick);
+    }
+
+    /// Counts the time survived since the last update, at the goal's level or above
+    pub fn update(&mut self, game: &Game, tick: u64) {
+        let elapsed = tick.saturating_sub(self.last_tick);
+        self.last_tick = tick;
+        if let Some(Goal::Survive { level, .. }) = self.current_goal()
+            && !game.is_game_over
+            && level.is_none_or(|level| game.level >= level)
+        {
+            self.advance(elapsed, tick);
+        }
+    }
+
+    // Adds to the current goal's progress, and moves on to the next goal when it's reached.
+    // Whatever goes past a goal is lost, the next one starts from nothing.
+    fn advance(&mut self, amount: u64, tick: u64) {
+        let Some(target) = self.current_goal().map(Goal::target) else { return };
+        self.progress += amount;
+        if self.progress >= target {
+            self.current += 1;
+            self.progress = 0;
+            self.reached_at.push(tick);
+        }
+    }
+}
+
+fn default_start_level() -> u32 {
+    1
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+use crate::rules::RuleSet;
+
+/// The different ways to play, picked from the title screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum GameMode {
+    Endless,
+    /// Clear 40 lines as fast as possible
+    Sprint,
+    /// Classic: gravity speeds up every 10 lines, done at level 15 (150 lines)
+    Marathon,
+    /// Race the bot on the same piece sequence, last one standing wins
+    VersusCpu,
+    /// Against another player over the network, garbage is sent on line clears
+    Online,
+    /// A preset board and a fixed set of pieces to solve it with
+    Puzzle,
+    /// Dig through 100 garbage rows as fast as possible, the board is refilled up to 10 of them
+    CheeseRace,
+    /// Endless, with every placement checked for the fewest inputs that reach it
+    Finesse,
+    /// Goals to reach one after the other, read from data files
+    Mission,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 9] = [
+        GameMode::Endless,
+        GameMode::Sprint,
+        GameMode::Marathon,
+        GameMode::CheeseRace,
+        GameMode::Finesse,
+        GameMode::VersusCpu,
+        GameMode::Online,
+        GameMode::Puzzle,
+        GameMode::Mission,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Endless => "ENDLESS",
+            GameMode::Sprint => "SPRINT 40L",
+            GameMode::Marathon => "MARATHON",
+            GameMode::VersusCpu => "VS CPU",
+            GameMode::Online => "ONLINE",
+            GameMode::Puzzle => "PUZZLE",
+            GameMode::CheeseRace => "CHEESE RACE",
+            GameMode::Finesse => "FINESSE",
+            GameMode::Mission => "MISSION",
+        }
+    }
+
+    /// Stable identifier, used as a key in score files. Unlike [`name`](Self::name) it never changes.
+    pub fn id(self) -> &'static str {
+        match self {
+            GameMode::Endless => "endless",
+            GameMode::Sprint => "sprint40",
+            GameMode::Marathon => "marathon",
+            GameMode::VersusCpu => "versus_cpu",
+            GameMode::Online => "online",
+            GameMode::Puzzle => "puzzle",
+            GameMode::CheeseRace => "cheese_race",
+            GameMode::Finesse => "finesse",
+            GameMode::Mission => "mission",
+        }
+    }
+
+    /// Lines to clear to finish the game, None for modes that only end by topping out
+    pub fn line_goal(self) -> Option<u32> {
+        match self {
+            GameMode::Sprint => Some(40),
+            GameMode::Marathon => Some(150),
+            _ => None,
+        }
+    }
+
+    /// The rules a new game in this mode is played with
+    pub fn rules(self) -> RuleSet {
+        match self {
+            GameMode::CheeseRace => RuleSet {
+                starting_garbage: 10,
+                cheese_rows: 10,
+                dig_goal: Some(100),
+                ..RuleSet::default()
+            },
+            _ => RuleSet::default(),
+        }
+    }
+
+    /// Gravity follows the level instead of staying fixed
+    pub fn has_levels(self) -> bool {
+        matches!(self, GameMode::Marathon | GameMode::Mission)
+    }
+
+    /// Practice modes let the player take back pieces
+    pub fn is_practice(self) -> bool {
+        matches!(self, GameMode::Endless | GameMode::Finesse)
+    }
+}
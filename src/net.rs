@@ -0,0 +1,187 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use learning1::snapshot::BoardSnapshot;
+
+// Bumped whenever a message changes shape, both sides must agree
+pub const PROTOCOL_VERSION: u32 = 7;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// Longest message we wait for the end of. Ours are a few kilobytes, more is a peer that never ends its line.
+const MAX_MESSAGE_BYTES: usize = 1 << 20;
+
+// Everything two clients say to each other during an online match.
+// Each side runs its own game; only garbage and what the board looks like cross the wire.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum NetMessage {
+    // First message from the host, both games are started from this seed
+    // and each player may pause `pauses` times
+    Hello { version: u32, seed: u64, pauses: u32 },
+    // Lines to add to the receiver's board, with the column left open
+    Garbage { lines: u8, hole: u8 },
+    // Latest look of the sender's board, for drawing it
+    Snapshot(Box<BoardSnapshot>),
+    // The sender wants to pause: both games freeze once they reach `at_tick`, in the sender's ticks
+    Pause { at_tick: u64 },
+    // The player who paused is back, both sides count down and continue
+    Resume,
+    // The sender topped out (or left)
+    GameOver,
+}
+
+// A TCP connection carrying newline separated JSON messages.
+// Never blocks: sends are buffered and flushed from poll(), which the scene calls every frame.
+pub struct Connection {
+    stream: TcpStream,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+    // The other side hung up, after we've handed out its last messages
+    closed: bool,
+    // Already received, handed out again by the next poll (see requeue)
+    requeued: Vec<NetMessage>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            stream,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+            closed: false,
+            requeued: Vec::new(),
+        })
+    }
+
+    pub fn send(&mut self, message: &NetMessage) {
+        // Serializing our own message types can't fail
+        serde_json::to_writer(&mut self.outgoing, message).expect("net message serializes");
+        self.outgoing.push(b'\n');
+    }
+
+    // Flushes what we can and returns every complete message received so far.
+    // An error means the connection is gone.
+    pub fn poll(&mut self) -> io::Result<Vec<NetMessage>> {
+        if self.closed {
+            if !self.requeued.is_empty() {
+                return Ok(std::mem::take(&mut self.requeued));
+            }
+            return Err(io::Error::new(ErrorKind::ConnectionAborted, "connection closed"));
+        }
+        self.flush()?;
+
+        let mut buf = [0u8; 4096];
+        // The rest waits in the socket until the complete messages are taken out below
+        while self.incoming.len() <= MAX_MESSAGE_BYTES {
+            match self.stream.read(&mut buf) {
+                Ok(0) => {
+                    // What arrived before the hang up (e.g. a GameOver) still counts
+                    self.closed = true;
+                    break;
+                }
+                Ok(n) => self.incoming.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut messages = std::mem::take(&mut self.requeued);
+        while let Some(end) = self.incoming.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.incoming.drain(..=end).collect();
+            let message = serde_json::from_slice(&line).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+            messages.push(message);
+        }
+        if self.incoming.len() > MAX_MESSAGE_BYTES {
+            return Err(io::Error::new(ErrorKind::InvalidData, "message too long"));
+        }
+        Ok(messages)
+    }
+
+    // Puts messages taken out by poll() back in front of whatever arrives next, for when the
+    // scene that polled isn't the one that should handle them (e.g. the lobby during the handshake)
+    pub fn requeue(&mut self, messages: Vec<NetMessage>) {
+        self.requeued.splice(0..0, messages);
+    }
+
+    // Sends whatever is still buffered (waiting for it this time) and hangs up
+    pub fn close(mut self) {
+        if self.stream.set_nonblocking(false).is_ok() {
+            let _ = self.stream.write_all(&self.outgoing);
+        }
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(io::Error::new(ErrorKind::WriteZero, "connection closed")),
+                Ok(n) => {
+                    self.outgoing.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+// Waits for a player to join
+pub struct Host {
+    listener: TcpListener,
+}
+
+impl Host {
+    pub fn listen(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Host { listener })
+    }
+
+    pub fn accept(&self) -> io::Result<Option<Connection>> {
+        match self.listener.accept() {
+            Ok((stream, _)) => Connection::new(stream).map(Some),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// Connects to a host on a background thread, so the window stays responsive meanwhile
+pub struct Joining {
+    result: Receiver<io::Result<TcpStream>>,
+}
+
+impl Joining {
+    pub fn start(address: &str) -> Self {
+        let address = address.to_string();
+        let (sender, result) = mpsc::channel();
+
+        thread::spawn(move || {
+            let connected = address.to_socket_addrs().and_then(|mut addrs| {
+                let addr = addrs.next().ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no address found"))?;
+                TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)
+            });
+            // The lobby may be gone already, nobody to tell then
+            let _ = sender.send(connected);
+        });
+
+        Joining { result }
+    }
+
+    pub fn poll(&self) -> io::Result<Option<Connection>> {
+        match self.result.try_recv() {
+            Ok(stream) => Connection::new(stream?).map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(io::Error::other("connection attempt failed")),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use learning1::bot::Bot;
+use learning1::game::{Game, HEIGHT, HIDDEN_ROWS, VISIBLE_HEIGHT, WIDTH};
+use learning1::mode::GameMode;
+use learning1::replay::Replay;
+use learning1::session::Session;
+use learning1::snapshot::BoardSnapshot;
+
+use crate::tui;
+
+// The generated script stops after this many pieces, enough to fill and clear the board many times
+const SCRIPT_PIECES: u32 = 300;
+// Ticks the bot waits before each piece and between two inputs, so gravity gets to move pieces too
+const THINK_TICKS: u64 = 120;
+const INPUT_TICKS: u64 = 30;
+
+// Runs an input script through the simulation and checks, on every tick, that the frontends
+// see the same board:
+// - the text frontend, drawn from a board snapshot (the GPU renderer reads the same snapshot)
+// - a headless dump taken straight from the game state, without going through a snapshot
+// - the snapshot after a JSON round trip, which is what an online opponent receives
+// Returns false on the first tick where they disagree.
+pub fn run_parity_check(replay: &Replay) -> bool {
+    let mut session = Session::from_replay(replay);
+
+    loop {
+        let snapshot = session.game.snapshot();
+
+        let tui_board = tui::board_lines(&snapshot);
+        let dumped_board = dump_board(&session.game);
+        if tui_board != dumped_board {
+            println!("Parity check failed at tick {}: text frontend and game state differ", session.tick);
+            print_side_by_side(&tui_board, &dumped_board);
+            return false;
+        }
+
+        let json = serde_json::to_string(&snapshot).expect("snapshots always serialize");
+        let received: BoardSnapshot = serde_json::from_str(&json).expect("snapshots always deserialize");
+        if received != snapshot {
+            println!("Parity check failed at tick {}: the snapshot changed in a JSON round trip", session.tick);
+            print_side_by_side(&tui::board_lines(&received), &tui_board);
+            return false;
+        }
+
+        if session.is_playback_finished(replay.end_tick) {
+            break;
+        }
+        session.step();
+    }
+
+    println!(
+        "Parity check passed: {} ticks, {} inputs, {} lines, score {}",
+        session.tick,
+        replay.inputs.len(),
+        session.game.lines_cleared,
+        session.game.score,
+    );
+    true
+}
+
+// A script to check when no replay is given: the bot playing a seeded game at a human-ish pace
+pub fn bot_script(seed: u64) -> Replay {
+    let bot = Bot::new();
+    let mut session = Session::new(Game::with_seed(seed), GameMode::Endless);
+
+    for _ in 0..SCRIPT_PIECES {
+        for _ in 0..THINK_TICKS {
+            session.step();
+        }
+        for action in bot.plan(&session.game) {
+            session.apply(action);
+            for _ in 0..INPUT_TICKS {
+                session.step();
+            }
+        }
+        if session.is_finished() {
+            break;
+        }
+    }
+
+    session.to_replay()
+}
+
+// The visible board in the text frontend's format, read directly from the game
+fn dump_board(game: &Game) -> Vec<String> {
+    let mut rows = [[tui::EMPTY_CHAR; WIDTH]; VISIBLE_HEIGHT];
+
+    for (row, grid_row) in rows.iter_mut().zip(&game.grid[HIDDEN_ROWS..]) {
+        for (cell, &grid_cell) in row.iter_mut().zip(grid_row) {
+            *cell = tui::cell_char(grid_cell, &game.rules.piece_set);
+        }
+    }
+
+    if let Some(piece) = &game.current_piece {
+        for &(local_x, local_y) in &piece.cells {
+            let (x, y) = (piece.x + local_x, piece.y + local_y);
+            if (0..WIDTH as i32).contains(&x) && (HIDDEN_ROWS as i32..HEIGHT as i32).contains(&y) {
+                rows[y as usize - HIDDEN_ROWS][x as usize] = tui::FALLING_CHAR;
+            }
+        }
+    }
+
+    rows.iter().map(|row| row.iter().collect()).collect()
+}
+
+fn print_side_by_side(left: &[String], right: &[String]) {
+    for (y, (l, r)) in left.iter().zip(right).enumerate() {
+        let marker = if l != r { "<>" } else { "  " };
+        println!("{:>2} {} {} {}", y, l, marker, r);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frontends_see_the_same_board_on_every_tick() {
+        let replay = bot_script(42);
+        assert!(replay.inputs.len() > 100, "the bot should have played a real game");
+        assert!(run_parity_check(&replay));
+    }
+}
@@ -0,0 +1,89 @@
+use rand::Rng;
+
+use learning1::game::{GARBAGE_CELL, HIDDEN_ROWS, WIDTH};
+use learning1::piece_set::PieceSet;
+
+use crate::effects::HudQuad;
+use crate::theme::Theme;
+
+// How long a particle lives, it fades out over that time
+const LIFETIME: f32 = 0.5;
+// Particles thrown per cell of a cleared row
+const PER_CELL: usize = 2;
+const SIZE: f32 = 0.3;
+// Speeds in cells per second
+const SIDE_SPEED: f32 = 6.0;
+const UP_SPEED: f32 = 5.0;
+const GRAVITY: f32 = 25.0;
+
+struct Particle {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+    // The grid cell it came from, which gives its color
+    cell: u8,
+    age: f32,
+}
+
+// Small squares bursting out of cleared rows.
+// Purely cosmetic, so they use the thread RNG and not the game's seeded one.
+#[derive(Default)]
+pub struct Particles {
+    particles: Vec<Particle>,
+}
+
+impl Particles {
+    // A burst from a cleared row, `y` is its grid row and `cells` what it held
+    pub fn burst(&mut self, y: i32, cells: &[u8; WIDTH]) {
+        let mut rng = rand::rng();
+        let center = WIDTH as f32 / 2.0;
+        let row = (y - HIDDEN_ROWS as i32) as f32;
+
+        for (x, &cell) in cells.iter().enumerate().filter(|&(_, &cell)| cell != 0) {
+            for _ in 0..PER_CELL {
+                // Outwards from the middle of the row, the cells on the edges fly the furthest
+                let side = (x as f32 + 0.5 - center) / center;
+                self.particles.push(Particle {
+                    x: x as f32 + rng.random_range(0.0..1.0 - SIZE),
+                    y: row + rng.random_range(0.0..1.0 - SIZE),
+                    vx: SIDE_SPEED * (side + rng.random_range(-0.4..0.4)),
+                    vy: -UP_SPEED * rng.random_range(0.3..1.0),
+                    cell,
+                    age: 0.0,
+                });
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for particle in &mut self.particles {
+            particle.vy += GRAVITY * dt;
+            particle.x += particle.vx * dt;
+            particle.y += particle.vy * dt;
+            particle.age += dt;
+        }
+        self.particles.retain(|particle| particle.age < LIFETIME);
+    }
+
+    pub fn draw(&self, quads: &mut Vec<HudQuad>, theme: &Theme, pieces: &PieceSet) {
+        for particle in &self.particles {
+            let [r, g, b, _] = match particle.cell {
+                GARBAGE_CELL => theme.cell_color(pieces, particle.cell),
+                // Pieces are brightened a bit so the burst stands out from the stack
+                cell => theme.cell_color(pieces, cell).map(|c| (c + 0.3).min(1.0)),
+            };
+            quads.push(HudQuad {
+                x: particle.x,
+                y: particle.y,
+                w: SIZE,
+                h: SIZE,
+                color: [r, g, b, 1.0 - particle.age / LIFETIME],
+            });
+        }
+    }
+}
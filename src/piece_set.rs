@@ -0,0 +1,284 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::action::Rotation;
+use crate::game::{GARBAGE_CELL, HIDDEN_ROWS, SPAWN_X, WIDTH};
+use crate::tetromino::{rotate_cw, Point, TetrominoShape};
+
+/// How far a cell can be from the pivot, so every piece fits the 5x5 preview boxes
+pub const MAX_REACH: i32 = 2;
+
+// The 7 tetrominoes, their spawn positions and kicks, as every game has played them so far
+static STANDARD: LazyLock<PieceSet> = LazyLock::new(|| {
+    let set: PieceSet = serde_json::from_str(include_str!("../assets/rules/standard.json")).expect("assets/rules/standard.json is a piece set");
+    set.validate().expect("assets/rules/standard.json is playable");
+    set
+});
+
+/// One shape of a [`PieceSet`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct PieceDef {
+    /// One-letter name, shown in the stats and by the text frontend
+    pub letter: char,
+    /// Red, green and blue, 0-255
+    pub color: [u8; 3],
+    /// Cells relative to the pivot (0, 0) the piece turns around, y grows downwards
+    pub cells: Vec<Point>,
+    /// Cells after one, two and three clockwise turns. When empty, `cells` are turned around the pivot.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rotations: Vec<Vec<Point>>,
+    /// Added to the spawn position, which puts the pivot in the middle column
+    #[serde(default, skip_serializing_if = "is_origin")]
+    pub spawn_offset: Point,
+    /// Kicks for this shape only, instead of the set's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kicks: Option<KickTable>,
+    /// Turns of this piece into a tight spot are T-spins, judged by the three-corner rule around
+    /// the pivot. Only for a T whose pivot is its center in every orientation.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub t_spin: bool,
+}
+
+/// Offsets tried in order when a turned piece doesn't fit where it is ("wall kicks"),
+/// in cells with y growing downwards. The first offset that fits wins, the turn fails if none does.
+///
+/// Each kind of turn has either a single list used from every orientation, or four lists:
+/// one per orientation turned from (spawn, then one, two and three clockwise turns from it).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct KickTable {
+    pub cw: Vec<Vec<Point>>,
+    pub ccw: Vec<Vec<Point>>,
+    pub half: Vec<Vec<Point>>,
+}
+
+impl KickTable {
+    /// The offsets for a turn from `orientation`, see [`ActivePiece::orientation`](crate::game::ActivePiece::orientation)
+    pub fn offsets(&self, rotation: Rotation, orientation: u8) -> &[Point] {
+        let lists = match rotation {
+            Rotation::Cw => &self.cw,
+            Rotation::Ccw => &self.ccw,
+            Rotation::Half => &self.half,
+        };
+        let index = if lists.len() == 4 { orientation as usize % 4 } else { 0 };
+        lists.get(index).map_or(&[], Vec::as_slice)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for lists in [&self.cw, &self.ccw, &self.half] {
+            if lists.len() != 1 && lists.len() != 4 {
+                return Err("kicks need one list per kind of turn, or four (one per orientation)".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The shapes a game deals and how they turn, the 7 tetrominoes unless the rules say otherwise.
+///
+/// Sets are JSON files, see [`PieceSet::load`]. Only the shapes are required, `assets/rules`
+/// has complete rotation systems (the standard one, SRS and ARS):
+///
+/// ```json
+/// {
+///   "name": "DOMINO",
+///   "pieces": [{ "letter": "D", "color": [255, 255, 255], "cells": [[0, 0], [1, 0]] }],
+///   "kicks": { "cw": [[[0, 0], [-1, 0]]], "ccw": [[[0, 0], [1, 0]]], "half": [[[0, 0]]] }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct PieceSet {
+    pub name: String,
+    /// A [`TetrominoShape`] is a position in this list
+    pub pieces: Vec<PieceDef>,
+    /// Kicks for every shape without its own, the standard ones when left out
+    #[serde(default = "standard_kicks")]
+    pub kicks: KickTable,
+}
+
+impl Default for PieceSet {
+    fn default() -> Self {
+        PieceSet::standard()
+    }
+}
+
+impl PieceSet {
+    /// The 7 tetrominoes in their usual colors, from `assets/rules/standard.json` (built in)
+    pub fn standard() -> Self {
+        STANDARD.clone()
+    }
+
+    /// Reads a set from a JSON file and checks that the game can play it
+    pub fn load(path: &Path) -> io::Result<PieceSet> {
+        let json = fs::read_to_string(path)?;
+        let set: PieceSet = serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        set.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(set)
+    }
+
+    /// Checks that the game can play the set, see [`PieceSet::load`]
+    // Grid cells store the piece index + 1 below GARBAGE_CELL, and the previews are 5x5
+    pub fn validate(&self) -> Result<(), String> {
+        if self.pieces.is_empty() || self.pieces.len() >= GARBAGE_CELL as usize {
+            return Err(format!("a piece set needs 1 to {} pieces", GARBAGE_CELL - 1));
+        }
+        self.kicks.validate()?;
+        for (index, piece) in self.pieces.iter().enumerate() {
+            // Puzzles and the stats find shapes by letter
+            if self.pieces[..index].iter().any(|other| other.letter == piece.letter) {
+                return Err(format!("more than one piece is called {}", piece.letter));
+            }
+            if !piece.rotations.is_empty() && piece.rotations.len() != 3 {
+                return Err(format!("piece {} needs cells for all 3 turned orientations, or none", piece.letter));
+            }
+            for cells in std::iter::once(&piece.cells).chain(&piece.rotations) {
+                if cells.is_empty() {
+                    return Err(format!("piece {} has no cells", piece.letter));
+                }
+                if cells.iter().any(|&(x, y)| x.abs() > MAX_REACH || y.abs() > MAX_REACH) {
+                    return Err(format!("piece {} has cells more than {} away from its pivot", piece.letter, MAX_REACH));
+                }
+                if cells.iter().enumerate().any(|(i, cell)| cells[..i].contains(cell)) {
+                    return Err(format!("piece {} has the same cell twice", piece.letter));
+                }
+            }
+            if piece.cells.iter().any(|&(x, _)| !(0..WIDTH as i32).contains(&(SPAWN_X + piece.spawn_offset.0 + x))) {
+                return Err(format!("piece {} spawns outside the board", piece.letter));
+            }
+            if let Some(kicks) = &piece.kicks {
+                kicks.validate()?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_standard(&self) -> bool {
+        *self == *STANDARD
+    }
+
+    // The caller owns the RNG so games can be replayed from a seed.
+    // Drawn straight from `next_u64` so the sequence doesn't depend on how `rand` maps ranges.
+    pub fn random(&self, rng: &mut impl RngCore) -> TetrominoShape {
+        TetrominoShape::from_index((rng.next_u64() % self.pieces.len() as u64) as usize)
+    }
+
+    /// The shape with this letter, if the set has one
+    pub fn find(&self, letter: char) -> Option<TetrominoShape> {
+        self.pieces.iter().position(|piece| piece.letter == letter).map(TetrominoShape::from_index)
+    }
+
+    /// The cells of a shape in spawn orientation, none for a shape that isn't in the set
+    pub fn cells(&self, shape: TetrominoShape) -> &[Point] {
+        self.pieces.get(shape.to_index()).map_or(&[], |piece| &piece.cells)
+    }
+
+    /// Whether spins of this shape count as T-spins, see [`PieceDef::t_spin`]
+    pub fn has_t_spins(&self, shape: TetrominoShape) -> bool {
+        self.pieces.get(shape.to_index()).is_some_and(|piece| piece.t_spin)
+    }
+
+    pub fn letter(&self, shape: TetrominoShape) -> char {
+        self.pieces.get(shape.to_index()).map_or('?', |piece| piece.letter)
+    }
+
+    pub fn color(&self, shape: TetrominoShape) -> [u8; 3] {
+        self.pieces.get(shape.to_index()).map_or([255, 255, 255], |piece| piece.color)
+    }
+
+    /// The cells of a shape after `orientation` clockwise turns from its spawn orientation
+    pub fn turned_cells(&self, shape: TetrominoShape, orientation: u8) -> Vec<Point> {
+        let orientation = orientation as usize % 4;
+        match self.pieces.get(shape.to_index()) {
+            Some(piece) if orientation > 0 && !piece.rotations.is_empty() => piece.rotations[orientation - 1].clone(),
+            Some(piece) => (0..orientation).fold(piece.cells.clone(), |cells, _| rotate_cw(&cells)),
+            None => Vec::new(),
+        }
+    }
+
+    /// The kicks for a turn of a shape from `orientation`
+    pub fn kicks(&self, shape: TetrominoShape, rotation: Rotation, orientation: u8) -> &[Point] {
+        let table = self.pieces.get(shape.to_index()).and_then(|piece| piece.kicks.as_ref()).unwrap_or(&self.kicks);
+        table.offsets(rotation, orientation)
+    }
+
+    /// The row pieces spawn on (their pivot, before the offset): as low as it gets while every piece
+    /// of the set still starts out entirely in the hidden rows
+    pub fn spawn_y(&self) -> i32 {
+        let lowest = self.pieces.iter()
+            .flat_map(|piece| piece.cells.iter().map(|&(_, y)| y + piece.spawn_offset.1))
+            .max()
+            .unwrap_or(0);
+        HIDDEN_ROWS as i32 - 1 - lowest
+    }
+
+    /// Where the pivot of a new piece of this shape goes
+    pub fn spawn_position(&self, shape: TetrominoShape) -> Point {
+        let (dx, dy) = self.pieces.get(shape.to_index()).map_or((0, 0), |piece| piece.spawn_offset);
+        (SPAWN_X + dx, self.spawn_y() + dy)
+    }
+}
+
+fn standard_kicks() -> KickTable {
+    STANDARD.kicks.clone()
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn is_origin(point: &Point) -> bool {
+    *point == (0, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The domino from the PieceSet docs
+    fn domino_set() -> serde_json::Value {
+        serde_json::json!({
+            "name": "DOMINO",
+            "pieces": [{ "letter": "D", "color": [255, 255, 255], "cells": [[0, 0], [1, 0]] }],
+            "kicks": { "cw": [[[0, 0], [-1, 0]]], "ccw": [[[0, 0], [1, 0]]], "half": [[[0, 0]]] }
+        })
+    }
+
+    fn validate(set: serde_json::Value) -> Result<(), String> {
+        serde_json::from_value::<PieceSet>(set).expect("a piece set").validate()
+    }
+
+    #[test]
+    fn valid_set_passes() {
+        assert_eq!(validate(domino_set()), Ok(()));
+        assert_eq!(PieceSet::standard().validate(), Ok(()));
+    }
+
+    #[test]
+    fn set_without_kicks_for_a_turn_is_rejected() {
+        let mut set = domino_set();
+        set["kicks"]["cw"] = serde_json::json!([]);
+        assert!(validate(set).unwrap_err().contains("kicks"));
+    }
+
+    #[test]
+    fn piece_without_cells_is_rejected() {
+        let mut set = domino_set();
+        set["pieces"][0]["cells"] = serde_json::json!([]);
+        assert_eq!(validate(set), Err("piece D has no cells".to_string()));
+    }
+
+    #[test]
+    fn two_pieces_with_the_same_letter_are_rejected() {
+        let mut set = domino_set();
+        let second = set["pieces"][0].clone();
+        set["pieces"].as_array_mut().unwrap().push(second);
+        assert_eq!(validate(set), Err("more than one piece is called D".to_string()));
+    }
+}
@@ -0,0 +1,128 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Game, GameConfig, GARBAGE_CELL, HEIGHT, VISIBLE_HEIGHT, WIDTH};
+use crate::piece_set::PieceSet;
+use crate::rules::RuleSet;
+
+/// A puzzle: a pre-filled board, a fixed list of pieces and something to achieve with them.
+///
+/// Puzzles are JSON files, see [`Puzzle::load`]:
+///
+/// ```json
+/// {
+///   "name": "FIRST TETRIS",
+///   "description": "One I piece, four lines",
+///   "board": ["#########.", "#########.", "#########.", "#########."],
+///   "pieces": ["I"],
+///   "objective": "clear_all"
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Puzzle {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// The starting board, top to bottom, resting on the floor.
+    /// `.` or a space is empty, `I O T S Z J L` are blocks in that shape's color, anything else is garbage.
+    pub board: Vec<String>,
+    /// Every piece the puzzle deals, in order, by their letter in the standard set.
+    /// Running out of pieces fails the puzzle.
+    pub pieces: Vec<char>,
+    pub objective: Objective,
+}
+
+/// What solves a [`Puzzle`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Objective {
+    /// Leave no block on the board
+    ClearAll,
+    /// Clear at least this many lines
+    ClearLines(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PuzzleStatus {
+    Playing,
+    Solved,
+    Failed,
+}
+
+impl Puzzle {
+    pub fn load(path: &Path) -> io::Result<Puzzle> {
+        let json = fs::read_to_string(path)?;
+        let puzzle: Puzzle = serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if puzzle.board.len() > VISIBLE_HEIGHT || puzzle.board.iter().any(|row| row.chars().count() > WIDTH) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the board is larger than {}x{}", WIDTH, VISIBLE_HEIGHT),
+            ));
+        }
+        if puzzle.pieces.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the puzzle has no pieces"));
+        }
+        if let Some(letter) = puzzle.pieces.iter().find(|&&letter| PieceSet::standard().find(letter).is_none()) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("there is no {} piece", letter)));
+        }
+        Ok(puzzle)
+    }
+
+    /// The starting board as a game grid
+    pub fn grid(&self) -> [[u8; WIDTH]; HEIGHT] {
+        let pieces = PieceSet::standard();
+        let mut grid = [[0; WIDTH]; HEIGHT];
+        let top = HEIGHT - self.board.len().min(HEIGHT);
+        for (row, line) in grid[top..].iter_mut().zip(&self.board) {
+            for (cell, c) in row.iter_mut().zip(line.chars()) {
+                *cell = match (c, pieces.find(c)) {
+                    ('.' | ' ', _) => 0,
+                    (_, Some(shape)) => shape.to_index() as u8 + 1,
+                    _ => GARBAGE_CELL,
+                };
+            }
+        }
+        grid
+    }
+
+    // No hold: it would bring in a piece from outside the puzzle's list
+    pub fn game_config(&self) -> GameConfig {
+        let pieces = PieceSet::standard();
+        GameConfig::default()
+            .with_board(self.grid())
+            .with_pieces(self.pieces.iter().filter_map(|&letter| pieces.find(letter)).collect())
+            .with_rules(RuleSet { hold: false, ..RuleSet::default() })
+    }
+
+    /// Pieces that haven't locked yet, counting the falling one
+    pub fn pieces_left(&self, game: &Game) -> u32 {
+        (self.pieces.len() as u32).saturating_sub(pieces_locked(game))
+    }
+
+    pub fn status(&self, game: &Game) -> PuzzleStatus {
+        let solved = match self.objective {
+            Objective::ClearAll => game.grid.iter().all(|row| row.iter().all(|&cell| cell == 0)),
+            Objective::ClearLines(lines) => game.lines_cleared >= lines,
+        };
+
+        // The last piece can still solve it, so that's checked first
+        if solved {
+            PuzzleStatus::Solved
+        } else if game.is_game_over || self.pieces_left(game) == 0 {
+            PuzzleStatus::Failed
+        } else {
+            PuzzleStatus::Playing
+        }
+    }
+}
+
+// Pieces are counted in the stats when they are dealt, so the falling and the held one are in there too
+fn pieces_locked(game: &Game) -> u32 {
+    let dealt: u32 = game.piece_stats.iter().sum();
+    dealt - game.current_piece.is_some() as u32 - game.hold_piece.is_some() as u32
+}
@@ -0,0 +1,193 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::imageops::{self, FilterType};
+use image::{Delay, Frame, ImageResult, RgbaImage};
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+use crate::config::RecordingSettings;
+use crate::graphic_context::CapturedFrame;
+use crate::storage;
+
+// A clip asked for by a Tetris goes on a bit longer, so the clear itself is in it
+const TETRIS_TAIL: Duration = Duration::from_millis(1500);
+// Higher is faster and uglier, 10 is the gif crate's middle ground
+const GIF_SPEED: i32 = 10;
+
+// What a saved clip is written as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipFormat {
+    // An animated GIF in recordings/, named after when it was saved
+    #[default]
+    Gif,
+    // A folder of numbered PNGs in recordings/, for a video editor
+    Frames,
+}
+
+enum Command {
+    Frame(CapturedFrame, Instant),
+    Save,
+}
+
+// Keeps the last few seconds of what was on screen, to save as a clip on demand.
+// Frames are read back from the GPU by GraphicContext. Scaling them down, keeping them and
+// encoding clips happens on background threads, so the render loop only pays for the copy.
+pub struct Recorder {
+    frame_interval: Duration,
+    next_frame: Instant,
+    on_tetris: bool,
+    // A clip asked for by a Tetris, saved once its tail was recorded
+    save_at: Option<Instant>,
+    commands: Sender<Command>,
+    saved: Receiver<Result<PathBuf, String>>,
+}
+
+impl Recorder {
+    pub fn new(settings: &RecordingSettings, now: Instant) -> Self {
+        let (commands, command_receiver) = mpsc::channel();
+        let (saved_sender, saved) = mpsc::channel();
+        let worker_settings = settings.clone();
+        thread::spawn(move || run_worker(worker_settings, command_receiver, saved_sender));
+
+        Recorder {
+            frame_interval: Duration::from_secs(1) / settings.fps.max(1),
+            next_frame: now,
+            on_tetris: settings.on_tetris,
+            save_at: None,
+            commands,
+            saved,
+        }
+    }
+
+    // True when it's time to capture another frame
+    pub fn wants_frame(&mut self, now: Instant) -> bool {
+        if now < self.next_frame {
+            return false;
+        }
+        // Slow frames don't make the recorder catch up with a burst of captures
+        self.next_frame = (self.next_frame + self.frame_interval).max(now);
+        true
+    }
+
+    // When a frame should be drawn next for the recorder: its next capture, or a clip waiting to be saved
+    pub fn next_deadline(&self) -> Instant {
+        self.save_at.map_or(self.next_frame, |at| at.min(self.next_frame))
+    }
+
+    pub fn push_frame(&self, frame: CapturedFrame, now: Instant) {
+        let _ = self.commands.send(Command::Frame(frame, now));
+    }
+
+    // Saves what was recorded so far
+    pub fn save(&self) {
+        let _ = self.commands.send(Command::Save);
+    }
+
+    // A Tetris was scored: saves a clip a moment later, when enabled
+    pub fn tetris_scored(&mut self, now: Instant) {
+        if self.on_tetris && self.save_at.is_none() {
+            self.save_at = Some(now + TETRIS_TAIL);
+        }
+    }
+
+    pub fn update(&mut self, now: Instant) {
+        if self.save_at.is_some_and(|at| now >= at) {
+            self.save_at = None;
+            self.save();
+        }
+    }
+
+    // Where the next finished clip was written, None while none is done
+    pub fn poll(&self) -> Option<Result<PathBuf, String>> {
+        self.saved.try_recv().ok()
+    }
+}
+
+// Scales frames down and keeps the last `seconds` of them, until the Recorder is dropped.
+// Clips are encoded on a thread of their own, so frames keep coming in meanwhile.
+fn run_worker(settings: RecordingSettings, commands: Receiver<Command>, saved: Sender<Result<PathBuf, String>>) {
+    let length = Duration::from_secs(settings.seconds.into());
+    let mut frames: VecDeque<(Instant, RgbaImage)> = VecDeque::new();
+
+    for command in commands {
+        match command {
+            Command::Frame(frame, at) => {
+                frames.push_back((at, downscale(frame, settings.width)));
+                while frames.front().is_some_and(|(first, _)| at.saturating_duration_since(*first) > length) {
+                    frames.pop_front();
+                }
+            }
+            Command::Save => {
+                let clip: Vec<_> = frames.iter().cloned().collect();
+                let saved = saved.clone();
+                let format = settings.format;
+                thread::spawn(move || {
+                    let _ = saved.send(write_clip(&clip, format).map_err(|e| e.to_string()));
+                });
+            }
+        }
+    }
+}
+
+// RGBA at `width` pixels wide (never wider than the window), keeping the window's shape
+fn downscale(frame: CapturedFrame, width: u32) -> RgbaImage {
+    let CapturedFrame { width: frame_width, height: frame_height, bgra, mut pixels } = frame;
+    for pixel in pixels.chunks_exact_mut(4) {
+        if bgra {
+            pixel.swap(0, 2);
+        }
+        // The window isn't see-through, whatever the alpha ended up as
+        pixel[3] = 255;
+    }
+    let image = RgbaImage::from_raw(frame_width, frame_height, pixels).expect("captured frame size");
+
+    let width = width.clamp(1, frame_width);
+    let height = (frame_height as u64 * width as u64 / frame_width as u64).max(1) as u32;
+    imageops::resize(&image, width, height, FilterType::Triangle)
+}
+
+fn write_clip(frames: &[(Instant, RgbaImage)], format: ClipFormat) -> ImageResult<PathBuf> {
+    if frames.is_empty() {
+        return Err(std::io::Error::other("nothing recorded yet").into());
+    }
+    let dir = recordings_dir();
+    fs::create_dir_all(&dir)?;
+    let name = chrono::Local::now().format("%Y%m%d-%H%M%S");
+
+    match format {
+        ClipFormat::Gif => {
+            let path = dir.join(format!("{}.gif", name));
+            let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(&path)?), GIF_SPEED);
+            encoder.set_repeat(Repeat::Infinite)?;
+            // Each frame stays up until the next one was captured, the last one as long as the one before
+            let mut delay = Duration::ZERO;
+            for (i, (at, image)) in frames.iter().enumerate() {
+                if let Some((next, _)) = frames.get(i + 1) {
+                    delay = next.saturating_duration_since(*at);
+                }
+                encoder.encode_frame(Frame::from_parts(image.clone(), 0, 0, Delay::from_saturating_duration(delay)))?;
+            }
+            Ok(path)
+        }
+        ClipFormat::Frames => {
+            let path = dir.join(name.to_string());
+            fs::create_dir_all(&path)?;
+            for (i, (_, image)) in frames.iter().enumerate() {
+                image.save(path.join(format!("{:04}.png", i + 1)))?;
+            }
+            Ok(path)
+        }
+    }
+}
+
+pub fn recordings_dir() -> PathBuf {
+    storage::data_dir().join("recordings")
+}
@@ -1,139 +1,364 @@
-use crate::game::{Game, WIDTH, HEIGHT};
-use crate::tetromino::TetrominoShape;
+use std::io::{self, Stdout, Write};
+use std::time::Duration;
 
-pub struct ConsoleRenderer;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
+use crossterm::{cursor, execute, queue, terminal};
+use web_time::Instant;
+
+use learning1::action::Action;
+use learning1::events::TopOutKind;
+use learning1::game::{Game, HIDDEN_ROWS, VISIBLE_HEIGHT, WIDTH};
+use learning1::mode::GameMode;
+use learning1::session::Session;
+use learning1::snapshot::BoardSnapshot;
+use learning1::tetromino::TetrominoShape;
+
+use crate::autoplay::BotController;
+use crate::highscores::{self, BestResult};
+use crate::instance::GameInstance;
+use crate::scenes::playing::game_config;
+use crate::scenes::SceneContext;
+use crate::storage;
+use crate::theme::Theme;
+
+// Input is read and the game stepped about 60 times a second, like the window's frames
+const FRAME_TIME: Duration = Duration::from_millis(16);
+const CANVAS_WIDTH: usize = 48;
+const CANVAS_HEIGHT: usize = VISIBLE_HEIGHT + 1;
+// Each cell is two characters wide so the board looks square in most fonts
+const BOARD_RIGHT: usize = 1 + WIDTH * 2;
+const PANEL_X: usize = BOARD_RIGHT + 3;
+const NEXT_X: usize = PANEL_X + 10;
+const STATS_PER_LINE: usize = 3;
+
+const TEXT_COLOR: Color = Color::White;
+const LABEL_COLOR: Color = Color::Grey;
+const EMPTY_COLOR: Color = Color::DarkGrey;
+const DANGER_COLOR: Color = Color::Red;
+const BEST_COLOR: Color = Color::Yellow;
+
+// The modes that need nothing but one board and a keyboard
+pub const TUI_MODES: [GameMode; 4] = [GameMode::Endless, GameMode::Sprint, GameMode::Marathon, GameMode::CheeseRace];
+
+// The console frontend: plays a game in the terminal with crossterm, for playing over SSH or
+// without a GPU. Like the window it draws nothing but a board snapshot, and drives the same
+// GameInstance, so timing, scoring and replays are the same.
+pub struct ConsoleRenderer {
+    stdout: Stdout,
+    // What is on the terminal, only the rows that change are written again
+    shown: Vec<Vec<(char, Color)>>,
+}
+
+// A frame being drawn: a character and its color for every position
+struct Canvas {
+    cells: Vec<Vec<(char, Color)>>,
+}
+
+impl Canvas {
+    fn new() -> Self {
+        Canvas { cells: vec![vec![(' ', TEXT_COLOR); CANVAS_WIDTH]; CANVAS_HEIGHT] }
+    }
+
+    fn text(&mut self, x: usize, y: usize, text: &str, color: Color) {
+        if let Some(row) = self.cells.get_mut(y) {
+            for (cell, c) in row.iter_mut().skip(x).zip(text.chars()) {
+                *cell = (c, color);
+            }
+        }
+    }
+}
 
 impl ConsoleRenderer {
-    pub fn new() -> Self {
-        ConsoleRenderer
-    }
-
-    pub fn render(&self, game: &Game) {
-        println!("Displaying Game State:\r"); // \r returns carriage to start of line
-        
-        let stats_start_y = 7;
-        let items_count = 7;
-        let item_height = 3; // 2 rows icon + 1 separator
-        let sidebar_height = stats_start_y + (items_count * item_height);
-        
-        // Determine total height (Grid is HEIGHT=20, so 0..20, plus bottom border at 21)
-        // We iterate y from 0 to max
-        let grid_height = HEIGHT; 
-        let total_height = if (grid_height + 1) > sidebar_height { grid_height + 1 } else { sidebar_height };
-        
-        for y in 0..total_height {
-            // --- LEFT COLUMN (GRID) ---
-            if y < HEIGHT {
-                print!("|"); 
-                for x in 0..WIDTH {
-                    let mut is_piece_part = false;
-
-                    // Check if this map position (x, y) is part of the current falling piece
-                    if let Some(ref piece) = game.current_piece {
-                        for (local_x, local_y) in piece.cells {
-                            let abs_x = piece.x + local_x;
-                            let abs_y = piece.y + local_y;
-
-                            if abs_x == x as i32 && abs_y == y as i32 {
-                                is_piece_part = true;
-                                break;
-                            }
-                        }
-                    }
-
-                    let cell = game.grid[y][x];
-                    if is_piece_part {
-                        print!(" O ");
-                    } else if cell == 0 {
-                        print!(" . ");
-                    } else {
-                        print!(" # ");
-                    }
+    // Switches the terminal to raw mode on the alternate screen, undone when dropped
+    pub fn new() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide, terminal::Clear(terminal::ClearType::All))?;
+        Ok(ConsoleRenderer { stdout, shown: Vec::new() })
+    }
+
+    pub fn render(&mut self, instance: &GameInstance, theme: &Theme, status: &Status) -> io::Result<()> {
+        let canvas = draw(instance, theme, status);
+
+        for (y, row) in canvas.cells.iter().enumerate() {
+            if self.shown.get(y) == Some(row) {
+                continue;
+            }
+            queue!(self.stdout, cursor::MoveTo(0, y as u16))?;
+            let mut current = None;
+            for &(c, color) in row {
+                if current != Some(color) {
+                    queue!(self.stdout, SetForegroundColor(color))?;
+                    current = Some(color);
                 }
-                print!("|  "); // End of grid row + spacing
-            } else if y == HEIGHT {
-                 // Bottom border
-                 print!(" {:-<30}   ", ""); // 3 spaces to align with sidebar
-            } else {
-                 // Empty space below grid
-                 print!("{:34}", " "); // spacer to match grid width
+                queue!(self.stdout, Print(c))?;
             }
+        }
+        queue!(self.stdout, ResetColor)?;
+        self.stdout.flush()?;
 
-            // --- RIGHT COLUMN (SIDEBAR) ---
-            match y {
-                0 => print!("Score: {}", game.score),
-                2 => print!("Next:"),
-                3 => print!("{}", self.get_mini_icon(game.next_piece, 0)),
-                4 => print!("{}", self.get_mini_icon(game.next_piece, 1)),
-                6 => print!("Stats:"),
-                i if i >= stats_start_y => {
-                    let stats_row = i - stats_start_y;
-                    let shape_idx = stats_row / item_height;
-                    let sub_row = stats_row % item_height;
-
-                    if shape_idx < items_count {
-                        let shape = TetrominoShape::from_index(shape_idx);
-                        match sub_row {
-                            0 | 1 => {
-                                let icon = self.get_mini_icon(shape, sub_row);
-                                print!("{} ", icon);
-                                if sub_row == 0 {
-                                    let count = game.piece_stats[shape_idx];
-                                    let total: u32 = game.piece_stats.iter().sum();
-                                    let pct = if total > 0 { (count as f32 / total as f32) * 100.0 } else { 0.0 };
-                                    print!(": {} ({:.1}%)", count, pct);
-                                }
-                            },
-                            2 => print!("-------------"), // Separator
-                            _ => {}
+        self.shown = canvas.cells;
+        Ok(())
+    }
+}
+
+impl Drop for ConsoleRenderer {
+    fn drop(&mut self) {
+        let _ = execute!(self.stdout, ResetColor, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// What the loop around the game is doing, drawn over the board
+pub enum Status {
+    Playing,
+    Paused,
+    Finished(Option<BestResult>),
+}
+
+// Runs games in the terminal until the player quits
+pub fn run(ctx: &SceneContext, mode: GameMode, start_level: Option<u32>) -> io::Result<()> {
+    let mut renderer = ConsoleRenderer::new()?;
+    let mut instance = start(ctx, mode, start_level);
+    let mut status = Status::Playing;
+
+    loop {
+        // Wait for keys until the next frame is due, without blocking the simulation
+        let frame_end = Instant::now() + FRAME_TIME;
+        while event::poll(frame_end.saturating_duration_since(Instant::now()))? {
+            let Event::Key(key) = event::read()? else { continue };
+            // Terminals without key release events only report presses and repeats
+            if key.kind == KeyEventKind::Release {
+                continue;
+            }
+            match handle_key(&mut instance, &status, key) {
+                Command::None => {}
+                Command::Quit => return Ok(()),
+                Command::TogglePause => {
+                    status = match status {
+                        Status::Playing => Status::Paused,
+                        _ => {
+                            instance.resume(Instant::now());
+                            Status::Playing
                         }
-                    }
+                    };
+                }
+                Command::Restart => {
+                    instance = start(ctx, mode, start_level);
+                    status = Status::Playing;
                 }
-                _ => {}
             }
+        }
+
+        if matches!(status, Status::Playing) {
+            instance.update(&ctx.config.handling, Instant::now());
+            instance.take_events();
+            if instance.session.is_finished() {
+                status = Status::Finished(finish(ctx, &instance));
+            }
+        }
+
+        renderer.render(&instance, ctx.theme(), &status)?;
+    }
+}
+
+fn start(ctx: &SceneContext, mode: GameMode, start_level: Option<u32>) -> GameInstance {
+    let now = Instant::now();
+    let config = match start_level {
+        Some(level) => game_config(ctx, mode).with_start_level(level),
+        None => game_config(ctx, mode),
+    };
+    let mut instance = GameInstance::from_session(Session::new(Game::with_config(config), mode), now);
+    if ctx.autoplay {
+        instance.set_bot(Some(BotController::new(ctx.config.bot.actions_per_second, now)));
+    }
+    instance
+}
+
+// Saves the replay and, for games the player played, the personal best, like the results screen
+fn finish(ctx: &SceneContext, instance: &GameInstance) -> Option<BestResult> {
+    let path = storage::last_replay_path();
+    if let Err(e) = instance.session.to_replay().save(&path) {
+        tracing::error!("Could not save replay to {}: {}", path.display(), e);
+    }
+    (!ctx.autoplay).then(|| highscores::record_game(instance.session.mode.id(), &instance.session))
+}
+
+enum Command {
+    None,
+    Quit,
+    TogglePause,
+    Restart,
+}
+
+// The same keys as the window, except that soft drop moves one cell per press (or key repeat),
+// since terminals can't tell when a key is released
+fn handle_key(instance: &mut GameInstance, status: &Status, key: KeyEvent) -> Command {
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        return Command::Quit;
+    }
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => return Command::Quit,
+        KeyCode::Char('p') if !matches!(status, Status::Finished(_)) => return Command::TogglePause,
+        KeyCode::Char('r') if matches!(status, Status::Finished(_)) => return Command::Restart,
+        _ => {}
+    }
+    if !matches!(status, Status::Playing) || instance.is_bot_controlled() {
+        return Command::None;
+    }
+
+    let action = match key.code {
+        KeyCode::Left => Action::MoveLeft,
+        KeyCode::Right => Action::MoveRight,
+        KeyCode::Down => Action::SoftDrop,
+        KeyCode::Up | KeyCode::Char('x') => Action::RotateCw,
+        KeyCode::Char('z') => Action::RotateCcw,
+        KeyCode::Char('a') => Action::Rotate180,
+        KeyCode::Char(' ') => Action::HardDrop,
+        KeyCode::Char('c') => Action::Hold,
+        _ => return Command::None,
+    };
+    // Repeats only slide and soft drop, like the window's key handling
+    if key.kind == KeyEventKind::Repeat && !matches!(action, Action::MoveLeft | Action::MoveRight | Action::SoftDrop) {
+        return Command::None;
+    }
+    instance.apply(action);
+    Command::None
+}
+
+fn draw(instance: &GameInstance, theme: &Theme, status: &Status) -> Canvas {
+    let mut canvas = Canvas::new();
+    let snapshot = instance.snapshot();
+    draw_board(&mut canvas, &snapshot, theme);
+    draw_panel(&mut canvas, instance, &snapshot, theme);
+
+    let overlay = match status {
+        Status::Playing => None,
+        Status::Paused => Some(("PAUSED", "P: RESUME")),
+        Status::Finished(_) if instance.session.is_complete() => Some(("CLEAR!", "R: RETRY Q: QUIT")),
+        // Named after the rule that ended it
+        Status::Finished(_) => Some((instance.game().top_out.map_or("GAME OVER", TopOutKind::name), "R: RETRY Q: QUIT")),
+    };
+    if let Some((headline, hint)) = overlay {
+        let y = VISIBLE_HEIGHT / 2 - 1;
+        canvas.text(1 + (WIDTH * 2 - headline.len()) / 2, y, headline, TEXT_COLOR);
+        canvas.text(1 + (WIDTH * 2 - hint.len()) / 2, y + 1, hint, LABEL_COLOR);
+    }
+    if let Status::Finished(Some(best)) = status
+        && best.is_new_best
+    {
+        canvas.text(1, VISIBLE_HEIGHT / 2 + 1, "NEW PERSONAL BEST!", BEST_COLOR);
+    }
+    canvas
+}
+
+fn draw_board(canvas: &mut Canvas, snapshot: &BoardSnapshot, theme: &Theme) {
+    let border = if snapshot.danger { DANGER_COLOR } else { LABEL_COLOR };
+    for y in 0..VISIBLE_HEIGHT {
+        canvas.text(0, y, "|", border);
+        canvas.text(BOARD_RIGHT, y, "|", border);
+    }
+    canvas.text(0, VISIBLE_HEIGHT, &format!("+{}+", "-".repeat(WIDTH * 2)), border);
+
+    for (y, row) in snapshot.grid[HIDDEN_ROWS..].iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            if cell == 0 {
+                canvas.text(1 + x * 2, y, " .", EMPTY_COLOR);
+            } else if !snapshot.invisible {
+                canvas.text(1 + x * 2, y, "[]", to_color(theme.cell_color(&snapshot.piece_set, cell)));
+            }
+        }
+    }
 
-            println!("\r"); // \r required in raw mode
+    if let Some((shape, cells)) = &snapshot.current_piece {
+        let color = to_color(theme.piece_color(&snapshot.piece_set, *shape));
+        for (x, y) in snapshot.ghost_cells().filter(|_| theme.ghost).unwrap_or_default() {
+            if let Some((x, y)) = board_position(x, y) {
+                canvas.text(x, y, "::", color);
+            }
         }
-        
-        if game.is_game_over {
-            println!("GAME OVER! Press 'q' to quit.\r");
+        for &(x, y) in cells {
+            if let Some((x, y)) = board_position(x, y) {
+                canvas.text(x, y, "[]", color);
+            }
         }
     }
+}
+
+// Where a grid cell goes on the canvas, None in the hidden rows
+fn board_position(x: i32, y: i32) -> Option<(usize, usize)> {
+    let row = usize::try_from(y).ok()?.checked_sub(HIDDEN_ROWS)?;
+    let column = usize::try_from(x).ok().filter(|&x| x < WIDTH)?;
+    (row < VISIBLE_HEIGHT).then_some((1 + column * 2, row))
+}
 
-    fn get_mini_icon(&self, shape: TetrominoShape, row: usize) -> &str {
-        match (shape, row) {
-            // I: 4 wide
-            (TetrominoShape::I, 0) => "[][][][]",
-            (TetrominoShape::I, 1) => "        ",
-            
-            // O: 2x2
-            (TetrominoShape::O, 0) => "  [][]  ",
-            (TetrominoShape::O, 1) => "  [][]  ",
-    
-            // T: 3 wide top, 1 bottom middle
-            (TetrominoShape::T, 0) => " [][][] ",
-            (TetrominoShape::T, 1) => "   []   ",
-    
-            // S: 2 top right, 2 bottom left (offset)
-            // S coords: (0,0), (-1,0) [Top Left], (0,1), (1,1) [Bottom Right]
-            // Top: [ ][ ] . Bottom:     [ ][ ]
-            (TetrominoShape::S, 0) => " [][]   ",  
-            (TetrominoShape::S, 1) => "   [][] ",
-    
-            // Z: 2 top left, 2 bottom right (offset)
-            // Z coords: (0,0), (1,0) [Top Right], (0,1), (-1,1) [Bottom Left]
-            (TetrominoShape::Z, 0) => "   [][] ",
-            (TetrominoShape::Z, 1) => " [][]   ",
-    
-            // J: 3 top, 1 bottom left
-            (TetrominoShape::J, 0) => " [][][] ",
-            (TetrominoShape::J, 1) => " []     ",
-    
-            // L: 3 top, 1 bottom right
-            (TetrominoShape::L, 0) => " [][][] ",
-            (TetrominoShape::L, 1) => "     [] ",
-    
-            _ => "        "
+fn draw_panel(canvas: &mut Canvas, instance: &GameInstance, snapshot: &BoardSnapshot, theme: &Theme) {
+    let session = &instance.session;
+    canvas.text(PANEL_X, 0, "HOLD", LABEL_COLOR);
+    canvas.text(NEXT_X, 0, "NEXT", LABEL_COLOR);
+    if let Some(shape) = snapshot.hold_piece {
+        draw_mini(canvas, PANEL_X, 1, snapshot, theme, shape);
+    }
+    draw_mini(canvas, NEXT_X, 1, snapshot, theme, snapshot.next_piece);
+
+    let mut rows = vec![
+        ("SCORE", snapshot.score.to_string()),
+        ("LINES", match session.line_goal() {
+            Some(goal) => format!("{}/{}", snapshot.lines_cleared.min(goal), goal),
+            None => snapshot.lines_cleared.to_string(),
+        }),
+    ];
+    if session.mode.has_levels() {
+        rows.push(("LEVEL", snapshot.level.to_string()));
+    }
+    if session.is_timed() {
+        rows.push(("TIME", highscores::format_time(session.tick)));
+    }
+    if let Some(goal) = session.dig_goal() {
+        rows.push(("GARBAGE", format!("{}/{}", session.game.garbage_cleared.min(goal), goal)));
+    }
+    if let Some(metrics) = &snapshot.metrics {
+        rows.push(("PPS", format!("{:.2}", metrics.pps)));
+        rows.push(("APM", format!("{:.0}", metrics.apm)));
+    }
+    let mut y = 6;
+    for (label, value) in rows {
+        canvas.text(PANEL_X, y, label, LABEL_COLOR);
+        canvas.text(PANEL_X + 8, y, &value, TEXT_COLOR);
+        y += 1;
+    }
+
+    // Pieces dealt by shape, in their colors
+    y += 1;
+    for (i, &count) in snapshot.piece_stats.iter().enumerate() {
+        let shape = TetrominoShape::from_index(i);
+        let text = format!("{} {}", snapshot.piece_set.letter(shape), count);
+        let color = to_color(theme.piece_color(&snapshot.piece_set, shape));
+        canvas.text(PANEL_X + (i % STATS_PER_LINE) * 7, y + i / STATS_PER_LINE, &text, color);
+    }
+    y += snapshot.piece_stats.len().div_ceil(STATS_PER_LINE) + 1;
+
+    for help in ["ARROWS MOVE/DROP", "Z X A  ROTATE", "SPACE  HARD DROP", "C HOLD  P PAUSE  Q QUIT"] {
+        canvas.text(PANEL_X, y, help, EMPTY_COLOR);
+        y += 1;
+    }
+}
+
+// A shape in the hold or next box, up to 4 rows of it
+fn draw_mini(canvas: &mut Canvas, x: usize, y: usize, snapshot: &BoardSnapshot, theme: &Theme, shape: TetrominoShape) {
+    let cells = snapshot.piece_set.cells(shape);
+    let min_x = cells.iter().map(|&(cx, _)| cx).min().unwrap_or(0);
+    let min_y = cells.iter().map(|&(_, cy)| cy).min().unwrap_or(0);
+    let color = to_color(theme.piece_color(&snapshot.piece_set, shape));
+    for &(cx, cy) in cells {
+        let (column, row) = ((cx - min_x) as usize, (cy - min_y) as usize);
+        if row < 4 {
+            canvas.text(x + column * 2, y + row, "[]", color);
         }
     }
 }
+
+fn to_color([r, g, b, _]: [f32; 4]) -> Color {
+    Color::Rgb { r: (r * 255.0) as u8, g: (g * 255.0) as u8, b: (b * 255.0) as u8 }
+}
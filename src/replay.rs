@@ -0,0 +1,67 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::action::Action;
+use crate::mode::GameMode;
+use crate::puzzle::Puzzle;
+use crate::rules::RuleSet;
+
+/// One recorded input and the simulation tick it happened on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayInput {
+    pub tick: u64,
+    pub action: Action,
+}
+
+/// Everything needed to re-simulate a game: the seed, the mode, the rules and every input.
+///
+/// Stored as JSON, see [`Replay::save`] and [`Replay::load`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub mode: GameMode,
+    /// Missing in replays recorded before rule sets were saved, those used the defaults
+    #[serde(default)]
+    pub rules: RuleSet,
+    #[serde(default = "default_start_level")]
+    pub start_level: u32,
+    /// The puzzle the game was played on, its board and pieces aren't part of the seed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub puzzle: Option<Puzzle>,
+    /// The tick the recording stopped at
+    pub end_tick: u64,
+    pub inputs: Vec<ReplayInput>,
+}
+
+impl Replay {
+    /// Bumped whenever a change to the simulation would make old replays play out differently
+    pub const VERSION: u32 = 4;
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        let json = fs::read_to_string(path)?;
+        let replay: Replay = serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if replay.version != Replay::VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("replay version {} is not supported (expected {})", replay.version, Replay::VERSION),
+            ));
+        }
+        Ok(replay)
+    }
+}
+
+fn default_start_level() -> u32 {
+    1
+}
@@ -0,0 +1,38 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+// A tiny deterministic random number generator (SplitMix64).
+// We own the algorithm instead of using `StdRng` so that the same seed produces the
+// same piece sequence on every platform and every version of the `rand` crate,
+// which is what makes seeded games reproducible.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    pub fn seed_from(seed: u64) -> Self {
+        GameRng { state: seed }
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+use crate::piece_set::PieceSet;
+use crate::session::DEFAULT_GRAVITY_TICKS;
+
+/// The scoring and gameplay rules a [`Game`](crate::game::Game) is played with.
+///
+/// New rules get added over time, so construct it with `RuleSet::default()` and
+/// override the fields you care about.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct RuleSet {
+    /// Points per cell moved by a soft drop
+    pub soft_drop_points: u32,
+    /// Points per cell moved by a hard drop
+    pub hard_drop_points: u32,
+    /// Points for clearing 1, 2, 3 and 4 lines at once
+    pub line_clear_points: [u32; 4],
+    /// Garbage lines sent to the opponent in versus for clearing 1, 2, 3 and 4 lines at once
+    pub garbage_lines: [u32; 4],
+    /// Ticks between two gravity steps
+    pub gravity_ticks: u64,
+    /// Rows of garbage ("cheese") the board starts with, each with its own hole
+    pub starting_garbage: u32,
+    /// Garbage rows kept on the board: after a clear, new ones rise from the bottom until there are this many again
+    pub cheese_rows: u32,
+    /// Garbage rows to clear to finish the game. Refilling stops once the rest of the goal is on the board.
+    pub dig_goal: Option<u32>,
+    /// Lines to clear to finish the game, overrides the goal of the mode
+    pub line_goal: Option<u32>,
+    /// Locked blocks are not drawn, only the falling piece is visible
+    pub invisible: bool,
+    /// The falling piece can be put aside and swapped back in later
+    pub hold: bool,
+    /// What happens to the blocks above a cleared row
+    pub clear_gravity: ClearGravity,
+    /// The shapes dealt, left out of saved rules when it's the standard 7
+    #[serde(skip_serializing_if = "PieceSet::is_standard")]
+    pub piece_set: PieceSet,
+}
+
+/// How blocks above cleared rows come down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ClearGravity {
+    /// Everything above moves down by the number of rows cleared, holes stay holes
+    #[default]
+    Naive,
+    /// Groups of touching blocks fall until they land, and can complete more rows (chains)
+    Sticky,
+    /// Every block falls on its own, filling every hole below it, with chains
+    Cascade,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet {
+            soft_drop_points: 1,
+            hard_drop_points: 2,
+            line_clear_points: [100, 300, 500, 800],
+            garbage_lines: [0, 1, 2, 4],
+            gravity_ticks: DEFAULT_GRAVITY_TICKS,
+            starting_garbage: 0,
+            cheese_rows: 0,
+            dig_goal: None,
+            line_goal: None,
+            invisible: false,
+            hold: true,
+            clear_gravity: ClearGravity::Naive,
+            piece_set: PieceSet::standard(),
+        }
+    }
+}
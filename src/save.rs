@@ -0,0 +1,137 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use learning1::session::Session;
+
+use crate::storage;
+
+// A game put aside with "Save & Quit", resumed from the title screen with "Continue".
+// The whole session is stored, so the replay of a resumed game is still complete.
+#[derive(Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    pub session: Session,
+}
+
+// Only the version, read before the rest: a save whose board or pieces have another shape
+// wouldn't parse, and should be reported as being from another version rather than broken
+#[derive(Deserialize)]
+struct SaveVersion {
+    version: u32,
+}
+
+impl SaveGame {
+    // Bumped when the saved data changes shape, older saves are then ignored.
+    // 3: the grid has the hidden rows above the visible board.
+    pub const VERSION: u32 = 3;
+
+    pub fn path() -> PathBuf {
+        storage::data_dir().join("save.json")
+    }
+
+    pub fn exists() -> bool {
+        Self::path().exists()
+    }
+
+    pub fn write(session: &Session) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, Self::to_json(session)?)
+    }
+
+    // Loading consumes the save, a game can only be continued once
+    pub fn take() -> io::Result<Session> {
+        let path = Self::path();
+        let json = fs::read_to_string(&path)?;
+        fs::remove_file(&path)?;
+        Self::from_json(&json)
+    }
+
+    fn to_json(session: &Session) -> io::Result<String> {
+        let save = SaveGame { version: Self::VERSION, session: session.clone() };
+        serde_json::to_string(&save).map_err(io::Error::other)
+    }
+
+    fn from_json(json: &str) -> io::Result<Session> {
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+        let saved: SaveVersion = serde_json::from_str(json).map_err(invalid)?;
+        if saved.version != Self::VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "save file is from an incompatible version"));
+        }
+        let save: SaveGame = serde_json::from_str(json).map_err(invalid)?;
+        Ok(save.session)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use learning1::action::Action;
+    use learning1::game::{Game, GameConfig, MAX_LEVEL, WIDTH};
+    use learning1::mode::GameMode;
+
+    fn session_in_progress() -> Session {
+        let mut session = Session::new(Game::with_config(GameConfig::default().with_seed(3)), GameMode::Marathon);
+        for action in [Action::MoveLeft, Action::HardDrop, Action::RotateCw, Action::Hold, Action::HardDrop] {
+            for _ in 0..200 {
+                session.step();
+            }
+            session.apply(action);
+        }
+        session
+    }
+
+    #[test]
+    fn saved_game_continues_where_it_was_left() {
+        let session = session_in_progress();
+        let restored = SaveGame::from_json(&SaveGame::to_json(&session).unwrap()).unwrap();
+
+        assert_eq!(restored.tick, session.tick);
+        assert_eq!(restored.mode, session.mode);
+        assert_eq!(restored.game.snapshot(), session.game.snapshot());
+        assert_eq!(restored.to_replay(), session.to_replay());
+
+        // Same seed, same position in the sequence: both deal the same pieces from here on
+        let (mut a, mut b) = (session, restored);
+        for _ in 0..5 {
+            a.apply(Action::HardDrop);
+            b.apply(Action::HardDrop);
+        }
+        assert_eq!(a.game.snapshot(), b.game.snapshot());
+    }
+
+    #[test]
+    fn saved_start_level_out_of_range_is_clamped() {
+        let mut save: serde_json::Value = serde_json::from_str(&SaveGame::to_json(&session_in_progress()).unwrap()).unwrap();
+        save["session"]["game"]["start_level"] = (MAX_LEVEL + 10).into();
+
+        let restored = SaveGame::from_json(&save.to_string()).unwrap();
+        assert_eq!(restored.game.start_level, MAX_LEVEL);
+    }
+
+    #[test]
+    fn saved_garbage_hole_out_of_range_is_clamped() {
+        let mut save: serde_json::Value = serde_json::from_str(&SaveGame::to_json(&session_in_progress()).unwrap()).unwrap();
+        save["session"]["game"]["pending_garbage"] = serde_json::json!([[1, WIDTH + 5]]);
+
+        let mut restored = SaveGame::from_json(&save.to_string()).unwrap();
+        assert_eq!(restored.game.pending_garbage, vec![(1, WIDTH - 1)]);
+        // The garbage rises with the next lock
+        restored.apply(Action::HardDrop);
+        assert!(restored.game.grid.iter().any(|row| row[WIDTH - 1] == 0 && row[..WIDTH - 1].iter().all(|&cell| cell != 0)));
+    }
+
+    #[test]
+    fn save_from_another_version_is_rejected() {
+        let mut save: serde_json::Value = serde_json::from_str(&SaveGame::to_json(&session_in_progress()).unwrap()).unwrap();
+        save["version"] = (SaveGame::VERSION + 1).into();
+
+        let error = SaveGame::from_json(&save.to_string()).err().expect("the save is rejected");
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
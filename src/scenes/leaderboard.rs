@@ -0,0 +1,83 @@
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+use crate::graphic_context::{QuadInstance, TextEntry};
+use crate::leaderboard::LeaderboardState;
+use crate::scenes::{self, KeyInput, MouseInput, Scene, SceneContext, Transition};
+
+const UI_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+const OWN_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+const ROWS_TOP: f32 = 8.0;
+const ROW_SPACING: f32 = 1.5;
+// Longer names are cut so the scores stay in their column
+const NAME_LENGTH: usize = 12;
+
+// The online top 10 of a mode, pushed from the results screen
+pub struct LeaderboardScene {
+    // Shown under the heading
+    title: String,
+    state: LeaderboardState,
+}
+
+impl LeaderboardScene {
+    pub fn new(title: String, state: LeaderboardState) -> Self {
+        LeaderboardScene { title, state }
+    }
+}
+
+impl Scene for LeaderboardScene {
+    fn update(&mut self, _ctx: &mut SceneContext, _now: Instant) -> Transition {
+        self.state.poll();
+        Transition::None
+    }
+
+    fn handle_key(&mut self, _ctx: &mut SceneContext, input: KeyInput) -> Transition {
+        if input.just_pressed(KeyCode::Escape) || input.just_pressed(KeyCode::Enter) || input.just_pressed(KeyCode::Space) {
+            return Transition::Pop;
+        }
+        Transition::None
+    }
+
+    fn handle_mouse(&mut self, _ctx: &mut SceneContext, input: MouseInput) -> Transition {
+        match input {
+            MouseInput::Clicked { .. } => Transition::Pop,
+            MouseInput::Moved { .. } => Transition::None,
+        }
+    }
+
+    fn next_update(&self, _ctx: &SceneContext, now: Instant) -> Option<Instant> {
+        self.state.is_loading().then(|| now + scenes::NETWORK_POLL_INTERVAL)
+    }
+
+    fn render(&self, ctx: &SceneContext) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+        let mut text = vec![
+            TextEntry { text: "TOP 10".to_string(), x: 10.0, y: 4.0, color: [1.0, 1.0, 1.0, 1.0], scale: 1.2 },
+            TextEntry { text: self.title.clone(), x: 7.0, y: 5.8, color: UI_COLOR, scale: 0.7 },
+        ];
+
+        match &self.state {
+            LeaderboardState::Loading(_) => {
+                text.push(TextEntry { text: "LOADING...".to_string(), x: 7.0, y: ROWS_TOP, color: UI_COLOR, scale: 0.8 });
+            }
+            LeaderboardState::Failed => {
+                text.push(TextEntry { text: "LEADERBOARD UNAVAILABLE".to_string(), x: 4.0, y: ROWS_TOP, color: UI_COLOR, scale: 0.8 });
+            }
+            LeaderboardState::Loaded(entries) if entries.is_empty() => {
+                text.push(TextEntry { text: "NO SCORES YET".to_string(), x: 7.0, y: ROWS_TOP, color: UI_COLOR, scale: 0.8 });
+            }
+            LeaderboardState::Loaded(entries) => {
+                let own_name = &ctx.config.leaderboard.name;
+                for (i, entry) in entries.iter().enumerate() {
+                    let y = ROWS_TOP + i as f32 * ROW_SPACING;
+                    let color = if &entry.name == own_name { OWN_COLOR } else { [1.0, 1.0, 1.0, 1.0] };
+                    let name: String = entry.name.chars().take(NAME_LENGTH).collect();
+                    text.push(TextEntry { text: format!("{}.", i + 1), x: 4.0, y, color: UI_COLOR, scale: 0.8 });
+                    text.push(TextEntry { text: name.to_uppercase(), x: 6.0, y, color, scale: 0.8 });
+                    text.push(TextEntry { text: entry.score.to_string(), x: 17.0, y, color, scale: 0.8 });
+                }
+            }
+        }
+
+        (Vec::new(), text)
+    }
+}
@@ -0,0 +1,119 @@
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+use learning1::game::MAX_LEVEL;
+use learning1::mode::GameMode;
+use learning1::session::{gravity_for_level, TICKS_PER_SECOND};
+use crate::graphic_context::{QuadInstance, TextEntry};
+use crate::scenes::playing::PlayingScene;
+use crate::scenes::{KeyInput, MouseInput, Scene, SceneContext, Transition};
+
+const UI_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+const SELECTED_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+// Levels are laid out in rows of 5
+const COLUMNS: u32 = 5;
+const CELL_WIDTH: f32 = 3.0;
+const CELL_HEIGHT: f32 = 2.0;
+const GRID_X: f32 = 6.0;
+const GRID_Y: f32 = 9.0;
+
+// Picks the level a game starts at, with the arrow keys or the mouse
+pub struct LevelSelectScene {
+    mode: GameMode,
+    level: u32,
+}
+
+impl LevelSelectScene {
+    pub fn new(mode: GameMode) -> Self {
+        LevelSelectScene { mode, level: 1 }
+    }
+
+    fn start(&self, ctx: &SceneContext) -> Transition {
+        Transition::Replace(Box::new(PlayingScene::with_start_level(ctx, self.mode, self.level)))
+    }
+
+    fn cell_position(level: u32) -> (f32, f32) {
+        let index = level - 1;
+        (
+            GRID_X + (index % COLUMNS) as f32 * CELL_WIDTH,
+            GRID_Y + (index / COLUMNS) as f32 * CELL_HEIGHT,
+        )
+    }
+
+    fn level_at(x: f32, y: f32) -> Option<u32> {
+        (1..=MAX_LEVEL).find(|&level| {
+            let (cell_x, cell_y) = Self::cell_position(level);
+            (cell_x..cell_x + CELL_WIDTH).contains(&x) && (cell_y..cell_y + CELL_HEIGHT).contains(&y)
+        })
+    }
+}
+
+impl Scene for LevelSelectScene {
+    fn handle_key(&mut self, ctx: &mut SceneContext, input: KeyInput) -> Transition {
+        if !input.pressed {
+            return Transition::None;
+        }
+
+        match input.code {
+            KeyCode::Escape => return Transition::Pop,
+            KeyCode::Enter | KeyCode::Space if !input.repeat => return self.start(ctx),
+            KeyCode::ArrowLeft => self.level = self.level.saturating_sub(1).max(1),
+            KeyCode::ArrowRight => self.level = (self.level + 1).min(MAX_LEVEL),
+            KeyCode::ArrowUp if self.level > COLUMNS => self.level -= COLUMNS,
+            KeyCode::ArrowDown if self.level + COLUMNS <= MAX_LEVEL => self.level += COLUMNS,
+            _ => {}
+        }
+        Transition::None
+    }
+
+    fn handle_mouse(&mut self, ctx: &mut SceneContext, input: MouseInput) -> Transition {
+        let (x, y) = input.position();
+        let Some(level) = Self::level_at(x, y) else {
+            return Transition::None;
+        };
+
+        self.level = level;
+        match input {
+            MouseInput::Clicked { .. } => self.start(ctx),
+            MouseInput::Moved { .. } => Transition::None,
+        }
+    }
+
+    // Menus only change with input
+    fn next_update(&self, _ctx: &SceneContext, _now: Instant) -> Option<Instant> {
+        None
+    }
+
+    fn render(&self, _ctx: &SceneContext) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+        let mut text = vec![TextEntry {
+            text: "START LEVEL".to_string(),
+            x: 6.5,
+            y: 5.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            scale: 1.2,
+        }];
+
+        for level in 1..=MAX_LEVEL {
+            let (x, y) = Self::cell_position(level);
+            let selected = level == self.level;
+            text.push(TextEntry {
+                text: format!("{:>2}", level),
+                x,
+                y,
+                color: if selected { SELECTED_COLOR } else { UI_COLOR },
+                scale: if selected { 1.0 } else { 0.8 },
+            });
+        }
+
+        let seconds_per_row = gravity_for_level(self.level) as f32 / TICKS_PER_SECOND as f32;
+        text.push(TextEntry {
+            text: format!("GRAVITY {:.3}S PER ROW", seconds_per_row),
+            x: 4.5,
+            y: 17.5,
+            color: UI_COLOR,
+            scale: 0.6,
+        });
+
+        (Vec::new(), text)
+    }
+}
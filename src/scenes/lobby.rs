@@ -0,0 +1,184 @@
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+use crate::graphic_context::{QuadInstance, TextEntry};
+use crate::net::{Connection, Host, Joining, NetMessage, PROTOCOL_VERSION};
+use crate::scenes::menu::Menu;
+use crate::scenes::online::OnlineScene;
+use crate::scenes::{self, KeyInput, MouseInput, Scene, SceneContext, Transition};
+
+const HOST: usize = 0;
+const JOIN: usize = 1;
+const UI_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+const ERROR_COLOR: [f32; 4] = [1.0, 0.3, 0.3, 1.0];
+
+// Host/join flow for online matches. The host picks the seed and sends it first,
+// the match starts on both sides as soon as the joiner has it.
+pub struct LobbyScene {
+    menu: Menu,
+    state: LobbyState,
+    // Shown under the menu, e.g. why the last attempt failed
+    status: Option<String>,
+}
+
+enum LobbyState {
+    Choosing,
+    Hosting(Host),
+    Joining(Joining),
+    // Connected to a host, waiting for its Hello
+    Handshake(Connection),
+}
+
+impl LobbyScene {
+    pub fn new(ctx: &SceneContext) -> Self {
+        let items = vec![
+            "HOST GAME".to_string(),
+            format!("JOIN {}", ctx.config.online.join_address),
+            "BACK".to_string(),
+        ];
+        LobbyScene {
+            menu: Menu::new(items, 5.0, 10.0),
+            state: LobbyState::Choosing,
+            status: None,
+        }
+    }
+
+    fn activate(&mut self, ctx: &mut SceneContext, index: usize) -> Transition {
+        match index {
+            HOST => match Host::listen(ctx.config.online.port) {
+                Ok(host) => {
+                    self.state = LobbyState::Hosting(host);
+                    self.status = None;
+                }
+                Err(e) => self.status = Some(format!("CAN'T HOST: {}", e)),
+            },
+            JOIN => {
+                self.state = LobbyState::Joining(Joining::start(&ctx.config.online.join_address));
+                self.status = None;
+            }
+            _ => return Transition::Pop,
+        }
+        Transition::None
+    }
+
+    fn fail(&mut self, message: String) {
+        self.state = LobbyState::Choosing;
+        self.status = Some(message);
+    }
+}
+
+impl Scene for LobbyScene {
+    fn update(&mut self, ctx: &mut SceneContext, _now: Instant) -> Transition {
+        match &mut self.state {
+            LobbyState::Choosing => {}
+            LobbyState::Hosting(host) => match host.accept() {
+                Ok(Some(mut connection)) => {
+                    let seed = ctx.seed.unwrap_or_else(rand::random);
+                    let pauses = ctx.config.online.pauses_per_match;
+                    connection.send(&NetMessage::Hello { version: PROTOCOL_VERSION, seed, pauses });
+                    return Transition::Replace(Box::new(OnlineScene::new(connection, seed, pauses)));
+                }
+                Ok(None) => {}
+                Err(e) => self.fail(format!("HOSTING FAILED: {}", e)),
+            },
+            LobbyState::Joining(joining) => match joining.poll() {
+                Ok(Some(connection)) => self.state = LobbyState::Handshake(connection),
+                Ok(None) => {}
+                Err(e) => self.fail(format!("CAN'T CONNECT: {}", e)),
+            },
+            LobbyState::Handshake(connection) => match connection.poll() {
+                Ok(mut messages) => {
+                    let Some(index) = messages.iter().position(|message| matches!(message, NetMessage::Hello { .. })) else {
+                        return Transition::None;
+                    };
+                    // The host may have sent garbage or its board right behind the Hello, the match handles those
+                    let rest = messages.split_off(index + 1);
+                    match messages.pop() {
+                        Some(NetMessage::Hello { version: PROTOCOL_VERSION, seed, pauses }) => {
+                            let LobbyState::Handshake(mut connection) = std::mem::replace(&mut self.state, LobbyState::Choosing) else {
+                                unreachable!()
+                            };
+                            connection.requeue(rest);
+                            return Transition::Replace(Box::new(OnlineScene::new(connection, seed, pauses)));
+                        }
+                        Some(NetMessage::Hello { version, .. }) => self.fail(format!("HOST RUNS VERSION {}, WE RUN {}", version, PROTOCOL_VERSION)),
+                        _ => unreachable!(),
+                    }
+                }
+                Err(e) => self.fail(format!("CONNECTION LOST: {}", e)),
+            },
+        }
+        Transition::None
+    }
+
+    fn handle_key(&mut self, ctx: &mut SceneContext, input: KeyInput) -> Transition {
+        if input.just_pressed(KeyCode::Escape) {
+            // Stop waiting first, leave on the second press
+            if matches!(self.state, LobbyState::Choosing) {
+                return Transition::Pop;
+            }
+            self.state = LobbyState::Choosing;
+            return Transition::None;
+        }
+
+        if !matches!(self.state, LobbyState::Choosing) {
+            return Transition::None;
+        }
+        match self.menu.handle_key(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    fn handle_mouse(&mut self, ctx: &mut SceneContext, input: MouseInput) -> Transition {
+        if !matches!(self.state, LobbyState::Choosing) {
+            return Transition::None;
+        }
+        match self.menu.handle_mouse(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    // Checks on the connection while hosting or joining, the menu itself only changes with input
+    fn next_update(&self, _ctx: &SceneContext, now: Instant) -> Option<Instant> {
+        match self.state {
+            LobbyState::Choosing => None,
+            _ => Some(now + scenes::NETWORK_POLL_INTERVAL),
+        }
+    }
+
+    // Whoever connects shouldn't have to wait for the window to get the focus back
+    fn runs_unfocused(&self) -> bool {
+        true
+    }
+
+    fn render(&self, _ctx: &SceneContext) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+        let mut text = vec![TextEntry {
+            text: "ONLINE".to_string(),
+            x: 9.5,
+            y: 5.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            scale: 1.2,
+        }];
+
+        let waiting = match &self.state {
+            LobbyState::Choosing => None,
+            LobbyState::Hosting(_) => Some("WAITING FOR A PLAYER...".to_string()),
+            LobbyState::Joining(_) | LobbyState::Handshake(_) => Some("CONNECTING...".to_string()),
+        };
+        match waiting {
+            Some(message) => {
+                text.push(TextEntry { text: message, x: 5.0, y: 10.0, color: UI_COLOR, scale: 0.8 });
+                text.push(TextEntry { text: "ESC TO CANCEL".to_string(), x: 5.0, y: 12.0, color: UI_COLOR, scale: 0.6 });
+            }
+            None => text.extend(self.menu.text_entries()),
+        }
+
+        if let Some(status) = &self.status {
+            text.push(TextEntry { text: status.clone(), x: 2.0, y: 16.0, color: ERROR_COLOR, scale: 0.6 });
+        }
+
+        (Vec::new(), text)
+    }
+}
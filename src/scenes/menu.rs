@@ -0,0 +1,82 @@
+use winit::keyboard::KeyCode;
+
+use crate::graphic_context::TextEntry;
+use crate::layout::Rect;
+use crate::scenes::{KeyInput, MouseInput};
+
+const SELECTED_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+const ITEM_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+const ITEM_SPACING: f32 = 1.5;
+// Hit area width in logical units, wide enough for the longest labels
+const ITEM_WIDTH: f32 = 14.0;
+
+// A vertical list of text items, navigated with the arrow keys or the mouse.
+// (x, y) is the logical position of the first item.
+pub struct Menu {
+    pub items: Vec<String>,
+    pub selected: usize,
+    x: f32,
+    y: f32,
+}
+
+impl Menu {
+    pub fn new(items: Vec<String>, x: f32, y: f32) -> Self {
+        Menu { items, selected: 0, x, y }
+    }
+
+    // Returns the index of the activated item when Enter/Space is pressed
+    pub fn handle_key(&mut self, input: KeyInput) -> Option<usize> {
+        if !input.pressed || self.items.is_empty() {
+            return None;
+        }
+
+        match input.code {
+            KeyCode::ArrowUp => {
+                self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+                None
+            }
+            KeyCode::ArrowDown => {
+                self.selected = (self.selected + 1) % self.items.len();
+                None
+            }
+            KeyCode::Enter | KeyCode::Space if !input.repeat => Some(self.selected),
+            _ => None,
+        }
+    }
+
+    // Hovering selects an item, clicking activates it
+    pub fn handle_mouse(&mut self, input: MouseInput) -> Option<usize> {
+        let (x, y) = input.position();
+        let index = (0..self.items.len()).find(|&i| self.item_rect(i).contains(x, y))?;
+
+        self.selected = index;
+        match input {
+            MouseInput::Clicked { .. } => Some(index),
+            MouseInput::Moved { .. } => None,
+        }
+    }
+
+    // The area of an item, text is drawn from its top left corner
+    pub fn item_rect(&self, index: usize) -> Rect {
+        Rect {
+            x: self.x,
+            y: self.y + index as f32 * ITEM_SPACING,
+            w: ITEM_WIDTH,
+            h: ITEM_SPACING,
+        }
+    }
+
+    pub fn text_entries(&self) -> Vec<TextEntry> {
+        self.items.iter().enumerate().map(|(i, item)| {
+            let is_selected = i == self.selected;
+            let rect = self.item_rect(i);
+            TextEntry {
+                text: if is_selected { format!("> {}", item) } else { format!("  {}", item) },
+                x: rect.x,
+                y: rect.y,
+                color: if is_selected { SELECTED_COLOR } else { ITEM_COLOR },
+                scale: 0.8,
+            }
+        }).collect()
+    }
+}
@@ -0,0 +1,122 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+use learning1::mission::Mission;
+use crate::graphic_context::{QuadInstance, TextEntry};
+use crate::scenes::menu::Menu;
+use crate::scenes::playing::PlayingScene;
+use crate::scenes::{KeyInput, MouseInput, Scene, SceneContext, Transition};
+
+// Missions ship with the game like the puzzles
+const MISSION_DIR: &str = "assets/missions";
+const UI_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+
+// Lists the missions found in assets/missions, with the goals of the selected one
+pub struct MissionSelectScene {
+    missions: Vec<Mission>,
+    menu: Menu,
+}
+
+impl MissionSelectScene {
+    pub fn new() -> Self {
+        let missions = load_missions(Path::new(MISSION_DIR));
+        let mut items: Vec<String> = missions.iter().map(|mission| mission.name.clone()).collect();
+        items.push("BACK".to_string());
+
+        MissionSelectScene {
+            missions,
+            menu: Menu::new(items, 5.0, 7.0),
+        }
+    }
+
+    fn activate(&mut self, ctx: &mut SceneContext, index: usize) -> Transition {
+        match self.missions.get(index) {
+            Some(mission) => Transition::Replace(Box::new(PlayingScene::with_mission(ctx, mission.clone()))),
+            None => Transition::Pop,
+        }
+    }
+}
+
+// Every mission file in the directory, sorted by file name. Broken files are reported and skipped.
+fn load_missions(dir: &Path) -> Vec<Mission> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect(),
+        Err(e) => {
+            tracing::warn!("Could not read missions from {}: {}", dir.display(), e);
+            Vec::new()
+        }
+    };
+    paths.sort();
+
+    paths.iter().filter_map(|path| match Mission::load(path) {
+        Ok(mission) => Some(mission),
+        Err(e) => {
+            tracing::warn!("Skipping mission {}: {}", path.display(), e);
+            None
+        }
+    }).collect()
+}
+
+impl Scene for MissionSelectScene {
+    fn handle_key(&mut self, ctx: &mut SceneContext, input: KeyInput) -> Transition {
+        if input.just_pressed(KeyCode::Escape) {
+            return Transition::Pop;
+        }
+
+        match self.menu.handle_key(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    fn handle_mouse(&mut self, ctx: &mut SceneContext, input: MouseInput) -> Transition {
+        match self.menu.handle_mouse(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    fn next_update(&self, _ctx: &SceneContext, _now: Instant) -> Option<Instant> {
+        None
+    }
+
+    fn render(&self, _ctx: &SceneContext) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+        let mut text = vec![TextEntry {
+            text: "MISSIONS".to_string(),
+            x: 5.0,
+            y: 4.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            scale: 1.2,
+        }];
+        text.extend(self.menu.text_entries());
+
+        let details_y = self.menu.item_rect(self.menu.items.len()).y + 1.0;
+        match self.missions.get(self.menu.selected) {
+            Some(mission) => {
+                let mut details = vec![format!("FROM LEVEL {}", mission.start_level), mission.description.clone()];
+                details.extend(mission.goals.iter().enumerate().map(|(i, goal)| format!("{}. {}", i + 1, goal.describe())));
+                for (i, line) in details.into_iter().enumerate() {
+                    text.push(TextEntry { text: line, x: 5.0, y: details_y + i as f32, color: UI_COLOR, scale: 0.6 });
+                }
+            }
+            None if self.missions.is_empty() => {
+                text.push(TextEntry {
+                    text: format!("NO MISSIONS IN {}", MISSION_DIR),
+                    x: 5.0,
+                    y: details_y,
+                    color: UI_COLOR,
+                    scale: 0.6,
+                });
+            }
+            None => {}
+        }
+
+        (Vec::new(), text)
+    }
+}
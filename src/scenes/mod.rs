@@ -0,0 +1,269 @@
+// Scenes are the top level states of the application (title, playing, paused, results).
+// The App keeps a stack of them: only the top scene receives input and updates,
+// which makes overlays like the pause menu a simple push/pop.
+pub mod leaderboard;
+pub mod level_select;
+pub mod lobby;
+pub mod menu;
+pub mod mission_select;
+pub mod online;
+pub mod paused;
+pub mod playing;
+pub mod puzzle_select;
+pub mod replay;
+pub mod results;
+pub mod settings;
+pub mod title;
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+use learning1::action::{Action, Rotation};
+use learning1::mode::GameMode;
+use learning1::piece_set::PieceSet;
+use learning1::snapshot::BoardSnapshot;
+
+use crate::background::Background;
+use crate::config::{Config, CONFIG_FILE};
+use crate::graphic_context::{QuadInstance, TextEntry};
+use crate::theme::{Theme, THEME_DIR};
+
+// State shared by all scenes
+pub struct SceneContext {
+    pub config: Config,
+    // Where the settings screen writes the config back to
+    pub config_path: PathBuf,
+    // Fixed seed from the command line, every game started uses it when set
+    pub seed: Option<u64>,
+    // The bot plays instead of the keyboard
+    pub autoplay: bool,
+    // Every theme found, and the one in use
+    pub themes: Vec<Theme>,
+    pub theme_index: usize,
+    // The theme in use with the accessibility settings applied, what everything is drawn with
+    theme: Theme,
+    // Set when the theme changes, the App picks it up to reload the block tiles
+    pub theme_changed: bool,
+    // Set when the player scores a Tetris, the App picks it up for the recorder
+    pub tetris_scored: bool,
+    // The pieces and rotation system from rules.file, what new games are played with
+    pub pieces: PieceSet,
+    // Playing scenes report the level and line clears for the animated background
+    pub background: Background,
+}
+
+impl SceneContext {
+    pub fn new(config: Config, seed: Option<u64>, autoplay: bool) -> Self {
+        let themes = Theme::load_all(Path::new(THEME_DIR));
+        let theme_index = themes.iter().position(|theme| theme.name == config.display.theme).unwrap_or_else(|| {
+            tracing::warn!("Unknown theme {}, using {}", config.display.theme, themes[0].name);
+            0
+        });
+
+        let pieces = match &config.rules.file {
+            Some(path) => PieceSet::load(Path::new(path)).unwrap_or_else(|e| {
+                tracing::warn!("Could not load rules {}: {}, using the standard rules", path, e);
+                PieceSet::standard()
+            }),
+            None => PieceSet::standard(),
+        };
+
+        let mut ctx = SceneContext {
+            config,
+            config_path: PathBuf::from(CONFIG_FILE),
+            seed,
+            autoplay,
+            themes,
+            theme_index,
+            theme: Theme::default(),
+            theme_changed: false,
+            tetris_scored: false,
+            pieces,
+            background: Background::new(Instant::now()),
+        };
+        ctx.refresh_theme();
+        ctx
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    // Switches to the next theme, back to the first one after the last
+    pub fn next_theme(&mut self) {
+        self.set_theme((self.theme_index + 1) % self.themes.len());
+    }
+
+    pub fn previous_theme(&mut self) {
+        self.set_theme((self.theme_index + self.themes.len() - 1) % self.themes.len());
+    }
+
+    // The theme is also what the config starts with next time, if the settings screen saves it
+    fn set_theme(&mut self, index: usize) {
+        self.theme_index = index;
+        self.config.display.theme = self.themes[index].name.clone();
+        self.refresh_theme();
+    }
+
+    // To be called after changing the display settings
+    pub fn refresh_theme(&mut self) {
+        self.theme = self.themes[self.theme_index].with_settings(&self.config.display);
+        self.theme_changed = true;
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct KeyInput {
+    pub code: KeyCode,
+    pub pressed: bool,
+    pub repeat: bool,
+}
+
+impl KeyInput {
+    // True for a fresh key press, ignoring OS key repeat
+    pub fn just_pressed(&self, code: KeyCode) -> bool {
+        self.pressed && !self.repeat && self.code == code
+    }
+}
+
+// Mouse input, already converted to logical layout coordinates
+#[derive(Clone, Copy, Debug)]
+pub enum MouseInput {
+    Moved { x: f32, y: f32 },
+    Clicked { x: f32, y: f32 },
+}
+
+impl MouseInput {
+    pub fn position(&self) -> (f32, f32) {
+        match *self {
+            MouseInput::Moved { x, y } | MouseInput::Clicked { x, y } => (x, y),
+        }
+    }
+}
+
+// The game input bound to a key press. Rotations and drops need a fresh press.
+// Soft drop and the side moves are held rather than pressed, scenes track them separately.
+pub fn key_action(input: KeyInput) -> Option<Action> {
+    if !input.pressed {
+        return None;
+    }
+
+    match input.code {
+        KeyCode::ArrowUp | KeyCode::KeyX if !input.repeat => Some(Action::RotateCw),
+        KeyCode::KeyZ if !input.repeat => Some(Action::RotateCcw),
+        KeyCode::KeyA if !input.repeat => Some(Action::Rotate180),
+        KeyCode::Space if !input.repeat => Some(Action::HardDrop),
+        KeyCode::KeyC | KeyCode::ShiftLeft | KeyCode::ShiftRight if !input.repeat => Some(Action::Hold),
+        _ => None,
+    }
+}
+
+// The side move bound to a key, held down it repeats with the configured DAS and ARR
+pub fn shift_action(code: KeyCode) -> Option<Action> {
+    match code {
+        KeyCode::ArrowLeft => Some(Action::MoveLeft),
+        KeyCode::ArrowRight => Some(Action::MoveRight),
+        _ => None,
+    }
+}
+
+// Rotation and hold keys also arm IRS/IHS: while one is down, every new piece spawns already
+// turned (or held). Pressing sends the armed state, releasing clears it.
+pub fn initial_action(input: KeyInput) -> Option<Action> {
+    if input.repeat {
+        return None;
+    }
+
+    let rotation = match input.code {
+        KeyCode::ArrowUp | KeyCode::KeyX => Rotation::Cw,
+        KeyCode::KeyZ => Rotation::Ccw,
+        KeyCode::KeyA => Rotation::Half,
+        KeyCode::KeyC | KeyCode::ShiftLeft | KeyCode::ShiftRight => return Some(Action::InitialHold(input.pressed)),
+        _ => return None,
+    };
+    Some(Action::InitialRotation(input.pressed.then_some(rotation)))
+}
+
+// What the scene stack should do after a scene handled an update or input
+pub enum Transition {
+    None,
+    Push(Box<dyn Scene>),
+    Pop,
+    Replace(Box<dyn Scene>),
+    // Clears the whole stack and starts over with the given scene
+    Reset(Box<dyn Scene>),
+    Quit,
+}
+
+pub trait Scene {
+    fn update(&mut self, _ctx: &mut SceneContext, _now: Instant) -> Transition {
+        Transition::None
+    }
+
+    fn handle_key(&mut self, ctx: &mut SceneContext, input: KeyInput) -> Transition;
+
+    fn handle_mouse(&mut self, _ctx: &mut SceneContext, _input: MouseInput) -> Transition {
+        Transition::None
+    }
+
+    // The window gained or lost the keyboard focus
+    fn on_focus_changed(&mut self, _ctx: &mut SceneContext, _focused: bool) -> Transition {
+        Transition::None
+    }
+
+    fn render(&self, ctx: &SceneContext) -> (Vec<QuadInstance>, Vec<TextEntry>);
+
+    // Identifies what render() would draw, so the App can reuse the last frame's geometry while
+    // it stays the same. Input, scene changes and theme changes always rebuild it.
+    // None (the default) rebuilds on every frame, for scenes that change with time.
+    fn frame_key(&self, _ctx: &SceneContext) -> Option<u64> {
+        None
+    }
+
+    // When the scene next changes without any input, so the App can sleep until then. `now` (the
+    // default) updates on every frame, None waits for the next input. Input always updates and redraws.
+    fn next_update(&self, _ctx: &SceneContext, now: Instant) -> Option<Instant> {
+        Some(now)
+    }
+
+    // Scenes that talk to the network keep updating while the window is in the background,
+    // everything else waits for the focus to come back
+    fn runs_unfocused(&self) -> bool {
+        false
+    }
+
+    // Called when the scene becomes the top of the stack again (e.g. after unpausing)
+    fn on_resume(&mut self, _now: Instant) {}
+
+    // The board the debug dump (F3) writes to the log, for scenes that show one being played
+    fn debug_board(&self) -> Option<BoardSnapshot> {
+        None
+    }
+}
+
+// How often scenes waiting on an answer from the network check for it
+pub const NETWORK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// Combines everything a frame depends on into a key for Scene::frame_key
+pub fn frame_key(parts: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    parts.hash(&mut hasher);
+    hasher.finish()
+}
+
+// The first scene of a new game in the given mode.
+// Online games need an opponent first, they start in the lobby. Marathon asks for the start level
+// and puzzles and missions for which one to play.
+pub fn start_game(ctx: &SceneContext, mode: GameMode) -> Box<dyn Scene> {
+    match mode {
+        GameMode::Online => Box::new(lobby::LobbyScene::new(ctx)),
+        GameMode::Marathon => Box::new(level_select::LevelSelectScene::new(mode)),
+        GameMode::Puzzle => Box::new(puzzle_select::PuzzleSelectScene::new()),
+        GameMode::Mission => Box::new(mission_select::MissionSelectScene::new()),
+        _ => Box::new(playing::PlayingScene::new(ctx, mode)),
+    }
+}
@@ -0,0 +1,369 @@
+use std::time::Duration;
+
+use rand::Rng;
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+use learning1::action::Action;
+use learning1::events::{ClearKind, GameEvent};
+use learning1::game::{Game, WIDTH};
+use learning1::mode::GameMode;
+use learning1::snapshot::BoardSnapshot;
+use crate::effects::Effects;
+use crate::graphic_context::{QuadInstance, TextEntry};
+use crate::instance::GameInstance;
+use crate::net::{Connection, NetMessage};
+use crate::scenes::results::{MatchOutcome, ResultsScene};
+use crate::scenes::title::TitleScene;
+use crate::scenes::{self, KeyInput, Scene, SceneContext, Transition};
+use crate::{storage, vertex_data};
+
+// How often our board is sent to the opponent, it is only used for drawing
+const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(100);
+// A pause takes effect this many ticks after it is asked for, so the message usually reaches
+// the opponent before their game gets there (see remote_pause for how close the two freezes are)
+const PAUSE_LEAD_TICKS: u64 = 150;
+// Nobody can hold a match hostage, a pause ends by itself after this long
+const PAUSE_TIMEOUT: Duration = Duration::from_secs(60);
+// The opponent's pause may have started a little before ours, so their timeout can come a bit early
+const PAUSE_TIMEOUT_SLACK: Duration = Duration::from_secs(5);
+const RESUME_COUNTDOWN: Duration = Duration::from_secs(3);
+// How long messages like "no pauses left" stay on screen
+const NOTICE_DURATION: Duration = Duration::from_secs(2);
+const OVERLAY_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// An online match. Each side simulates its own game from the shared seed;
+// line clears send garbage and the boards are mirrored with snapshots.
+pub struct OnlineScene {
+    local: GameInstance,
+    // Last known look of the opponent's board
+    remote: BoardSnapshot,
+    remote_effects: Effects,
+    connection: Option<Connection>,
+    last_snapshot_sent: Instant,
+    remote_topped_out: bool,
+    pause: PauseState,
+    // Pauses each side still has in this match
+    pauses_left: u32,
+    remote_pauses_left: u32,
+    notice: Option<(&'static str, Instant)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pauser {
+    Local,
+    Remote,
+}
+
+enum PauseState {
+    Running,
+    // Asked for, the game keeps going until the freeze tick
+    Pending(Pauser),
+    Paused { by: Pauser, since: Instant },
+    // Resuming, both sides count down before the games continue
+    Countdown { until: Instant },
+}
+
+impl OnlineScene {
+    pub fn new(connection: Connection, seed: u64, pauses: u32) -> Self {
+        let now = Instant::now();
+        let game = Game::with_seed(seed);
+        // Both games start identical, so that's what the opponent looks like until their first snapshot
+        let remote = game.snapshot();
+
+        OnlineScene {
+            local: GameInstance::new(game, GameMode::Online, now),
+            remote,
+            remote_effects: Effects::new(now),
+            connection: Some(connection),
+            last_snapshot_sent: now,
+            remote_topped_out: false,
+            pause: PauseState::Running,
+            pauses_left: pauses,
+            remote_pauses_left: pauses,
+            notice: None,
+        }
+    }
+
+    fn request_pause(&mut self, now: Instant) {
+        if !matches!(self.pause, PauseState::Running) {
+            return;
+        }
+        if self.pauses_left == 0 {
+            self.notice = Some(("NO PAUSES LEFT", now));
+            return;
+        }
+
+        self.pauses_left -= 1;
+        let at_tick = self.local.session.tick + PAUSE_LEAD_TICKS;
+        self.local.set_freeze_tick(Some(at_tick));
+        self.send(&NetMessage::Pause { at_tick });
+        self.pause = PauseState::Pending(Pauser::Local);
+    }
+
+    // The opponent's tick counter started when the Hello crossed the wire, so it is only roughly ours:
+    // the joiner's runs behind the host's by about the time the Hello took to arrive. The pause is
+    // best effort, both games freeze within that latency of each other rather than on the same tick.
+    fn remote_pause(&mut self, at_tick: u64) {
+        // A client that ignores the budget doesn't get to pause anyway, and a pause that can't
+        // take effect (while paused or counting down) doesn't use one up
+        if self.remote_pauses_left == 0 || !matches!(self.pause, PauseState::Running | PauseState::Pending(_)) {
+            return;
+        }
+        self.remote_pauses_left -= 1;
+
+        // If the message came late our game is already past the tick, freeze right away then
+        let at_tick = at_tick.max(self.local.session.tick);
+        match self.pause {
+            PauseState::Running => {
+                self.local.set_freeze_tick(Some(at_tick));
+                self.pause = PauseState::Pending(Pauser::Remote);
+            }
+            // Both asked at about the same time, the earlier tick wins
+            PauseState::Pending(_) => {
+                let earliest = self.local.freeze_tick().map_or(at_tick, |tick| tick.min(at_tick));
+                self.local.set_freeze_tick(Some(earliest));
+            }
+            _ => {}
+        }
+    }
+
+    fn start_countdown(&mut self, now: Instant) {
+        if matches!(self.pause, PauseState::Pending(_) | PauseState::Paused { .. }) {
+            self.pause = PauseState::Countdown { until: now + RESUME_COUNTDOWN };
+        }
+    }
+
+    // Only the player who paused resumes early, the other one has to wait for the timeout
+    fn remote_resume(&mut self, now: Instant) {
+        let allowed = match self.pause {
+            PauseState::Pending(Pauser::Remote) | PauseState::Paused { by: Pauser::Remote, .. } => true,
+            PauseState::Paused { since, .. } => now.duration_since(since) + PAUSE_TIMEOUT_SLACK >= PAUSE_TIMEOUT,
+            _ => false,
+        };
+        if allowed {
+            self.start_countdown(now);
+        }
+    }
+
+    fn update_pause(&mut self, now: Instant) {
+        match self.pause {
+            PauseState::Pending(by) if self.local.is_frozen() => {
+                self.pause = PauseState::Paused { by, since: now };
+            }
+            // Either side ends an overlong pause, the other one gets the Resume
+            PauseState::Paused { since, .. } if now.duration_since(since) >= PAUSE_TIMEOUT => {
+                self.send(&NetMessage::Resume);
+                self.start_countdown(now);
+            }
+            PauseState::Countdown { until } if now >= until => {
+                self.local.set_freeze_tick(None);
+                self.local.resume(now);
+                self.pause = PauseState::Running;
+            }
+            _ => {}
+        }
+    }
+
+    // The simulation only runs while no pause has kicked in yet
+    fn is_running(&self) -> bool {
+        matches!(self.pause, PauseState::Running | PauseState::Pending(_)) && !self.local.is_frozen()
+    }
+
+    fn forfeit(&mut self) -> Transition {
+        self.send(&NetMessage::GameOver);
+        if let Some(connection) = self.connection.take() {
+            connection.close();
+        }
+        Transition::Reset(Box::new(TitleScene::new()))
+    }
+
+    fn send(&mut self, message: &NetMessage) {
+        if let Some(connection) = &mut self.connection {
+            connection.send(message);
+        }
+    }
+
+    // Hangs up after telling the opponent we're done, then shows the results
+    fn finish(&mut self, ctx: &SceneContext, outcome: MatchOutcome) -> Transition {
+        if outcome != MatchOutcome::OpponentLeft {
+            let snapshot = self.local.snapshot();
+            self.send(&NetMessage::Snapshot(Box::new(snapshot)));
+            self.send(&NetMessage::GameOver);
+        }
+        if let Some(connection) = self.connection.take() {
+            connection.close();
+        }
+
+        let path = storage::last_replay_path();
+        if let Err(e) = self.local.session.to_replay().save(&path) {
+            tracing::error!("Could not save replay to {}: {}", path.display(), e);
+        }
+
+        let mut results = ResultsScene::new(&self.local.session)
+            .with_opponent(outcome, "RIVAL", self.remote.score, None);
+        if ctx.config.stats.auto_export {
+            results.export_stats(ctx.config.stats.format);
+        }
+        Transition::Replace(Box::new(results))
+    }
+}
+
+impl Scene for OnlineScene {
+    fn update(&mut self, ctx: &mut SceneContext, now: Instant) -> Transition {
+        if matches!(self.pause, PauseState::Running | PauseState::Pending(_)) {
+            self.local.update(&ctx.config.handling, now);
+        }
+        self.update_pause(now);
+
+        for event in self.local.take_events() {
+            if let GameEvent::LinesCleared { count, kind, .. } = event {
+                ctx.tetris_scored |= kind == ClearKind::Tetris;
+                ctx.background.lines_cleared(count, now);
+                let lines = self.local.game().rules.garbage_lines[kind.index()];
+                if lines > 0 {
+                    let hole = rand::rng().random_range(0..WIDTH) as u8;
+                    self.send(&NetMessage::Garbage { lines: lines as u8, hole });
+                }
+            }
+        }
+
+        ctx.background.set_level(self.local.game().level);
+
+        if now.duration_since(self.last_snapshot_sent) >= SNAPSHOT_INTERVAL {
+            let snapshot = self.local.snapshot();
+            self.send(&NetMessage::Snapshot(Box::new(snapshot)));
+            self.last_snapshot_sent = now;
+        }
+
+        let received = match &mut self.connection {
+            Some(connection) => connection.poll(),
+            None => Ok(Vec::new()),
+        };
+        let messages = match received {
+            Ok(messages) => messages,
+            Err(e) => {
+                tracing::warn!("Lost the connection to the opponent: {}", e);
+                self.connection = None;
+                return self.finish(ctx, MatchOutcome::OpponentLeft);
+            }
+        };
+
+        for message in messages {
+            match message {
+                // Recorded like an input, so the replay of this game includes the garbage
+                NetMessage::Garbage { lines, hole } => self.local.apply(Action::AddGarbage { lines, hole }),
+                // Only drawn, so a board we can't draw is skipped rather than ending the match
+                NetMessage::Snapshot(snapshot) => match snapshot.validate() {
+                    Ok(()) => self.remote = *snapshot,
+                    Err(e) => tracing::warn!("Ignoring a bad board from the opponent: {}", e),
+                },
+                NetMessage::Pause { at_tick } => self.remote_pause(at_tick),
+                NetMessage::Resume => self.remote_resume(now),
+                NetMessage::GameOver => self.remote_topped_out = true,
+                NetMessage::Hello { .. } => {}
+            }
+        }
+        self.remote_effects.update(&self.remote, now);
+
+        if self.local.game().is_game_over {
+            return self.finish(ctx, MatchOutcome::Lost);
+        }
+        if self.remote_topped_out {
+            return self.finish(ctx, MatchOutcome::Won);
+        }
+
+        Transition::None
+    }
+
+    fn handle_key(&mut self, ctx: &mut SceneContext, input: KeyInput) -> Transition {
+        let now = Instant::now();
+        if input.just_pressed(KeyCode::KeyQ) {
+            return self.forfeit();
+        }
+        if input.just_pressed(KeyCode::Escape) || input.just_pressed(KeyCode::KeyP) {
+            match self.pause {
+                PauseState::Running => self.request_pause(now),
+                // Only the player who paused can resume early
+                PauseState::Paused { by: Pauser::Local, .. } => {
+                    self.send(&NetMessage::Resume);
+                    self.start_countdown(now);
+                }
+                _ => {}
+            }
+            self.local.release_held_keys(&ctx.config.handling);
+            return Transition::None;
+        }
+
+        // Frozen games take no input, it would land on the freeze tick on one side only
+        if !self.is_running() {
+            return Transition::None;
+        }
+
+        if input.code == KeyCode::ArrowDown {
+            self.local.set_soft_drop_held(&ctx.config.handling, input.pressed);
+        }
+        if let Some(action) = scenes::shift_action(input.code).filter(|_| !input.repeat) {
+            self.local.set_shift_held(&ctx.config.handling, action, input.pressed);
+        }
+
+        if let Some(action) = scenes::key_action(input) {
+            self.local.apply(action);
+        }
+        if let Some(action) = scenes::initial_action(input).filter(|_| ctx.config.handling.initial_actions) {
+            self.local.apply(action);
+        }
+
+        Transition::None
+    }
+
+    // The other player keeps playing, and so does the match
+    fn runs_unfocused(&self) -> bool {
+        true
+    }
+
+    fn render(&self, ctx: &SceneContext) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+        let boards = [
+            (self.local.snapshot(), self.local.effects().frame(ctx.theme(), &self.local.game().rules.piece_set)),
+            (self.remote.clone(), self.remote_effects.frame(ctx.theme(), &self.remote.piece_set)),
+        ];
+        let (instances, mut text) = vertex_data::build_multi_mesh(&boards, ctx.theme());
+
+        let now = Instant::now();
+        let overlay = match self.pause {
+            PauseState::Paused { by: Pauser::Local, .. } => Some("PAUSED - P TO RESUME".to_string()),
+            PauseState::Paused { by: Pauser::Remote, since } => {
+                let left = PAUSE_TIMEOUT.saturating_sub(now.duration_since(since)).as_secs();
+                Some(format!("RIVAL PAUSED ({}S)", left))
+            }
+            PauseState::Countdown { until } => Some(format!("RESUMING IN {}", until.saturating_duration_since(now).as_secs() + 1)),
+            PauseState::Running | PauseState::Pending(_) => None,
+        };
+        if let Some(overlay) = overlay {
+            text.push(TextEntry { text: overlay, x: 3.0, y: 3.0, color: OVERLAY_COLOR, scale: 1.0 });
+        }
+        if let Some((notice, shown_at)) = self.notice
+            && now.duration_since(shown_at) < NOTICE_DURATION
+        {
+            text.push(TextEntry { text: notice.to_string(), x: 3.0, y: 4.5, color: [1.0, 0.3, 0.3, 1.0], scale: 0.8 });
+        }
+        text.push(TextEntry {
+            text: format!("PAUSES LEFT {}   Q FORFEITS", self.pauses_left),
+            x: 0.5,
+            y: 24.0,
+            color: [0.8, 0.8, 0.8, 1.0],
+            scale: 0.6,
+        });
+
+        (instances, text)
+    }
+
+    fn on_resume(&mut self, now: Instant) {
+        self.local.resume(now);
+        self.remote_effects.resume(now);
+    }
+
+    fn debug_board(&self) -> Option<BoardSnapshot> {
+        Some(self.local.snapshot())
+    }
+}
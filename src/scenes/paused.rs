@@ -0,0 +1,119 @@
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+use learning1::session::Session;
+
+use crate::graphic_context::{QuadInstance, TextEntry};
+use crate::save::SaveGame;
+use crate::scenes::menu::Menu;
+use crate::scenes::settings::SettingsScene;
+use crate::scenes::title::TitleScene;
+use crate::scenes::{KeyInput, MouseInput, Scene, SceneContext, Transition};
+
+// What each line of the pause menu does
+enum PauseEntry {
+    Resume,
+    Settings,
+    SaveAndQuit,
+    QuitToTitle,
+}
+
+// Overlay pushed on top of the playing scene. The board is hidden while paused.
+pub struct PausedScene {
+    entries: Vec<PauseEntry>,
+    menu: Menu,
+    // Copy of the paused game, written out by "Save & Quit". None when the game can't be saved.
+    session: Option<Session>,
+    // Paused because the window lost focus rather than by the player
+    automatic: bool,
+}
+
+impl PausedScene {
+    pub fn new(session: Option<Session>) -> Self {
+        let mut entries = vec![PauseEntry::Resume, PauseEntry::Settings];
+        if session.is_some() {
+            entries.push(PauseEntry::SaveAndQuit);
+        }
+        entries.push(PauseEntry::QuitToTitle);
+
+        let items = entries.iter().map(|entry| match entry {
+            PauseEntry::Resume => "RESUME".to_string(),
+            PauseEntry::Settings => "SETTINGS".to_string(),
+            PauseEntry::SaveAndQuit => "SAVE & QUIT".to_string(),
+            PauseEntry::QuitToTitle => "QUIT TO TITLE".to_string(),
+        }).collect();
+
+        PausedScene {
+            entries,
+            menu: Menu::new(items, 8.0, 12.0),
+            session,
+            automatic: false,
+        }
+    }
+
+    // The pause for when the window loses focus, which can resume by itself when it comes back
+    pub fn automatic(session: Option<Session>) -> Self {
+        PausedScene { automatic: true, ..PausedScene::new(session) }
+    }
+
+    fn activate(&mut self, ctx: &SceneContext, index: usize) -> Transition {
+        match self.entries[index] {
+            PauseEntry::Resume => Transition::Pop,
+            PauseEntry::Settings => Transition::Push(Box::new(SettingsScene::new(ctx))),
+            PauseEntry::SaveAndQuit => match self.session.as_ref().map(SaveGame::write) {
+                Some(Err(e)) => {
+                    tracing::error!("Could not save the game: {}", e);
+                    Transition::None
+                }
+                _ => Transition::Reset(Box::new(TitleScene::new())),
+            },
+            PauseEntry::QuitToTitle => Transition::Reset(Box::new(TitleScene::new())),
+        }
+    }
+}
+
+impl Scene for PausedScene {
+    fn handle_key(&mut self, ctx: &mut SceneContext, input: KeyInput) -> Transition {
+        if input.just_pressed(KeyCode::Escape) || input.just_pressed(KeyCode::KeyP) {
+            return Transition::Pop;
+        }
+
+        match self.menu.handle_key(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    fn handle_mouse(&mut self, ctx: &mut SceneContext, input: MouseInput) -> Transition {
+        match self.menu.handle_mouse(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    fn on_focus_changed(&mut self, ctx: &mut SceneContext, focused: bool) -> Transition {
+        if focused && self.automatic && ctx.config.pause.resume_on_focus {
+            Transition::Pop
+        } else {
+            Transition::None
+        }
+    }
+
+    // The game underneath doesn't run while paused, nothing moves until a key is pressed
+    fn next_update(&self, _ctx: &SceneContext, _now: Instant) -> Option<Instant> {
+        None
+    }
+
+    fn render(&self, _ctx: &SceneContext) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+        let mut text = vec![TextEntry {
+            text: "PAUSED".to_string(),
+            x: 9.5,
+            y: 8.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            scale: 1.2,
+        }];
+        text.extend(self.menu.text_entries());
+
+        (Vec::new(), text)
+    }
+}
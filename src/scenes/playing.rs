@@ -0,0 +1,495 @@
+use std::collections::VecDeque;
+use std::path::Path;
+
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+use learning1::action::Action;
+use learning1::challenge::Challenge;
+use learning1::events::{ClearKind, GameEvent};
+use learning1::finesse::FinesseTracker;
+use learning1::game::{Game, GameConfig};
+use learning1::mission::{Goal, Mission, MissionTracker};
+use learning1::mode::GameMode;
+use learning1::piece_set::PieceSet;
+use learning1::puzzle::{Objective, Puzzle};
+use learning1::session::Session;
+use learning1::snapshot::BoardSnapshot;
+use crate::autoplay::{self, BotController};
+use crate::daily::{self, DailyResult};
+use crate::graphic_context::{QuadInstance, TextEntry};
+use crate::instance::GameInstance;
+use crate::leaderboard::{LeaderboardRequest, LeaderboardState, Submission};
+use crate::scenes::paused::PausedScene;
+use crate::scenes::results::{MatchOutcome, ResultsScene};
+use crate::scenes::{self, KeyInput, Scene, SceneContext, Transition};
+use crate::{highscores, storage, vertex_data};
+
+// The local player is always the first instance
+const LOCAL_PLAYER: usize = 0;
+// In versus, the bot opponent comes right after
+const CPU_PLAYER: usize = 1;
+// How many pieces a practice game can be taken back
+const REWIND_DEPTH: usize = 30;
+
+pub struct PlayingScene {
+    mode: GameMode,
+    players: Vec<GameInstance>,
+    // Set when playing the weekly or daily challenge, whose scores go to their own bucket
+    challenge: Option<Challenge>,
+    // Judges the local player's inputs in finesse mode
+    finesse: Option<FinesseTracker>,
+    // Follows the local player through the goals of a mission
+    mission: Option<MissionTracker>,
+    // Practice games keep the session as it was when each of the last pieces spawned, newest last
+    history: VecDeque<Session>,
+}
+
+impl PlayingScene {
+    pub fn new(ctx: &SceneContext, mode: GameMode) -> Self {
+        let config = game_config(ctx, mode);
+        Self::start(ctx, Session::new(Game::with_config(config), mode), None)
+    }
+
+    // Marathon from a chosen level
+    pub fn with_start_level(ctx: &SceneContext, mode: GameMode, level: u32) -> Self {
+        let config = game_config(ctx, mode).with_start_level(level);
+        Self::start(ctx, Session::new(Game::with_config(config), mode), None)
+    }
+
+    // The challenge brings its own seed and rules
+    pub fn with_challenge(ctx: &SceneContext, challenge: Challenge) -> Self {
+        let session = Session::new(Game::with_config(challenge.game_config()), challenge.mode);
+        Self::start(ctx, session, Some(challenge))
+    }
+
+    // The puzzle brings its own board and pieces
+    pub fn with_puzzle(ctx: &SceneContext, puzzle: Puzzle) -> Self {
+        let session = Session::new(Game::with_config(puzzle.game_config()), GameMode::Puzzle).with_puzzle(puzzle);
+        Self::start(ctx, session, None)
+    }
+
+    // The mission brings its own rules and start level, the command line seed still applies
+    pub fn with_mission(ctx: &SceneContext, mission: Mission) -> Self {
+        let config = mission.game_config();
+        let config = match ctx.seed {
+            Some(seed) => config.with_seed(seed),
+            None => config,
+        };
+        let mut scene = Self::start(ctx, Session::new(Game::with_config(config), GameMode::Mission), None);
+        scene.mission = Some(MissionTracker::new(mission));
+        scene
+    }
+
+    fn start(ctx: &SceneContext, session: Session, challenge: Option<Challenge>) -> Self {
+        let now = Instant::now();
+        let mode = session.mode;
+        let mut local = GameInstance::from_session(session, now);
+        if ctx.autoplay {
+            local.set_bot(Some(BotController::new(ctx.config.bot.actions_per_second, now)));
+        }
+
+        let mut players = vec![local];
+        if mode == GameMode::VersusCpu {
+            // Same rules and seed, so both sides get the same pieces and turn them the same way
+            let config = game_config(ctx, mode).with_seed(players[LOCAL_PLAYER].game().seed);
+            let mut cpu = GameInstance::new(Game::with_config(config), mode, now);
+            let bot = BotController::new(ctx.config.bot.actions_per_second, now).with_difficulty(&ctx.config.versus);
+            cpu.set_bot(Some(bot));
+            players.push(cpu);
+        }
+
+        let mut scene = PlayingScene { mode, players, challenge, finesse: finesse_tracker(mode), mission: None, history: VecDeque::new() };
+        scene.remember_spawn();
+        scene
+    }
+
+    // Continues a saved game
+    pub fn from_session(session: Session) -> Self {
+        let mut scene = PlayingScene {
+            mode: session.mode,
+            finesse: finesse_tracker(session.mode),
+            mission: None,
+            players: vec![GameInstance::from_session(session, Instant::now())],
+            challenge: None,
+            history: VecDeque::new(),
+        };
+        scene.remember_spawn();
+        scene
+    }
+
+    fn local(&mut self) -> &mut GameInstance {
+        &mut self.players[LOCAL_PLAYER]
+    }
+
+    // Opens the pause menu made by `paused` (PausedScene::new or PausedScene::automatic)
+    fn pause(&mut self, ctx: &SceneContext, paused: fn(Option<Session>) -> PausedScene) -> Transition {
+        self.local().release_held_keys(&ctx.config.handling);
+        // A versus match can't be saved, the opponent would have to be saved too.
+        // Neither can a challenge or a mission, they would come back as a regular game.
+        let can_save = self.players.len() == 1 && self.challenge.is_none() && self.mission.is_none();
+        let session = can_save.then(|| self.players[LOCAL_PLAYER].session.clone());
+        Transition::Push(Box::new(paused(session)))
+    }
+
+    // Finesse judges the local player's pieces as they lock, missions count them towards the goals,
+    // practice games remember the next spawn and clears are reported for the recorder and the background.
+    // Other events are dropped so they don't pile up.
+    fn handle_events(&mut self, ctx: &mut SceneContext, now: Instant) {
+        let mut locked = false;
+        for (i, player) in self.players.iter_mut().enumerate() {
+            let events = player.take_events();
+            if i != LOCAL_PLAYER {
+                continue;
+            }
+            for event in events {
+                if let Some(mission) = &mut self.mission {
+                    mission.handle_event(&event, player.game(), player.session.tick);
+                }
+                match event {
+                    GameEvent::PieceLocked { shape, cells } => {
+                        locked = true;
+                        if let Some(finesse) = self.finesse.as_mut().filter(|_| !player.is_bot_controlled()) {
+                            finesse.piece_locked(&player.game().rules, shape, &cells);
+                        }
+                    }
+                    GameEvent::LinesCleared { count, kind, .. } => {
+                        ctx.tetris_scored |= kind == ClearKind::Tetris;
+                        ctx.background.lines_cleared(count, now);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        ctx.background.set_level(self.players[LOCAL_PLAYER].game().level);
+
+        if locked {
+            self.remember_spawn();
+        }
+    }
+
+    fn remember_spawn(&mut self) {
+        let session = &self.players[LOCAL_PLAYER].session;
+        if !self.mode.is_practice() || session.is_finished() {
+            return;
+        }
+        if self.history.len() == REWIND_DEPTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(session.clone());
+    }
+
+    // Takes back the last placed piece: the game goes back to when that piece spawned.
+    // The oldest remembered spawn is as far back as it goes.
+    fn rewind(&mut self, ctx: &SceneContext) {
+        if self.history.len() > 1 {
+            self.history.pop_back();
+        }
+        let Some(session) = self.history.back() else { return };
+
+        let local = &mut self.players[LOCAL_PLAYER];
+        local.release_held_keys(&ctx.config.handling);
+        local.session = session.clone();
+        local.session.rewound = true;
+        local.take_events();
+        local.resume(Instant::now());
+        if let Some(finesse) = &mut self.finesse {
+            finesse.reset();
+        }
+    }
+}
+
+fn finesse_tracker(mode: GameMode) -> Option<FinesseTracker> {
+    (mode == GameMode::Finesse).then(FinesseTracker::new)
+}
+
+// The mode's rules with the configured rotation system, and the command line seed.
+// Endless also takes the practice garbage and piece set from the config.
+pub fn game_config(ctx: &SceneContext, mode: GameMode) -> GameConfig {
+    let mut rules = mode.rules();
+    rules.piece_set = ctx.pieces.clone();
+    if mode == GameMode::Endless {
+        rules.starting_garbage = ctx.config.practice.starting_garbage;
+        if let Some(path) = &ctx.config.practice.piece_set {
+            match PieceSet::load(Path::new(path)) {
+                Ok(pieces) => rules.piece_set = pieces,
+                Err(e) => tracing::warn!("Could not load piece set {}: {}", path, e),
+            }
+        }
+    }
+
+    let config = GameConfig::default().with_rules(rules);
+    match ctx.seed {
+        Some(seed) => config.with_seed(seed),
+        None => config,
+    }
+}
+
+impl Scene for PlayingScene {
+    fn update(&mut self, ctx: &mut SceneContext, now: Instant) -> Transition {
+        if let [local, cpu] = self.players.as_mut_slice() {
+            let lead = autoplay::match_lead(cpu.game(), local.game());
+            if let Some(bot) = cpu.bot_mut() {
+                bot.adapt(lead);
+            }
+        }
+
+        for player in &mut self.players {
+            player.update(&ctx.config.handling, now);
+        }
+        self.handle_events(ctx, now);
+
+        let local = &self.players[LOCAL_PLAYER];
+        if let Some(mission) = &mut self.mission {
+            mission.update(local.game(), local.session.tick);
+        }
+        let cpu = self.players.get(CPU_PLAYER);
+        let cpu_topped_out = cpu.is_some_and(|cpu| cpu.game().is_game_over);
+        let mission_complete = self.mission.as_ref().is_some_and(MissionTracker::is_complete);
+        if local.session.is_finished() || cpu_topped_out || mission_complete {
+            let path = storage::last_replay_path();
+            if let Err(e) = local.session.to_replay().save(&path) {
+                tracing::error!("Could not save replay to {}: {}", path.display(), e);
+            }
+
+            let mut results = ResultsScene::new(&local.session);
+            if ctx.config.stats.auto_export {
+                results.export_stats(ctx.config.stats.format);
+            }
+            // Games played by the bot or with pieces taken back don't count, and puzzles and missions are
+            // completed or not, there's no best
+            if !local.is_bot_controlled() && !local.session.rewound && local.session.puzzle().is_none() && self.mission.is_none() {
+                let bucket = match &self.challenge {
+                    Some(challenge) => challenge.bucket(),
+                    None => self.mode.id().to_string(),
+                };
+                results = results.with_best(highscores::record_game(&bucket, &local.session));
+                if let Some(challenge) = self.challenge.as_ref().filter(|challenge| challenge.single_attempt()) {
+                    let game = local.game();
+                    daily::record(challenge, DailyResult { score: game.score, lines: game.lines_cleared, finished: true });
+                }
+                let leaderboard = &ctx.config.leaderboard;
+                if leaderboard.enabled {
+                    let submission = Submission {
+                        name: leaderboard.name.clone(),
+                        score: local.game().score,
+                        mode: bucket.clone(),
+                        seed: local.game().seed,
+                    };
+                    results = results.with_leaderboard(bucket, LeaderboardState::Loading(LeaderboardRequest::submit(leaderboard, submission)));
+                }
+            }
+            if let Some(challenge) = &self.challenge {
+                results = results.with_challenge(challenge.clone());
+            }
+            if let Some(mission) = &self.mission {
+                results = results.with_mission(mission.clone());
+            }
+            if let Some(cpu) = cpu {
+                let outcome = if local.game().is_game_over { MatchOutcome::Lost } else { MatchOutcome::Won };
+                let level = cpu.bot().and_then(|bot| bot.effective_skill());
+                results = results.with_opponent(outcome, "CPU", cpu.game().score, level);
+            }
+            return Transition::Replace(Box::new(results));
+        }
+
+        Transition::None
+    }
+
+    fn handle_key(&mut self, ctx: &mut SceneContext, input: KeyInput) -> Transition {
+        // Held keys are tracked on press/release instead of relying on OS key repeat
+        let bot_controlled = self.players[LOCAL_PLAYER].is_bot_controlled();
+        if input.code == KeyCode::ArrowDown && !bot_controlled {
+            if let Some(finesse) = self.finesse.as_mut().filter(|_| input.just_pressed(KeyCode::ArrowDown)) {
+                finesse.record_input(Action::SoftDrop);
+            }
+            self.local().set_soft_drop_held(&ctx.config.handling, input.pressed);
+        }
+
+        if input.just_pressed(KeyCode::Escape) || input.just_pressed(KeyCode::KeyP) {
+            return self.pause(ctx, PausedScene::new);
+        }
+
+        // While the bot plays, the keyboard can only pause
+        if bot_controlled {
+            return Transition::None;
+        }
+        if let Some(action) = scenes::shift_action(input.code).filter(|_| !input.repeat) {
+            // Holding a key down to slide the piece is one input
+            if let Some(finesse) = self.finesse.as_mut().filter(|_| input.pressed) {
+                finesse.record_input(action);
+            }
+            self.local().set_shift_held(&ctx.config.handling, action, input.pressed);
+        }
+        if input.just_pressed(KeyCode::Backspace) && self.mode.is_practice() {
+            self.rewind(ctx);
+            return Transition::None;
+        }
+        if let Some(action) = scenes::key_action(input) {
+            if let Some(finesse) = &mut self.finesse {
+                finesse.record_input(action);
+            }
+            self.local().apply(action);
+        }
+        if let Some(action) = scenes::initial_action(input).filter(|_| ctx.config.handling.initial_actions) {
+            self.local().apply(action);
+        }
+        self.handle_events(ctx, Instant::now());
+
+        // The lock from a hard drop may have ended the game, let update() switch scenes
+        Transition::None
+    }
+
+    // So the stack doesn't top out while the player is in another window
+    fn on_focus_changed(&mut self, ctx: &mut SceneContext, focused: bool) -> Transition {
+        if focused || !ctx.config.pause.on_focus_loss || self.players[LOCAL_PLAYER].session.is_finished() {
+            return Transition::None;
+        }
+        self.pause(ctx, PausedScene::automatic)
+    }
+
+    fn render(&self, ctx: &SceneContext) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+        let boards: Vec<_> = self.players.iter()
+            .map(|player| (player.snapshot(), player.effects().frame(ctx.theme(), &player.game().rules.piece_set)))
+            .collect();
+        let (instances, mut text) = vertex_data::build_multi_mesh(&boards, ctx.theme());
+
+        // Timed modes show the clock, leveled ones the level, and both the progress under the board
+        let session = &self.players[LOCAL_PLAYER].session;
+        if session.is_timed() {
+            text.push(TextEntry {
+                text: highscores::format_time(session.tick),
+                x: 0.5,
+                y: 21.0,
+                color: [1.0, 1.0, 1.0, 1.0],
+                scale: 1.2,
+            });
+        } else if self.mode.has_levels() {
+            text.push(TextEntry {
+                text: format!("LEVEL {}", session.game.level),
+                x: 0.5,
+                y: 21.0,
+                color: [1.0, 1.0, 1.0, 1.0],
+                scale: 1.2,
+            });
+        }
+        if let Some(goal) = session.dig_goal() {
+            text.push(TextEntry {
+                text: format!("GARBAGE {}/{}", session.game.garbage_cleared.min(goal), goal),
+                x: 0.5,
+                y: 23.0,
+                color: [0.8, 0.8, 0.8, 1.0],
+                scale: 0.8,
+            });
+        } else if let Some(goal) = session.line_goal() {
+            text.push(TextEntry {
+                text: format!("LINES {}/{}", session.game.lines_cleared.min(goal), goal),
+                x: 0.5,
+                y: 23.0,
+                color: [0.8, 0.8, 0.8, 1.0],
+                scale: 0.8,
+            });
+        }
+
+        // Finesse shows the faults with the accuracy and the last piece under the board
+        // (the side panel next to the score has the play rates)
+        if let Some(finesse) = &self.finesse {
+            text.push(TextEntry {
+                text: format!("FAULTS {}  {:.0}%", finesse.faults, finesse.accuracy()),
+                x: 0.5,
+                y: 21.0,
+                color: [1.0, 1.0, 1.0, 1.0],
+                scale: 1.2,
+            });
+            let (last, color) = match finesse.last_extra {
+                Some(0) => ("LAST PIECE OK".to_string(), [0.3, 1.0, 0.3, 1.0]),
+                Some(extra) => (format!("LAST PIECE +{} INPUTS", extra), [1.0, 0.3, 0.3, 1.0]),
+                None => (String::new(), [0.8, 0.8, 0.8, 1.0]),
+            };
+            text.push(TextEntry { text: last, x: 0.5, y: 23.0, color, scale: 0.8 });
+        }
+
+        if self.mode.is_practice() && self.history.len() > 1 {
+            text.push(TextEntry {
+                text: "BACKSPACE: UNDO".to_string(),
+                x: 0.5,
+                y: 25.0,
+                color: [0.6, 0.6, 0.6, 1.0],
+                scale: 0.6,
+            });
+        }
+
+        // Puzzles show what's left to do, and with how many pieces
+        if let Some(puzzle) = session.puzzle() {
+            let objective = match puzzle.objective {
+                Objective::ClearAll => "CLEAR ALL".to_string(),
+                Objective::ClearLines(lines) => format!("LINES {}/{}", session.game.lines_cleared.min(lines), lines),
+                _ => String::new(),
+            };
+            text.push(TextEntry {
+                text: objective,
+                x: 0.5,
+                y: 21.0,
+                color: [1.0, 1.0, 1.0, 1.0],
+                scale: 1.2,
+            });
+            text.push(TextEntry {
+                text: format!("PIECES LEFT {}", puzzle.pieces_left(&session.game)),
+                x: 0.5,
+                y: 23.0,
+                color: [0.8, 0.8, 0.8, 1.0],
+                scale: 0.8,
+            });
+        }
+
+        // Missions show the goal being played and how far along it is, under the level
+        if let Some(mission) = &self.mission
+            && let Some(goal) = mission.current_goal()
+        {
+            text.push(TextEntry {
+                text: format!("GOAL {}/{}", mission.current + 1, mission.mission.goals.len()),
+                x: 0.5,
+                y: 23.0,
+                color: [0.8, 0.8, 0.8, 1.0],
+                scale: 0.8,
+            });
+            let progress = match goal {
+                Goal::TSpin { .. } => goal.describe(),
+                _ => format!("{} {}", goal.describe(), mission.progress_text()),
+            };
+            text.push(TextEntry { text: progress, x: 0.5, y: 24.5, color: [0.8, 0.8, 0.8, 1.0], scale: 0.6 });
+        }
+
+        (instances, text)
+    }
+
+    fn frame_key(&self, _ctx: &SceneContext) -> Option<u64> {
+        let boards = self.players.iter().map(GameInstance::frame_key).collect::<Option<Vec<_>>>()?;
+        // The clock of timed modes changes on every tick, and so does the time survived in missions
+        let session = &self.players[LOCAL_PLAYER].session;
+        let clock = session.is_timed().then_some(session.tick);
+        let mission = self.mission.as_ref().map(|mission| (mission.current, mission.progress_text()));
+        Some(scenes::frame_key((boards, clock, mission)))
+    }
+
+    // A board left alone only changes with gravity, unless there's a clock on screen
+    fn next_update(&self, _ctx: &SceneContext, now: Instant) -> Option<Instant> {
+        let session = &self.players[LOCAL_PLAYER].session;
+        let surviving = self.mission.as_ref().and_then(MissionTracker::current_goal).is_some_and(|goal| matches!(goal, Goal::Survive { .. }));
+        if session.is_timed() || surviving {
+            return Some(now);
+        }
+        self.players.iter().filter_map(|player| player.next_update(now)).min()
+    }
+
+    fn on_resume(&mut self, now: Instant) {
+        for player in &mut self.players {
+            player.resume(now);
+        }
+    }
+
+    // The local player's board, not the CPU opponent's
+    fn debug_board(&self) -> Option<BoardSnapshot> {
+        Some(self.players[LOCAL_PLAYER].snapshot())
+    }
+}
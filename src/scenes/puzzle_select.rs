@@ -0,0 +1,130 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+use learning1::puzzle::{Objective, Puzzle};
+use crate::graphic_context::{QuadInstance, TextEntry};
+use crate::scenes::menu::Menu;
+use crate::scenes::playing::PlayingScene;
+use crate::scenes::{KeyInput, MouseInput, Scene, SceneContext, Transition};
+
+// Puzzles ship with the game, next to the font, and are read from the working directory like config.toml
+const PUZZLE_DIR: &str = "assets/puzzles";
+const UI_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+
+// Lists the puzzles found in assets/puzzles, with the details of the selected one
+pub struct PuzzleSelectScene {
+    puzzles: Vec<Puzzle>,
+    menu: Menu,
+}
+
+impl PuzzleSelectScene {
+    pub fn new() -> Self {
+        let puzzles = load_puzzles(Path::new(PUZZLE_DIR));
+        let mut items: Vec<String> = puzzles.iter().map(|puzzle| puzzle.name.clone()).collect();
+        items.push("BACK".to_string());
+
+        PuzzleSelectScene {
+            puzzles,
+            menu: Menu::new(items, 5.0, 7.0),
+        }
+    }
+
+    fn activate(&mut self, ctx: &mut SceneContext, index: usize) -> Transition {
+        match self.puzzles.get(index) {
+            Some(puzzle) => Transition::Replace(Box::new(PlayingScene::with_puzzle(ctx, puzzle.clone()))),
+            None => Transition::Pop,
+        }
+    }
+}
+
+// Every puzzle file in the directory, sorted by file name so they can be numbered.
+// Broken files are reported and skipped.
+fn load_puzzles(dir: &Path) -> Vec<Puzzle> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect(),
+        Err(e) => {
+            tracing::warn!("Could not read puzzles from {}: {}", dir.display(), e);
+            Vec::new()
+        }
+    };
+    paths.sort();
+
+    paths.iter().filter_map(|path| match Puzzle::load(path) {
+        Ok(puzzle) => Some(puzzle),
+        Err(e) => {
+            tracing::warn!("Skipping puzzle {}: {}", path.display(), e);
+            None
+        }
+    }).collect()
+}
+
+impl Scene for PuzzleSelectScene {
+    fn handle_key(&mut self, ctx: &mut SceneContext, input: KeyInput) -> Transition {
+        if input.just_pressed(KeyCode::Escape) {
+            return Transition::Pop;
+        }
+
+        match self.menu.handle_key(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    fn handle_mouse(&mut self, ctx: &mut SceneContext, input: MouseInput) -> Transition {
+        match self.menu.handle_mouse(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    fn next_update(&self, _ctx: &SceneContext, _now: Instant) -> Option<Instant> {
+        None
+    }
+
+    fn render(&self, _ctx: &SceneContext) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+        let mut text = vec![TextEntry {
+            text: "PUZZLES".to_string(),
+            x: 5.0,
+            y: 4.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            scale: 1.2,
+        }];
+        text.extend(self.menu.text_entries());
+
+        let details_y = self.menu.item_rect(self.menu.items.len()).y + 1.0;
+        match self.puzzles.get(self.menu.selected) {
+            Some(puzzle) => {
+                let objective = match puzzle.objective {
+                    Objective::ClearAll => "CLEAR ALL BLOCKS".to_string(),
+                    Objective::ClearLines(lines) => format!("CLEAR {} LINES", lines),
+                    _ => String::new(),
+                };
+                let details = [
+                    format!("{} WITH {} PIECES", objective, puzzle.pieces.len()),
+                    puzzle.description.clone(),
+                ];
+                for (i, line) in details.into_iter().enumerate() {
+                    text.push(TextEntry { text: line, x: 5.0, y: details_y + i as f32, color: UI_COLOR, scale: 0.6 });
+                }
+            }
+            None if self.puzzles.is_empty() => {
+                text.push(TextEntry {
+                    text: format!("NO PUZZLES IN {}", PUZZLE_DIR),
+                    x: 5.0,
+                    y: details_y,
+                    color: UI_COLOR,
+                    scale: 0.6,
+                });
+            }
+            None => {}
+        }
+
+        (Vec::new(), text)
+    }
+}
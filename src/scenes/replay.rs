@@ -0,0 +1,77 @@
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+use learning1::replay::Replay;
+use learning1::snapshot::BoardSnapshot;
+use crate::graphic_context::{QuadInstance, TextEntry};
+use crate::instance::GameInstance;
+use crate::scenes::title::TitleScene;
+use crate::scenes::{self, KeyInput, Scene, SceneContext, Transition};
+use crate::vertex_data;
+
+// Re-simulates a recorded game from its seed and inputs and shows it
+pub struct ReplayScene {
+    instance: GameInstance,
+    end_tick: u64,
+    finished: bool,
+}
+
+impl ReplayScene {
+    pub fn new(replay: &Replay) -> Self {
+        ReplayScene {
+            instance: GameInstance::from_replay(replay, Instant::now()),
+            end_tick: replay.end_tick,
+            finished: false,
+        }
+    }
+}
+
+impl Scene for ReplayScene {
+    fn update(&mut self, ctx: &mut SceneContext, now: Instant) -> Transition {
+        if !self.finished {
+            self.instance.update(&ctx.config.handling, now);
+            self.instance.take_events();
+            self.finished = self.instance.session.is_playback_finished(self.end_tick);
+        }
+        Transition::None
+    }
+
+    fn handle_key(&mut self, _ctx: &mut SceneContext, input: KeyInput) -> Transition {
+        let leave = input.just_pressed(KeyCode::Escape)
+            || (self.finished && (input.just_pressed(KeyCode::Enter) || input.just_pressed(KeyCode::Space)));
+
+        if leave {
+            Transition::Reset(Box::new(TitleScene::new()))
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&self, ctx: &SceneContext) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+        let effects = self.instance.effects().frame(ctx.theme(), &self.instance.game().rules.piece_set);
+        let (instances, mut text) = vertex_data::build_multi_mesh(&[(self.instance.snapshot(), effects)], ctx.theme());
+
+        text.push(TextEntry {
+            text: if self.finished { "REPLAY END".to_string() } else { "REPLAY".to_string() },
+            x: 0.5,
+            y: 21.0,
+            color: [1.0, 0.3, 0.3, 1.0],
+            scale: 0.8,
+        });
+
+        (instances, text)
+    }
+
+    fn frame_key(&self, _ctx: &SceneContext) -> Option<u64> {
+        let board = self.instance.frame_key()?;
+        Some(scenes::frame_key((board, self.finished)))
+    }
+
+    fn on_resume(&mut self, now: Instant) {
+        self.instance.resume(now);
+    }
+
+    fn debug_board(&self) -> Option<BoardSnapshot> {
+        Some(self.instance.snapshot())
+    }
+}
@@ -0,0 +1,391 @@
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+use learning1::challenge::Challenge;
+use learning1::events::TopOutKind;
+use learning1::mission::MissionTracker;
+use learning1::mode::GameMode;
+use learning1::piece_set::PieceSet;
+use learning1::puzzle::Puzzle;
+use learning1::session::Session;
+use learning1::tetromino::TetrominoShape;
+use crate::graphic_context::{QuadInstance, TextEntry};
+use crate::highscores::{self, BestResult};
+use crate::leaderboard::{LeaderboardRequest, LeaderboardState};
+use crate::scenes::leaderboard::LeaderboardScene;
+use crate::scenes::menu::Menu;
+use crate::scenes::playing::PlayingScene;
+use crate::scenes::title::TitleScene;
+use crate::scenes::{self, KeyInput, MouseInput, Scene, SceneContext, Transition};
+use crate::stats_export::{ExportFormat, SessionStats};
+
+const UI_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+const BEST_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+const ROWS_TOP: f32 = 8.0;
+const ROW_SPACING: f32 = 1.5;
+const STATS_PER_LINE: usize = 7;
+
+// What each line of the results menu does
+enum ResultsEntry {
+    PlayAgain,
+    ExportStats,
+    Leaderboard,
+    Title,
+}
+
+// Shown after a game ends, with the final numbers
+pub struct ResultsScene {
+    mode: GameMode,
+    score: u32,
+    lines: u32,
+    pieces: u32,
+    seed: u64,
+    // Ranked by time (line goal) instead of score
+    timed: bool,
+    // Reached the line goal
+    complete: bool,
+    // Time taken, for timed games that were completed
+    time: Option<u64>,
+    // Final level, for modes with levels
+    level: Option<u32>,
+    // Garbage rows cleared, for digging games
+    garbage: Option<u32>,
+    // The rule that ended the game, when the local player topped out
+    top_out: Option<TopOutKind>,
+    piece_stats: Vec<u32>,
+    // For the letters and colors in the stats
+    piece_set: PieceSet,
+    entries: Vec<ResultsEntry>,
+    menu: Menu,
+    opponent: Option<Opponent>,
+    best: Option<BestResult>,
+    // Play again starts the same challenge, unless it's the daily one
+    challenge: Option<Challenge>,
+    // Or the same puzzle
+    puzzle: Option<Puzzle>,
+    // Or the same mission, whose goals are listed with when they were reached
+    mission: Option<MissionTracker>,
+    // Written out by EXPORT STATS
+    stats: SessionStats,
+    // Where the stats went, or why they couldn't be written
+    export_notice: Option<String>,
+    // The score's trip to the online leaderboard, when it was sent
+    leaderboard: Option<LeaderboardState>,
+    // What the game is ranked under online
+    leaderboard_mode: String,
+}
+
+// How a versus match ended for the local player
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MatchOutcome {
+    Won,
+    Lost,
+    // The connection to an online opponent dropped before anyone topped out
+    OpponentLeft,
+}
+
+struct Opponent {
+    outcome: MatchOutcome,
+    // "CPU", "RIVAL"...
+    name: &'static str,
+    score: u32,
+    // Average CPU skill over the match, after rubber-banding
+    level: Option<f32>,
+}
+
+impl ResultsScene {
+    pub fn new(session: &Session) -> Self {
+        let game = &session.game;
+        let mut scene = ResultsScene {
+            mode: session.mode,
+            score: game.score,
+            lines: game.lines_cleared,
+            pieces: game.piece_stats.iter().sum(),
+            seed: game.seed,
+            timed: session.is_timed(),
+            complete: session.is_complete(),
+            time: (session.is_timed() && session.is_complete()).then_some(session.tick),
+            level: session.mode.has_levels().then_some(game.level),
+            garbage: session.dig_goal().map(|_| game.garbage_cleared),
+            top_out: game.top_out,
+            piece_stats: game.piece_stats.clone(),
+            piece_set: game.rules.piece_set.clone(),
+            entries: Vec::new(),
+            menu: Menu::new(Vec::new(), 0.0, 0.0),
+            opponent: None,
+            best: None,
+            challenge: None,
+            puzzle: session.puzzle().cloned(),
+            mission: None,
+            stats: SessionStats::new(session),
+            export_notice: None,
+            leaderboard: None,
+            leaderboard_mode: session.mode.id().to_string(),
+        };
+        scene.place_menu();
+        scene
+    }
+
+    // Adds the outcome of a versus match
+    pub fn with_opponent(mut self, outcome: MatchOutcome, name: &'static str, score: u32, level: Option<f32>) -> Self {
+        self.opponent = Some(Opponent { outcome, name, score, level });
+        self.place_menu();
+        self
+    }
+
+    // Adds how the game compares to the personal best
+    pub fn with_best(mut self, best: BestResult) -> Self {
+        self.best = Some(best);
+        self.place_menu();
+        self
+    }
+
+    // Adds the submission of the score to the online leaderboard, ranked under `mode`
+    pub fn with_leaderboard(mut self, mode: String, state: LeaderboardState) -> Self {
+        self.leaderboard_mode = mode;
+        self.leaderboard = Some(state);
+        self.place_menu();
+        self
+    }
+
+    pub fn with_challenge(mut self, challenge: Challenge) -> Self {
+        self.challenge = Some(challenge);
+        self.place_menu();
+        self
+    }
+
+    // A mission is complete when all of its goals were reached
+    pub fn with_mission(mut self, mission: MissionTracker) -> Self {
+        self.complete = mission.is_complete();
+        self.mission = Some(mission);
+        self.place_menu();
+        self
+    }
+
+    // Writes the game's stats and says where they went under the menu
+    pub fn export_stats(&mut self, format: ExportFormat) {
+        self.export_notice = Some(match self.stats.export(format) {
+            Ok(path) => format!("SAVED TO {}", path.display()),
+            Err(e) => {
+                tracing::error!("Could not export stats: {}", e);
+                "COULD NOT EXPORT STATS".to_string()
+            }
+        });
+    }
+
+    fn mode_name(&self) -> &str {
+        match (&self.challenge, &self.puzzle, &self.mission) {
+            (Some(challenge), _, _) => challenge.name,
+            (None, Some(puzzle), _) => &puzzle.name,
+            (None, None, Some(mission)) => &mission.mission.name,
+            (None, None, None) => self.mode.name(),
+        }
+    }
+
+    // One line about the online submission, under the headline
+    fn leaderboard_status(&self, ctx: &SceneContext) -> Option<String> {
+        let status = match self.leaderboard.as_ref()? {
+            LeaderboardState::Loading(_) => "SUBMITTING SCORE...".to_string(),
+            LeaderboardState::Failed => "LEADERBOARD UNAVAILABLE".to_string(),
+            LeaderboardState::Loaded(entries) => {
+                let name = &ctx.config.leaderboard.name;
+                match entries.iter().position(|entry| &entry.name == name && entry.score == self.score) {
+                    Some(rank) => format!("ONLINE RANK #{}", rank + 1),
+                    None => "NOT IN THE ONLINE TOP 10".to_string(),
+                }
+            }
+        };
+        Some(status)
+    }
+
+    fn rows(&self) -> Vec<(String, String)> {
+        let mut rows = vec![("MODE".to_string(), self.mode_name().to_string())];
+        if let Some(top_out) = self.top_out {
+            rows.push(("TOP OUT".to_string(), top_out.name().to_string()));
+        }
+        if let Some(time) = self.time {
+            rows.push(("TIME".to_string(), highscores::format_time(time)));
+        }
+        rows.extend([
+            ("SCORE".to_string(), self.score.to_string()),
+            ("LINES".to_string(), self.lines.to_string()),
+        ]);
+        if let Some(level) = self.level {
+            rows.push(("LEVEL".to_string(), level.to_string()));
+        }
+        if let Some(garbage) = self.garbage {
+            rows.push(("GARBAGE".to_string(), garbage.to_string()));
+        }
+        rows.extend([
+            ("PIECES".to_string(), self.pieces.to_string()),
+            ("SEED".to_string(), self.seed.to_string()),
+        ]);
+
+        // The time each goal was reached at, in order
+        if let Some(mission) = &self.mission {
+            for i in 0..mission.mission.goals.len() {
+                let reached = mission.reached_at.get(i).map_or("-".to_string(), |&tick| highscores::format_time(tick));
+                rows.push((format!("GOAL {}", i + 1), reached));
+            }
+        }
+
+        if let Some(opponent) = &self.opponent {
+            rows.push((format!("{} SCORE", opponent.name), opponent.score.to_string()));
+            if let Some(level) = opponent.level {
+                rows.push((format!("{} LEVEL", opponent.name), format!("{:.0}%", level * 100.0)));
+            }
+        }
+
+        if let Some(best) = self.best.as_ref().filter(|best| !best.is_new_best) {
+            let value = match (self.timed, best.best.time_ticks) {
+                (true, Some(time)) => highscores::format_time(time),
+                (true, None) => "-".to_string(),
+                (false, _) => best.best.score.to_string(),
+            };
+            rows.push(("BEST".to_string(), value));
+        }
+
+        rows
+    }
+
+    // Lines taken by the piece stats under the rows
+    fn stats_lines(&self) -> usize {
+        self.piece_stats.len().div_ceil(STATS_PER_LINE).max(1)
+    }
+
+    // The menu goes under the rows and the piece stats lines, which vary with the kind of game
+    fn place_menu(&mut self) {
+        let mut y = ROWS_TOP + (self.rows().len() + self.stats_lines()) as f32 * ROW_SPACING + 1.0;
+        if self.best.as_ref().is_some_and(|best| best.is_new_best) {
+            y += ROW_SPACING;
+        }
+        // The daily challenge can't be played again
+        self.entries = Vec::new();
+        if !self.challenge.as_ref().is_some_and(|challenge| challenge.single_attempt()) {
+            self.entries.push(ResultsEntry::PlayAgain);
+        }
+        self.entries.push(ResultsEntry::ExportStats);
+        if self.leaderboard.is_some() {
+            self.entries.push(ResultsEntry::Leaderboard);
+        }
+        self.entries.push(ResultsEntry::Title);
+
+        let items = self.entries.iter().map(|entry| match entry {
+            ResultsEntry::PlayAgain => "PLAY AGAIN".to_string(),
+            ResultsEntry::ExportStats => "EXPORT STATS".to_string(),
+            ResultsEntry::Leaderboard => "LEADERBOARD".to_string(),
+            ResultsEntry::Title => "TITLE".to_string(),
+        }).collect();
+        self.menu = Menu::new(items, 8.0, y);
+    }
+
+    fn activate(&mut self, ctx: &mut SceneContext, index: usize) -> Transition {
+        match self.entries[index] {
+            ResultsEntry::PlayAgain => match (&self.challenge, &self.puzzle, &self.mission) {
+                (Some(challenge), _, _) => Transition::Replace(Box::new(PlayingScene::with_challenge(ctx, challenge.clone()))),
+                (None, Some(puzzle), _) => Transition::Replace(Box::new(PlayingScene::with_puzzle(ctx, puzzle.clone()))),
+                (None, None, Some(mission)) => Transition::Replace(Box::new(PlayingScene::with_mission(ctx, mission.mission.clone()))),
+                (None, None, None) => Transition::Replace(scenes::start_game(ctx, self.mode)),
+            },
+            ResultsEntry::ExportStats => {
+                self.export_stats(ctx.config.stats.format);
+                Transition::None
+            }
+            // The top 10 that came back with the submission, or a fresh look when it isn't there yet
+            ResultsEntry::Leaderboard => {
+                let state = match &self.leaderboard {
+                    Some(LeaderboardState::Loaded(entries)) => LeaderboardState::Loaded(entries.clone()),
+                    _ => LeaderboardState::Loading(LeaderboardRequest::fetch(&ctx.config.leaderboard, &self.leaderboard_mode)),
+                };
+                Transition::Push(Box::new(LeaderboardScene::new(self.mode_name().to_string(), state)))
+            }
+            ResultsEntry::Title => Transition::Reset(Box::new(TitleScene::new())),
+        }
+    }
+}
+
+impl Scene for ResultsScene {
+    fn update(&mut self, _ctx: &mut SceneContext, _now: Instant) -> Transition {
+        if let Some(leaderboard) = &mut self.leaderboard {
+            leaderboard.poll();
+        }
+        Transition::None
+    }
+
+    fn handle_key(&mut self, ctx: &mut SceneContext, input: KeyInput) -> Transition {
+        if input.just_pressed(KeyCode::Escape) {
+            return Transition::Reset(Box::new(TitleScene::new()));
+        }
+
+        match self.menu.handle_key(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    fn handle_mouse(&mut self, ctx: &mut SceneContext, input: MouseInput) -> Transition {
+        match self.menu.handle_mouse(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    // Waits for input, or for the leaderboard's answer while it is on its way
+    fn next_update(&self, _ctx: &SceneContext, now: Instant) -> Option<Instant> {
+        self.leaderboard.as_ref().filter(|leaderboard| leaderboard.is_loading()).map(|_| now + scenes::NETWORK_POLL_INTERVAL)
+    }
+
+    fn render(&self, ctx: &SceneContext) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+        let (headline, color) = match self.opponent.as_ref().map(|opponent| opponent.outcome) {
+            Some(MatchOutcome::Won) => ("YOU WIN", [0.3, 1.0, 0.3, 1.0]),
+            Some(MatchOutcome::Lost) => ("YOU LOSE", [1.0, 0.3, 0.3, 1.0]),
+            Some(MatchOutcome::OpponentLeft) => ("OPPONENT LEFT", [1.0, 0.8, 0.3, 1.0]),
+            None if self.complete => ("CLEAR!", [0.3, 1.0, 0.3, 1.0]),
+            None if self.puzzle.is_some() || self.mission.is_some() => ("FAILED", [1.0, 0.3, 0.3, 1.0]),
+            None => ("GAME OVER", [1.0, 0.3, 0.3, 1.0]),
+        };
+        let mut text = vec![TextEntry {
+            text: headline.to_string(),
+            x: 7.5,
+            y: 4.0,
+            color,
+            scale: 1.2,
+        }];
+        if let Some(status) = self.leaderboard_status(ctx) {
+            text.push(TextEntry { text: status, x: 7.0, y: 5.8, color: UI_COLOR, scale: 0.6 });
+        }
+
+        let rows = self.rows();
+        let stats_y = ROWS_TOP + rows.len() as f32 * ROW_SPACING;
+        for (i, (label, value)) in rows.into_iter().enumerate() {
+            let y = ROWS_TOP + i as f32 * ROW_SPACING;
+            text.push(TextEntry { text: label, x: 7.0, y, color: UI_COLOR, scale: 0.8 });
+            text.push(TextEntry { text: value, x: 14.0, y, color: [1.0, 1.0, 1.0, 1.0], scale: 0.8 });
+        }
+
+        // How many of each piece were dealt, in their colors, 7 per line
+        for (i, count) in self.piece_stats.iter().enumerate() {
+            let shape = TetrominoShape::from_index(i);
+            text.push(TextEntry {
+                text: format!("{}{}", self.piece_set.letter(shape), count),
+                x: 3.0 + (i % STATS_PER_LINE) as f32 * 3.0,
+                y: stats_y + (i / STATS_PER_LINE) as f32 * ROW_SPACING,
+                color: ctx.theme().piece_color(&self.piece_set, shape),
+                scale: 0.6,
+            });
+        }
+        let rows_end = stats_y + self.stats_lines() as f32 * ROW_SPACING;
+
+        if self.best.as_ref().is_some_and(|best| best.is_new_best) {
+            text.push(TextEntry { text: "NEW PERSONAL BEST!".to_string(), x: 7.0, y: rows_end, color: BEST_COLOR, scale: 0.8 });
+        }
+
+        text.extend(self.menu.text_entries());
+        if let Some(notice) = &self.export_notice {
+            let y = self.menu.item_rect(self.menu.items.len()).y;
+            text.push(TextEntry { text: notice.clone(), x: 3.0, y, color: UI_COLOR, scale: 0.5 });
+        }
+
+        (Vec::new(), text)
+    }
+}
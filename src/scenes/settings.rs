@@ -0,0 +1,202 @@
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+use crate::config::Config;
+use crate::graphic_context::{QuadInstance, TextEntry};
+use crate::scenes::menu::Menu;
+use crate::scenes::{KeyInput, MouseInput, Scene, SceneContext, Transition};
+
+const UI_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+const HINT_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+const DAS_STEP_MS: u32 = 10;
+const MAX_DAS_MS: u32 = 500;
+const ARR_STEP_MS: u32 = 5;
+const MAX_ARR_MS: u32 = 200;
+// The soft drop speeds offered, slowest first. The last one is sonic drop.
+const SOFT_DROP_FACTORS: [f32; 6] = [5.0, 10.0, 20.0, 40.0, 80.0, f32::INFINITY];
+
+// What each line of the settings menu changes
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SettingsEntry {
+    Das,
+    Arr,
+    SoftDrop,
+    Ghost,
+    Theme,
+    Palette,
+    Patterns,
+    Back,
+}
+
+const ENTRIES: [SettingsEntry; 8] = [
+    SettingsEntry::Das,
+    SettingsEntry::Arr,
+    SettingsEntry::SoftDrop,
+    SettingsEntry::Ghost,
+    SettingsEntry::Theme,
+    SettingsEntry::Palette,
+    SettingsEntry::Patterns,
+    SettingsEntry::Back,
+];
+
+// Handling and display settings, changed with the left and right arrows. They apply right away,
+// even to a paused game, and are written back to the config file after each change.
+pub struct SettingsScene {
+    menu: Menu,
+    // Where the settings went, or why they couldn't be written
+    notice: Option<String>,
+}
+
+impl SettingsScene {
+    pub fn new(ctx: &SceneContext) -> Self {
+        let items = ENTRIES.iter().map(|&entry| entry_label(ctx, entry)).collect();
+        SettingsScene { menu: Menu::new(items, 5.0, 7.0), notice: None }
+    }
+
+    // Moves the selected setting one step, `forward` to the right
+    fn change(&mut self, ctx: &mut SceneContext, forward: bool) {
+        let entry = ENTRIES[self.menu.selected];
+        let handling = &mut ctx.config.handling;
+        match entry {
+            SettingsEntry::Das => handling.das_ms = step(handling.das_ms, DAS_STEP_MS, MAX_DAS_MS, forward),
+            SettingsEntry::Arr => handling.arr_ms = step(handling.arr_ms, ARR_STEP_MS, MAX_ARR_MS, forward),
+            SettingsEntry::SoftDrop => {
+                // From the closest speed offered, for factors typed into the file
+                let factor = handling.soft_drop_factor();
+                let current = SOFT_DROP_FACTORS.iter().rposition(|&offered| offered <= factor).unwrap_or(0);
+                let next = if forward { (current + 1).min(SOFT_DROP_FACTORS.len() - 1) } else { current.saturating_sub(1) };
+                handling.soft_drop_factor = SOFT_DROP_FACTORS[next];
+            }
+            SettingsEntry::Ghost => {
+                ctx.config.display.ghost_piece = !ctx.config.display.ghost_piece;
+                ctx.refresh_theme();
+            }
+            SettingsEntry::Theme if forward => ctx.next_theme(),
+            SettingsEntry::Theme => ctx.previous_theme(),
+            SettingsEntry::Palette => {
+                ctx.config.display.palette = ctx.config.display.palette.cycle(forward);
+                ctx.refresh_theme();
+            }
+            SettingsEntry::Patterns => {
+                ctx.config.display.piece_patterns = !ctx.config.display.piece_patterns;
+                ctx.refresh_theme();
+            }
+            SettingsEntry::Back => return,
+        }
+
+        self.menu.items[self.menu.selected] = entry_label(ctx, entry);
+        self.save(ctx);
+    }
+
+    // Only the settings on this screen are written, the rest comes from the file as it is.
+    // That keeps command line overrides like --present-mode out of it. A file that doesn't parse
+    // is left alone, writing it back would replace everything else in it with the defaults.
+    fn save(&mut self, ctx: &SceneContext) {
+        let path = &ctx.config_path;
+        let mut config = match Config::read_from(path) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::error!("Not saving the settings, {} could not be read: {}", path.display(), e);
+                self.notice = Some(format!("NOT SAVED, FIX THE ERRORS IN {} FIRST", path.display()));
+                return;
+            }
+        };
+        config.handling = ctx.config.handling.clone();
+        config.display.ghost_piece = ctx.config.display.ghost_piece;
+        config.display.theme = ctx.config.display.theme.clone();
+        config.display.palette = ctx.config.display.palette;
+        config.display.piece_patterns = ctx.config.display.piece_patterns;
+
+        self.notice = Some(match config.save_to(path) {
+            Ok(()) => format!("SAVED TO {}", path.display()),
+            Err(e) => {
+                tracing::error!("Could not save the settings to {}: {}", path.display(), e);
+                "COULD NOT SAVE THE SETTINGS".to_string()
+            }
+        });
+    }
+
+    fn activate(&mut self, ctx: &mut SceneContext, index: usize) -> Transition {
+        match ENTRIES[index] {
+            SettingsEntry::Back => Transition::Pop,
+            _ => {
+                self.change(ctx, true);
+                Transition::None
+            }
+        }
+    }
+}
+
+// Steps `value` by `step` within 0..=max
+fn step(value: u32, step: u32, max: u32, forward: bool) -> u32 {
+    if forward { (value + step).min(max) } else { value.saturating_sub(step) }
+}
+
+fn entry_label(ctx: &SceneContext, entry: SettingsEntry) -> String {
+    let handling = &ctx.config.handling;
+    match entry {
+        SettingsEntry::Das => format!("DAS: {} MS", handling.das_ms),
+        SettingsEntry::Arr if handling.arr_ms == 0 => "ARR: 0 MS (INSTANT)".to_string(),
+        SettingsEntry::Arr => format!("ARR: {} MS", handling.arr_ms),
+        SettingsEntry::SoftDrop if handling.is_sonic_drop() => "SOFT DROP: SONIC".to_string(),
+        SettingsEntry::SoftDrop => format!("SOFT DROP: {}X", handling.soft_drop_factor()),
+        SettingsEntry::Ghost => format!("GHOST PIECE: {}", if ctx.config.display.ghost_piece { "ON" } else { "OFF" }),
+        SettingsEntry::Theme => format!("THEME: {}", ctx.theme().name),
+        SettingsEntry::Palette => format!("COLORS: {}", ctx.config.display.palette.name()),
+        SettingsEntry::Patterns => format!("SHAPE PATTERNS: {}", if ctx.config.display.piece_patterns { "ON" } else { "OFF" }),
+        SettingsEntry::Back => "BACK".to_string(),
+    }
+}
+
+impl Scene for SettingsScene {
+    fn handle_key(&mut self, ctx: &mut SceneContext, input: KeyInput) -> Transition {
+        if input.just_pressed(KeyCode::Escape) {
+            return Transition::Pop;
+        }
+        if input.pressed && matches!(input.code, KeyCode::ArrowLeft | KeyCode::ArrowRight) {
+            self.change(ctx, input.code == KeyCode::ArrowRight);
+            return Transition::None;
+        }
+
+        match self.menu.handle_key(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    fn handle_mouse(&mut self, ctx: &mut SceneContext, input: MouseInput) -> Transition {
+        match self.menu.handle_mouse(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    fn next_update(&self, _ctx: &SceneContext, _now: Instant) -> Option<Instant> {
+        None
+    }
+
+    fn render(&self, _ctx: &SceneContext) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+        let mut text = vec![TextEntry {
+            text: "SETTINGS".to_string(),
+            x: 5.0,
+            y: 4.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            scale: 1.2,
+        }];
+        text.extend(self.menu.text_entries());
+
+        let hint_y = self.menu.item_rect(self.menu.items.len()).y + 1.0;
+        text.push(TextEntry {
+            text: "LEFT/RIGHT: CHANGE  ESC: BACK".to_string(),
+            x: 5.0,
+            y: hint_y,
+            color: HINT_COLOR,
+            scale: 0.6,
+        });
+        if let Some(notice) = &self.notice {
+            text.push(TextEntry { text: notice.clone(), x: 5.0, y: hint_y + 1.0, color: UI_COLOR, scale: 0.5 });
+        }
+
+        (Vec::new(), text)
+    }
+}
@@ -0,0 +1,205 @@
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+use chrono::Datelike;
+
+use learning1::challenge::Challenge;
+use learning1::game::Game;
+use learning1::mode::GameMode;
+use learning1::replay::Replay;
+use learning1::snapshot::BoardSnapshot;
+use crate::autoplay::BotController;
+use crate::daily::{self, DailyLog, DailyResult};
+use crate::graphic_context::{QuadInstance, TextEntry};
+use crate::instance::GameInstance;
+use crate::save::SaveGame;
+use crate::scenes::menu::Menu;
+use crate::scenes::playing::PlayingScene;
+use crate::scenes::replay::ReplayScene;
+use crate::scenes::settings::SettingsScene;
+use crate::scenes::{self, KeyInput, MouseInput, Scene, SceneContext, Transition};
+use crate::storage;
+use crate::vertex_data::{self, BoardArea};
+
+// The attract demo plays a bit faster than the default bot speed, it's just for show
+const DEMO_ACTIONS_PER_SECOND: f32 = 15.0;
+const DEMO_ALPHA: f32 = 0.2;
+
+// What each line of the title menu does
+enum TitleEntry {
+    Continue,
+    Play(GameMode),
+    Weekly(Box<Challenge>),
+    // Today's challenge, with the result when it was already played
+    Daily(Box<Challenge>, Option<DailyResult>),
+    WatchReplay,
+    ToggleAutoplay,
+    Settings,
+    Quit,
+}
+
+pub struct TitleScene {
+    entries: Vec<TitleEntry>,
+    menu: Menu,
+    // A bot playing in the background of the menu
+    demo: GameInstance,
+}
+
+impl TitleScene {
+    pub fn new() -> Self {
+        let mut entries = Vec::new();
+        if SaveGame::exists() {
+            entries.push(TitleEntry::Continue);
+        }
+        // Online play needs sockets and threads, which the browser doesn't have
+        let modes = GameMode::ALL.into_iter().filter(|&mode| !(cfg!(target_arch = "wasm32") && mode == GameMode::Online));
+        entries.extend(modes.map(TitleEntry::Play));
+        entries.push(TitleEntry::Weekly(Box::new(this_weeks_challenge())));
+        let today = daily::todays_challenge();
+        let played = DailyLog::load().get(&today).cloned();
+        entries.push(TitleEntry::Daily(Box::new(today), played));
+        if storage::last_replay_path().exists() {
+            entries.push(TitleEntry::WatchReplay);
+        }
+        entries.push(TitleEntry::ToggleAutoplay);
+        entries.push(TitleEntry::Settings);
+        entries.push(TitleEntry::Quit);
+
+        let items = entries.iter().map(|entry| entry_label(entry, false)).collect();
+
+        TitleScene {
+            entries,
+            // Starts high enough for the longest menu (a saved game and a replay) to fit
+            menu: Menu::new(items, 8.0, 5.0),
+            demo: new_demo(Instant::now()),
+        }
+    }
+
+    fn activate(&mut self, ctx: &mut SceneContext, index: usize) -> Transition {
+        match &self.entries[index] {
+            TitleEntry::Continue => match SaveGame::take() {
+                Ok(session) => Transition::Push(Box::new(PlayingScene::from_session(session))),
+                Err(e) => {
+                    tracing::warn!("Could not load the saved game: {}", e);
+                    // The save is gone (or was broken), rebuild the menu without it
+                    *self = TitleScene::new();
+                    Transition::None
+                }
+            },
+            TitleEntry::Play(mode) => Transition::Push(scenes::start_game(ctx, *mode)),
+            TitleEntry::Weekly(challenge) => Transition::Push(Box::new(PlayingScene::with_challenge(ctx, (**challenge).clone()))),
+            // One attempt a day: it's used up as soon as the game starts. Games the bot plays don't count.
+            TitleEntry::Daily(_, Some(_)) => Transition::None,
+            TitleEntry::Daily(challenge, None) => {
+                if !ctx.autoplay {
+                    daily::record(challenge, DailyResult::default());
+                }
+                Transition::Push(Box::new(PlayingScene::with_challenge(ctx, (**challenge).clone())))
+            }
+            TitleEntry::WatchReplay => match Replay::load(&storage::last_replay_path()) {
+                Ok(replay) => Transition::Push(Box::new(ReplayScene::new(&replay))),
+                Err(e) => {
+                    tracing::warn!("Could not load replay: {}", e);
+                    Transition::None
+                }
+            },
+            TitleEntry::ToggleAutoplay => {
+                ctx.autoplay = !ctx.autoplay;
+                Transition::None
+            }
+            TitleEntry::Settings => Transition::Push(Box::new(SettingsScene::new(ctx))),
+            TitleEntry::Quit => Transition::Quit,
+        }
+    }
+}
+
+fn entry_label(entry: &TitleEntry, autoplay: bool) -> String {
+    match entry {
+        TitleEntry::Continue => "CONTINUE".to_string(),
+        TitleEntry::Play(mode) => mode.name().to_string(),
+        TitleEntry::Weekly(challenge) => format!("WEEKLY: {}", challenge.name),
+        TitleEntry::Daily(_, None) => "DAILY CHALLENGE".to_string(),
+        TitleEntry::Daily(_, Some(result)) => format!("DAILY: PLAYED ({})", result.score),
+        TitleEntry::WatchReplay => "WATCH REPLAY".to_string(),
+        TitleEntry::ToggleAutoplay => format!("AUTOPLAY: {}", if autoplay { "ON" } else { "OFF" }),
+        TitleEntry::Settings => "SETTINGS".to_string(),
+        TitleEntry::Quit => "QUIT".to_string(),
+    }
+}
+
+// Weeks follow ISO 8601 in UTC, like the daily challenge, so the challenge changes at the same moment everywhere
+fn this_weeks_challenge() -> Challenge {
+    let week = chrono::Utc::now().iso_week();
+    Challenge::for_week(week.year(), week.week())
+}
+
+fn new_demo(now: Instant) -> GameInstance {
+    let mut demo = GameInstance::new(Game::new(), GameMode::Endless, now);
+    demo.set_bot(Some(BotController::new(DEMO_ACTIONS_PER_SECOND, now)));
+    demo
+}
+
+impl Scene for TitleScene {
+    fn update(&mut self, ctx: &mut SceneContext, now: Instant) -> Transition {
+        self.demo.update(&ctx.config.handling, now);
+        self.demo.take_events();
+        if self.demo.game().is_game_over {
+            self.demo = new_demo(now);
+        }
+
+        // Labels can depend on shared state that changes elsewhere (e.g. the autoplay flag)
+        for (item, entry) in self.menu.items.iter_mut().zip(&self.entries) {
+            *item = entry_label(entry, ctx.autoplay);
+        }
+
+        Transition::None
+    }
+
+    fn handle_key(&mut self, ctx: &mut SceneContext, input: KeyInput) -> Transition {
+        if input.just_pressed(KeyCode::Escape) {
+            return Transition::Quit;
+        }
+
+        match self.menu.handle_key(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    fn handle_mouse(&mut self, ctx: &mut SceneContext, input: MouseInput) -> Transition {
+        match self.menu.handle_mouse(input) {
+            Some(index) => self.activate(ctx, index),
+            None => Transition::None,
+        }
+    }
+
+    fn render(&self, ctx: &SceneContext) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+        let demo_area = BoardArea { x: 0.0, y: 0.0, scale: 1.0, alpha: DEMO_ALPHA };
+        let effects = self.demo.effects().frame(ctx.theme(), &self.demo.game().rules.piece_set);
+        let (instances, mut text) = vertex_data::build_board_mesh(&self.demo.snapshot(), &effects, ctx.theme(), demo_area);
+
+        text.push(TextEntry {
+            text: "RUST TETRIS".to_string(),
+            x: 6.0,
+            y: 3.0,
+            color: [0.0, 1.0, 1.0, 1.0],
+            scale: 1.5,
+        });
+        text.extend(self.menu.text_entries());
+
+        (instances, text)
+    }
+
+    // The menu only changes with input, which rebuilds the frame anyway
+    fn frame_key(&self, _ctx: &SceneContext) -> Option<u64> {
+        self.demo.frame_key().map(scenes::frame_key)
+    }
+
+    fn on_resume(&mut self, now: Instant) {
+        self.demo.resume(now);
+    }
+
+    fn debug_board(&self) -> Option<BoardSnapshot> {
+        Some(self.demo.snapshot())
+    }
+}
@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+
+use crate::action::Action;
+use crate::game::{Game, MAX_LEVEL};
+use crate::mode::GameMode;
+use crate::puzzle::{Puzzle, PuzzleStatus};
+use crate::replay::{Replay, ReplayInput};
+
+// The simulation runs on a fixed clock of one tick per millisecond.
+// Gravity and inputs are tied to tick numbers instead of wall-clock time,
+// so feeding the same inputs at the same ticks always produces the same game.
+pub const TICKS_PER_SECOND: u64 = 1000;
+
+pub const DEFAULT_GRAVITY_TICKS: u64 = 500;
+
+/// How fast a game is being played: pieces per second, lines per minute and
+/// actions (inputs) per minute, over the whole time played.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct PlayMetrics {
+    pub pps: f32,
+    pub lpm: f32,
+    pub apm: f32,
+}
+
+/// A [`Game`] running on the deterministic tick clock, recording every input it receives.
+///
+/// A session can also be driven by a [`Replay`], in which case the recorded inputs
+/// are fed back in at the ticks they originally happened.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub game: Game,
+    pub mode: GameMode,
+    pub tick: u64,
+    // Ticks between two gravity steps
+    pub gravity_interval: u64,
+    next_gravity_tick: u64,
+    inputs: Vec<ReplayInput>,
+    // Set when playing a puzzle, which decides when the game is won or lost
+    #[serde(default)]
+    puzzle: Option<Puzzle>,
+    // Pieces were taken back in this game (practice modes), so it doesn't count towards the bests.
+    // Kept with the session so it survives Save & Quit.
+    #[serde(default)]
+    pub rewound: bool,
+    // Inputs still to be played back, and where we are in them
+    #[serde(skip)]
+    script: Option<(Vec<ReplayInput>, usize)>,
+}
+
+impl Session {
+    pub fn new(game: Game, mode: GameMode) -> Self {
+        let gravity = if mode.has_levels() { gravity_for_level(game.level) } else { game.rules.gravity_ticks };
+        Session {
+            game,
+            mode,
+            tick: 0,
+            gravity_interval: gravity,
+            next_gravity_tick: gravity,
+            inputs: Vec::new(),
+            puzzle: None,
+            rewound: false,
+            script: None,
+        }
+    }
+
+    // The game should have been created from the puzzle's config
+    pub fn with_puzzle(mut self, puzzle: Puzzle) -> Self {
+        self.puzzle = Some(puzzle);
+        self
+    }
+
+    pub fn from_replay(replay: &Replay) -> Self {
+        let config = replay.puzzle.as_ref().map(Puzzle::game_config).unwrap_or_default()
+            .with_seed(replay.seed)
+            .with_rules(replay.rules.clone())
+            .with_start_level(replay.start_level);
+        let mut session = Session::new(Game::with_config(config), replay.mode);
+        session.puzzle = replay.puzzle.clone();
+        session.script = Some((replay.inputs.clone(), 0));
+        session
+    }
+
+    // Advances the clock by one tick: gravity first, then any scripted inputs for that tick.
+    // Live inputs arriving "between" ticks are applied after this, which is the same order.
+    pub fn step(&mut self) {
+        // The clock stops with the game, so the final tick is the time it took
+        if self.is_finished() {
+            return;
+        }
+        self.tick += 1;
+
+        if self.mode.has_levels() {
+            self.gravity_interval = gravity_for_level(self.game.level);
+            // Don't wait out a slow interval that was scheduled before the level went up
+            self.next_gravity_tick = self.next_gravity_tick.min(self.tick + self.gravity_interval);
+        }
+
+        if self.tick >= self.next_gravity_tick {
+            self.game.update();
+            self.next_gravity_tick = self.tick + self.gravity_interval;
+        }
+
+        if let Some((script, cursor)) = &mut self.script {
+            while let Some(input) = script.get(*cursor).filter(|input| input.tick <= self.tick) {
+                self.game.apply(input.action);
+                self.inputs.push(*input);
+                *cursor += 1;
+            }
+        }
+    }
+
+    // Applies an input at the current tick and records it
+    pub fn apply(&mut self, action: Action) {
+        if self.is_finished() {
+            return;
+        }
+        self.game.apply(action);
+        self.inputs.push(ReplayInput { tick: self.tick, action });
+    }
+
+    /// Play rates from the game's counters and the ticks played. All 0 during the first second,
+    /// where they would mostly be noise.
+    pub fn metrics(&self) -> PlayMetrics {
+        if self.tick < TICKS_PER_SECOND {
+            return PlayMetrics::default();
+        }
+        let seconds = self.tick as f32 / TICKS_PER_SECOND as f32;
+        PlayMetrics {
+            pps: self.game.pieces_locked as f32 / seconds,
+            lpm: self.game.lines_cleared as f32 / seconds * 60.0,
+            apm: self.game.actions as f32 / seconds * 60.0,
+        }
+    }
+
+    // Lines to clear to finish, from the rules or else the mode
+    pub fn line_goal(&self) -> Option<u32> {
+        self.game.rules.line_goal.or(self.mode.line_goal())
+    }
+
+    // Garbage rows to clear to finish (cheese race)
+    pub fn dig_goal(&self) -> Option<u32> {
+        self.game.rules.dig_goal
+    }
+
+    // Sprint-like games and digging race the clock. Marathon has a goal too, but ranks by score.
+    pub fn is_timed(&self) -> bool {
+        (self.line_goal().is_some() || self.dig_goal().is_some()) && !self.mode.has_levels()
+    }
+
+    pub fn puzzle(&self) -> Option<&Puzzle> {
+        self.puzzle.as_ref()
+    }
+
+    pub fn puzzle_status(&self) -> Option<PuzzleStatus> {
+        self.puzzle.as_ref().map(|puzzle| puzzle.status(&self.game))
+    }
+
+    // True once the line goal is reached (e.g. 40 lines in Sprint), the garbage is dug or the puzzle is solved
+    pub fn is_complete(&self) -> bool {
+        self.line_goal().is_some_and(|goal| self.game.lines_cleared >= goal)
+            || self.dig_goal().is_some_and(|goal| self.game.garbage_cleared >= goal)
+            || self.puzzle_status() == Some(PuzzleStatus::Solved)
+    }
+
+    // Topped out, completed or out of puzzle pieces, either way nothing moves anymore
+    pub fn is_finished(&self) -> bool {
+        self.game.is_game_over || self.is_complete() || self.puzzle_status() == Some(PuzzleStatus::Failed)
+    }
+
+    // The tick gravity moves the piece down next, so a caller with nothing else to do can wait for it
+    pub fn next_gravity_tick(&self) -> u64 {
+        self.next_gravity_tick
+    }
+
+    pub fn is_playback(&self) -> bool {
+        self.script.is_some()
+    }
+
+    // True once a replay has fed all its inputs and the game has reached the recorded end
+    pub fn is_playback_finished(&self, end_tick: u64) -> bool {
+        match &self.script {
+            Some((script, cursor)) => self.is_finished() || (*cursor >= script.len() && self.tick >= end_tick),
+            None => false,
+        }
+    }
+
+    pub fn to_replay(&self) -> Replay {
+        Replay {
+            version: Replay::VERSION,
+            seed: self.game.seed,
+            mode: self.mode,
+            rules: self.game.rules.clone(),
+            start_level: self.game.start_level,
+            puzzle: self.puzzle.clone(),
+            end_tick: self.tick,
+            inputs: self.inputs.clone(),
+        }
+    }
+}
+
+// The guideline gravity curve: (0.8 - (level - 1) * 0.007) ^ (level - 1) seconds per row.
+// One second at level 1 down to a few ticks at level 15.
+pub fn gravity_for_level(level: u32) -> u64 {
+    let level = level.clamp(1, MAX_LEVEL) as f64 - 1.0;
+    let seconds = (0.8 - level * 0.007).powf(level);
+    ((seconds * TICKS_PER_SECOND as f64) as u64).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Bot;
+    use crate::game::{GameConfig, WIDTH};
+
+    #[test]
+    fn recorded_session_replays_to_the_same_game() {
+        let mut session = Session::new(Game::with_config(GameConfig::default().with_seed(7)), GameMode::Endless);
+        // The bot's inputs with time passing between them, so gravity moves pieces too, and some garbage on the way
+        let bot = Bot::new();
+        for piece in 0..60u64 {
+            for action in bot.plan(&session.game) {
+                for _ in 0..(piece * 17 % 40) {
+                    session.step();
+                }
+                session.apply(action);
+            }
+            if piece % 20 == 19 {
+                session.apply(Action::AddGarbage { lines: 2, hole: (piece % WIDTH as u64) as u8 });
+            }
+        }
+        assert!(session.game.lines_cleared > 0);
+
+        // Through JSON, like a saved replay file
+        let json = serde_json::to_string(&session.to_replay()).expect("replay serializes");
+        let replay: Replay = serde_json::from_str(&json).expect("replay deserializes");
+        let mut playback = Session::from_replay(&replay);
+        while !playback.is_playback_finished(replay.end_tick) {
+            playback.step();
+        }
+
+        assert_eq!(playback.tick, session.tick);
+        assert_eq!(playback.game.grid, session.game.grid);
+        assert_eq!(playback.game.score, session.game.score);
+        assert_eq!(playback.game.lines_cleared, session.game.lines_cleared);
+        assert_eq!(playback.game.is_game_over, session.game.is_game_over);
+    }
+}
@@ -1,28 +1,104 @@
 // Vertex shader
 
+// Logical units (one per grid cell, y down) to clip space, updated when the window is resized
+struct Projection {
+    matrix: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> projection: Projection;
+
 struct VertexInput {
-    @location(0) position: vec3<f32>,
-    @location(1) color: vec4<f32>,
+    @location(0) corner: vec2<f32>,
+};
+
+// Rectangles are left, right, top and bottom, in logical units
+struct InstanceInput {
+    @location(1) rect: vec4<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) uv: vec4<f32>,
+    @location(4) style: u32,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+    // Where in the quad the fragment is, from (0, 0) at the top left to (1, 1)
+    @location(2) local: vec2<f32>,
+    @location(3) @interpolate(flat) style: u32,
 };
 
 @vertex
 fn vs_main(
     model: VertexInput,
+    instance: InstanceInput,
 ) -> VertexOutput {
     var out: VertexOutput;
-    out.color = model.color;
-    out.clip_position = vec4<f32>(model.position, 1.0);
+    // The unit quad's corner picks between the instance's edges
+    let x = mix(instance.rect.x, instance.rect.y, model.corner.x);
+    let y = mix(instance.rect.z, instance.rect.w, model.corner.y);
+    out.color = instance.color;
+    out.uv = vec2<f32>(mix(instance.uv.x, instance.uv.y, model.corner.x), mix(instance.uv.z, instance.uv.w, model.corner.y));
+    out.local = model.corner;
+    out.style = instance.style;
+    out.clip_position = projection.matrix * vec4<f32>(x, y, 0.0, 1.0);
     return out;
 }
 
 // Fragment shader
 
+@group(0) @binding(0)
+var t_atlas: texture_2d<f32>;
+@group(0) @binding(1)
+var s_atlas: sampler;
+
+// Quad styles, see QUAD_STYLE_* in graphic_context.rs
+const STYLE_BEVEL: u32 = 1u;
+const STYLE_ROUNDED: u32 = 2u;
+const STYLE_GLOW: u32 = 3u;
+
+// Widths and radius as a part of the quad's side
+const BEVEL_WIDTH: f32 = 0.14;
+const CORNER_RADIUS: f32 = 0.22;
+
+// A soft light over the upper part of a block, brightest at the top
+fn highlight(local: vec2<f32>) -> f32 {
+    return 0.18 * (1.0 - smoothstep(0.0, 0.55, local.y));
+}
+
+// The atlas is greyscale, the instance color tints it. Flat quads read a white texel,
+// and so do styled ones, which are shaded from their local position instead.
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
+    // Sampled before branching, textures and derivatives need uniform control flow
+    let texel = textureSample(t_atlas, s_atlas, in.uv) * in.color;
+    let p = in.local;
+    // Distance outside the rounded corners, and about a pixel of it for anti-aliasing
+    let corner = length(max(abs(p - vec2<f32>(0.5)) - vec2<f32>(0.5 - CORNER_RADIUS), vec2<f32>(0.0))) - CORNER_RADIUS;
+    let pixel = max(fwidth(corner), 0.0001);
+
+    switch in.style {
+        case STYLE_BEVEL: {
+            // Distance to the nearest edge, and which one it is
+            let edge = min(min(p.x, 1.0 - p.x), min(p.y, 1.0 - p.y));
+            var light = highlight(p);
+            if (edge < BEVEL_WIDTH) {
+                light = select(-0.3, 0.3, min(p.x, p.y) < min(1.0 - p.x, 1.0 - p.y));
+            }
+            return vec4<f32>(clamp(texel.rgb + vec3<f32>(light), vec3<f32>(0.0), vec3<f32>(1.0)), texel.a);
+        }
+        case STYLE_ROUNDED: {
+            let coverage = 1.0 - smoothstep(-pixel, 0.0, corner);
+            let rgb = clamp(texel.rgb + vec3<f32>(highlight(p)), vec3<f32>(0.0), vec3<f32>(1.0));
+            return vec4<f32>(rgb, texel.a * coverage);
+        }
+        case STYLE_GLOW: {
+            let d = length((p - vec2<f32>(0.5)) * 2.0);
+            let falloff = 1.0 - smoothstep(0.0, 1.0, d);
+            return vec4<f32>(texel.rgb, texel.a * falloff * falloff);
+        }
+        default: {
+            return texel;
+        }
+    }
 }
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{is_valid_position, HEIGHT, WIDTH};
+use crate::piece_set::PieceSet;
+use crate::session::PlayMetrics;
+use crate::tetromino::{Point, TetrominoShape};
+
+/// A read-only copy of everything needed to draw a board.
+///
+/// Unlike [`Game`](crate::game::Game) it has no behavior, so it is cheap to keep
+/// around, compare, or send to another process.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct BoardSnapshot {
+    /// Locked cells, `[row][col]`, 0 is empty, then the shape index + 1 and [`GARBAGE_CELL`](crate::game::GARBAGE_CELL) is garbage.
+    /// The first [`HIDDEN_ROWS`](crate::game::HIDDEN_ROWS) rows are above the visible board and shouldn't be drawn.
+    pub grid: [[u8; WIDTH]; HEIGHT],
+    /// The falling piece and its absolute cell positions
+    pub current_piece: Option<(TetrominoShape, Vec<Point>)>,
+    pub next_piece: TetrominoShape,
+    pub score: u32,
+    pub lines_cleared: u32,
+    /// How many of each shape were dealt, indexed like [`TetrominoShape::to_index`]
+    pub piece_stats: Vec<u32>,
+    /// The shapes, letters and colors the game is played with. Only sent when it's not the standard set.
+    #[serde(default, skip_serializing_if = "PieceSet::is_standard")]
+    pub piece_set: PieceSet,
+    /// The piece put aside with Hold
+    pub hold_piece: Option<TetrominoShape>,
+    /// Consecutive pieces that cleared lines
+    pub combo: u32,
+    pub level: u32,
+    pub is_game_over: bool,
+    /// Locked cells should not be drawn (invisible challenge)
+    pub invisible: bool,
+    /// The stack is close to topping out, see [`Game::in_danger`](crate::game::Game::in_danger)
+    #[serde(default)]
+    pub danger: bool,
+    /// Play rates to show with the board. Filled in by whoever runs the game on a clock
+    /// (see [`Session::metrics`](crate::session::Session::metrics)), the game itself has none.
+    #[serde(default)]
+    pub metrics: Option<PlayMetrics>,
+}
+
+impl BoardSnapshot {
+    /// Where the falling piece would land with a hard drop, in absolute grid positions
+    pub fn ghost_cells(&self) -> Option<Vec<Point>> {
+        let (_, cells) = self.current_piece.as_ref()?;
+        let mut drop = 0;
+        // A piece never falls further than the board is tall, even in a snapshot from somewhere else
+        while drop < HEIGHT as i32 && is_valid_position(&self.grid, cells, 0, drop + 1) {
+            drop += 1;
+        }
+        Some(cells.iter().map(|&(x, y)| (x, y + drop)).collect())
+    }
+
+    /// Checks a snapshot that came from outside (e.g. the opponent in an online match) before it is drawn:
+    /// the falling piece has cells and they are on the board, or at most a board's height above it,
+    /// and the piece set is one the game could play
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some((_, cells)) = &self.current_piece {
+            if cells.is_empty() {
+                return Err("the falling piece has no cells".to_string());
+            }
+            let columns = 0..WIDTH as i32;
+            let rows = -(HEIGHT as i32)..HEIGHT as i32;
+            if cells.iter().any(|(x, y)| !columns.contains(x) || !rows.contains(y)) {
+                return Err("the falling piece is off the board".to_string());
+            }
+        }
+        self.piece_set.validate()
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use learning1::session::{Session, TICKS_PER_SECOND};
+use learning1::tetromino::TetrominoShape;
+
+use crate::storage;
+
+// How finished games are written out for external tools (spreadsheets, scripts...)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    // One file per game in stats/, named after when it ended
+    #[default]
+    Json,
+    // One row per game, appended to stats/sessions.csv
+    Csv,
+}
+
+// The numbers of one finished game
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionStats {
+    // Local time the game ended, RFC 3339
+    pub finished_at: String,
+    pub mode: String,
+    pub seed: u64,
+    pub score: u32,
+    pub level: u32,
+    pub lines: u32,
+    pub duration_seconds: f64,
+    pub pieces: u32,
+    // How many of each shape were dealt, by letter
+    pub piece_distribution: BTreeMap<char, u32>,
+    pub singles: u32,
+    pub doubles: u32,
+    pub triples: u32,
+    pub tetrises: u32,
+    pub t_spins: u32,
+    pub pps: f32,
+    pub lpm: f32,
+    pub apm: f32,
+}
+
+const CSV_HEADER: &str = "finished_at,mode,seed,score,level,lines,duration_seconds,pieces,piece_distribution,singles,doubles,triples,tetrises,t_spins,pps,lpm,apm";
+
+impl SessionStats {
+    pub fn new(session: &Session) -> Self {
+        let game = &session.game;
+        let pieces = &game.rules.piece_set;
+        let piece_distribution = game.piece_stats.iter().enumerate()
+            .map(|(i, &count)| (pieces.letter(TetrominoShape::from_index(i)), count))
+            .collect();
+        let [singles, doubles, triples, tetrises] = game.clear_stats;
+        let metrics = session.metrics();
+
+        SessionStats {
+            finished_at: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            mode: session.mode.id().to_string(),
+            seed: game.seed,
+            score: game.score,
+            level: game.level,
+            lines: game.lines_cleared,
+            duration_seconds: session.tick as f64 / TICKS_PER_SECOND as f64,
+            pieces: game.piece_stats.iter().sum(),
+            piece_distribution,
+            singles,
+            doubles,
+            triples,
+            tetrises,
+            t_spins: game.t_spins,
+            pps: metrics.pps,
+            lpm: metrics.lpm,
+            apm: metrics.apm,
+        }
+    }
+
+    // Writes the stats in the user data directory and returns where they went
+    pub fn export(&self, format: ExportFormat) -> io::Result<PathBuf> {
+        let dir = stats_dir();
+        fs::create_dir_all(&dir)?;
+
+        match format {
+            ExportFormat::Json => {
+                let name = chrono::Local::now().format("%Y%m%d-%H%M%S");
+                let path = dir.join(format!("{}.json", name));
+                let json = serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                fs::write(&path, json)?;
+                Ok(path)
+            }
+            ExportFormat::Csv => {
+                let path = dir.join("sessions.csv");
+                let is_new = !path.exists();
+                let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+                if is_new {
+                    writeln!(file, "{}", CSV_HEADER)?;
+                }
+                writeln!(file, "{}", self.csv_row())?;
+                Ok(path)
+            }
+        }
+    }
+
+    // The distribution is a single column like "I=12;O=10;T=11".
+    // Piece letters come from piece set files and can be anything, so text columns are escaped.
+    fn csv_row(&self) -> String {
+        let distribution: Vec<String> = self.piece_distribution.iter().map(|(letter, count)| format!("{}={}", letter, count)).collect();
+        format!(
+            "{},{},{},{},{},{},{:.3},{},{},{},{},{},{},{},{:.2},{:.1},{:.0}",
+            csv_field(&self.finished_at),
+            csv_field(&self.mode),
+            self.seed,
+            self.score,
+            self.level,
+            self.lines,
+            self.duration_seconds,
+            self.pieces,
+            csv_field(&distribution.join(";")),
+            self.singles,
+            self.doubles,
+            self.triples,
+            self.tetrises,
+            self.t_spins,
+            self.pps,
+            self.lpm,
+            self.apm,
+        )
+    }
+}
+
+pub fn stats_dir() -> PathBuf {
+    storage::data_dir().join("stats")
+}
+
+// Quoted when it has a separator, a quote or a line break in it, with quotes doubled (RFC 4180)
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use learning1::game::{Game, GameConfig};
+    use learning1::mode::GameMode;
+
+    #[test]
+    fn t_spins_are_exported_as_a_field_and_a_column() {
+        let mut game = Game::with_config(GameConfig::default().with_seed(1));
+        game.t_spins = 3;
+        let stats = SessionStats::new(&Session::new(game, GameMode::Endless));
+
+        let json: serde_json::Value = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["t_spins"], 3);
+
+        let header: Vec<&str> = CSV_HEADER.split(',').collect();
+        let row = stats.csv_row();
+        let row: Vec<&str> = row.split(',').collect();
+        assert_eq!(row.len(), header.len());
+        let column = header.iter().position(|&name| name == "t_spins").expect("a t_spins column");
+        assert_eq!(row[column], "3");
+    }
+
+    #[test]
+    fn letters_that_are_csv_syntax_are_escaped() {
+        let mut stats = SessionStats::new(&Session::new(Game::with_config(GameConfig::default().with_seed(1)), GameMode::Endless));
+        stats.piece_distribution = BTreeMap::from([(',', 1), ('"', 2), ('I', 3)]);
+
+        let row = stats.csv_row();
+        assert!(row.contains(",\"\"\"=2;,=1;I=3\","), "{}", row);
+        assert_eq!(csv_field("plain"), "plain");
+    }
+}
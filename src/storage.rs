@@ -0,0 +1,19 @@
+use std::path::PathBuf;
+
+// Everything the game writes (replays, saves, scores) lives in the user's data directory,
+// e.g. `~/.local/share/rust-tetris` on Linux or `%APPDATA%\rust-tetris` on Windows.
+// Falls back to the working directory if the platform has no such thing.
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join("rust-tetris"))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+pub fn replay_dir() -> PathBuf {
+    data_dir().join("replays")
+}
+
+// The most recently finished game is always saved here, so it can be watched from the title screen
+pub fn last_replay_path() -> PathBuf {
+    replay_dir().join("last.json")
+}
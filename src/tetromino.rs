@@ -1,56 +1,43 @@
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-// Represents the 7 standard Tetris shapes
-#[derive(Clone, Copy, Debug)] 
-pub enum TetrominoShape {
-    I, O, T, S, Z, J, L
-}
+// A shape of the game's piece set, by its position in the set (see piece_set.rs).
+// With the standard set these are the 7 Tetris shapes, in the order of the constants below.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TetrominoShape(u8);
 
 // A simple coordinate type
 pub type Point = (i32, i32);
 
 impl TetrominoShape {
-    pub fn random() -> Self {
-        let mut rng = rand::rng();
-        TetrominoShape::from_index(rng.random_range(0..7))
-    }
+    pub const I: TetrominoShape = TetrominoShape(0);
+    pub const O: TetrominoShape = TetrominoShape(1);
+    pub const T: TetrominoShape = TetrominoShape(2);
+    pub const S: TetrominoShape = TetrominoShape(3);
+    pub const Z: TetrominoShape = TetrominoShape(4);
+    pub const J: TetrominoShape = TetrominoShape(5);
+    pub const L: TetrominoShape = TetrominoShape(6);
 
     pub fn from_index(index: usize) -> Self {
-        match index {
-            0 => TetrominoShape::I,
-            1 => TetrominoShape::O,
-            2 => TetrominoShape::T,
-            3 => TetrominoShape::S,
-            4 => TetrominoShape::Z,
-            5 => TetrominoShape::J,
-            _ => TetrominoShape::L,
-        }
+        TetrominoShape(index as u8)
     }
 
-    pub fn to_index(&self) -> usize {
-        match self {
-            TetrominoShape::I => 0,
-            TetrominoShape::O => 1,
-            TetrominoShape::T => 2,
-            TetrominoShape::S => 3,
-            TetrominoShape::Z => 4,
-            TetrominoShape::J => 5,
-            TetrominoShape::L => 6,
-        }
+    pub fn to_index(self) -> usize {
+        self.0 as usize
     }
+}
 
-    // Returns the 4 coordinates that make up this shape.
-    // The coordinates are relative to a pivot point (0,0).
-    // We return a fixed-size array of 4 Points.
-    pub fn cells(&self) -> [Point; 4] {
-        match self {
-            TetrominoShape::I => [(0, 0), (-1, 0), (1, 0), (2, 0)],
-            TetrominoShape::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
-            TetrominoShape::T => [(0, 0), (-1, 0), (1, 0), (0, 1)],
-            TetrominoShape::S => [(0, 0), (-1, 0), (0, 1), (1, 1)],
-            TetrominoShape::Z => [(0, 0), (1, 0), (0, 1), (-1, 1)],
-            TetrominoShape::J => [(0, 0), (-1, 0), (1, 0), (-1, 1)],
-            TetrominoShape::L => [(0, 0), (-1, 0), (1, 0), (1, 1)],
-        }
-    }
+// Rotates a set of cells 90 degrees clockwise around the pivot (0,0).
+// In screen space (y grows downwards) (x, y) becomes (-y, x).
+pub fn rotate_cw(cells: &[Point]) -> Vec<Point> {
+    cells.iter().map(|&(x, y)| (-y, x)).collect()
+}
+
+// The other way around: (x, y) becomes (y, -x)
+pub fn rotate_ccw(cells: &[Point]) -> Vec<Point> {
+    cells.iter().map(|&(x, y)| (y, -x)).collect()
+}
+
+// Half a turn flips both axes
+pub fn rotate_180(cells: &[Point]) -> Vec<Point> {
+    cells.iter().map(|&(x, y)| (-x, -y)).collect()
 }
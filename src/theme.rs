@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use learning1::game::GARBAGE_CELL;
+use learning1::piece_set::PieceSet;
+use learning1::tetromino::TetrominoShape;
+
+use crate::config::DisplaySettings;
+
+// Themes ship with the game like the puzzles, and are read from the working directory
+pub const THEME_DIR: &str = "assets/themes";
+
+// How the game looks: background, UI and block colors, and optionally the block tiles.
+// The built-in CLASSIC theme is the default, theme files are JSON and can leave out anything
+// they don't change:
+//
+// { "name": "NEON", "background": [5, 5, 20], "pieces": { "T": [255, 0, 255] } }
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub name: String,
+    // Colors are red, green and blue from 0 to 255, like in piece sets
+    pub background: [u8; 3],
+    pub ui: [u8; 3],
+    pub garbage: [u8; 3],
+    // Behind the playfield, when the well background is on
+    pub well: [u8; 3],
+    // Colors by piece letter, replacing the piece set's own. Pieces not listed keep theirs.
+    pub pieces: BTreeMap<char, [u8; 3]>,
+    // PNG laid out like assets/blocks.png (twice as wide as tall, a white tile on the left and
+    // the block tile on the right), relative to the theme file. The default tiles when missing.
+    pub blocks: Option<PathBuf>,
+    // How blocks are shaded: with the tiles, or by the shader
+    pub block_style: BlockStyle,
+    // A soft light around the falling piece, in its color
+    pub glow: bool,
+    // Display settings from the config rather than theme files, see DisplaySettings
+    #[serde(skip)]
+    pub patterns: bool,
+    #[serde(skip)]
+    pub ghost: bool,
+    #[serde(skip)]
+    pub grid_lines: bool,
+    #[serde(skip)]
+    pub well_background: bool,
+}
+
+// How blocks are drawn. Everything but the tiles ignores `blocks`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockStyle {
+    // The block tile from `blocks`, tinted with the piece color
+    #[default]
+    Tiles,
+    // A plain square of the piece color
+    Flat,
+    // Light and dark edges, and a highlight on top
+    Bevel,
+    // Rounded corners and a highlight on top
+    Rounded,
+}
+
+// Piece colors that stay apart for players with color vision deficiencies.
+// They replace the colors of the 7 standard pieces (by letter) in whatever theme is used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Palette {
+    // The theme's own colors
+    #[default]
+    Standard,
+    // Red-green, weak green (based on the Okabe-Ito colors)
+    Deuteranopia,
+    // Red-green, weak red: no red piece, reds look dark
+    Protanopia,
+    // Blue-yellow: told apart by red and brightness instead
+    Tritanopia,
+    // Saturated colors on black, also for the background and UI
+    HighContrast,
+}
+
+impl Palette {
+    // In the order the settings screen goes through them
+    pub const ALL: [Palette; 5] = [Palette::Standard, Palette::Deuteranopia, Palette::Protanopia, Palette::Tritanopia, Palette::HighContrast];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Standard => "STANDARD",
+            Palette::Deuteranopia => "DEUTERANOPIA",
+            Palette::Protanopia => "PROTANOPIA",
+            Palette::Tritanopia => "TRITANOPIA",
+            Palette::HighContrast => "HIGH CONTRAST",
+        }
+    }
+
+    // The next one in ALL, `forward` or back, wrapping around
+    pub fn cycle(self, forward: bool) -> Palette {
+        let count = Palette::ALL.len();
+        let index = Palette::ALL.iter().position(|&palette| palette == self).unwrap_or(0);
+        Palette::ALL[if forward { (index + 1) % count } else { (index + count - 1) % count }]
+    }
+
+    // Colors of I, O, T, S, Z, J and L
+    fn piece_colors(self) -> Option<[[u8; 3]; 7]> {
+        match self {
+            Palette::Standard => None,
+            Palette::Deuteranopia => Some([[86, 180, 233], [240, 228, 66], [204, 121, 167], [0, 158, 115], [213, 94, 0], [0, 114, 178], [230, 159, 0]]),
+            Palette::Protanopia => Some([[86, 180, 233], [240, 228, 66], [204, 121, 167], [0, 158, 115], [240, 240, 240], [0, 114, 178], [230, 159, 0]]),
+            Palette::Tritanopia => Some([[215, 48, 39], [240, 240, 240], [241, 182, 218], [1, 102, 94], [140, 81, 10], [253, 174, 97], [128, 128, 128]]),
+            Palette::HighContrast => Some([[0, 255, 255], [255, 255, 0], [255, 0, 255], [0, 255, 0], [255, 0, 0], [80, 120, 255], [255, 128, 0]]),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            name: "CLASSIC".to_string(),
+            background: [26, 26, 26],
+            ui: [204, 204, 204],
+            garbage: [115, 115, 115],
+            well: [12, 12, 14],
+            pieces: BTreeMap::new(),
+            blocks: None,
+            block_style: BlockStyle::Tiles,
+            glow: false,
+            patterns: false,
+            ghost: true,
+            grid_lines: false,
+            well_background: false,
+        }
+    }
+}
+
+impl Theme {
+    pub fn load(path: &Path) -> io::Result<Theme> {
+        let json = fs::read_to_string(path)?;
+        let mut theme: Theme = serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        // Tiles sit next to the theme, wherever the game is started from
+        if let (Some(blocks), Some(dir)) = (&theme.blocks, path.parent()) {
+            theme.blocks = Some(dir.join(blocks));
+        }
+        Ok(theme)
+    }
+
+    // The built-in theme followed by every theme file in the directory, sorted by file name.
+    // Broken files are reported and skipped.
+    pub fn load_all(dir: &Path) -> Vec<Theme> {
+        let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect(),
+            Err(e) => {
+                tracing::warn!("Could not read themes from {}: {}", dir.display(), e);
+                Vec::new()
+            }
+        };
+        paths.sort();
+
+        let mut themes = vec![Theme::default()];
+        themes.extend(paths.iter().filter_map(|path| match Theme::load(path) {
+            Ok(theme) => Some(theme),
+            Err(e) => {
+                tracing::warn!("Skipping theme {}: {}", path.display(), e);
+                None
+            }
+        }));
+        themes
+    }
+
+    // This theme with the display settings on top: accessibility and well styling
+    pub fn with_settings(&self, display: &DisplaySettings) -> Theme {
+        let mut theme = self.clone();
+        let palette = display.palette;
+        if let Some(colors) = palette.piece_colors() {
+            for (letter, color) in "IOTSZJL".chars().zip(colors) {
+                theme.pieces.insert(letter, color);
+            }
+        }
+        if palette == Palette::HighContrast {
+            theme.background = [0, 0, 0];
+            theme.ui = [255, 255, 255];
+            theme.garbage = [150, 150, 150];
+            theme.well = [0, 0, 0];
+        }
+        theme.patterns = display.piece_patterns;
+        theme.ghost = display.ghost_piece;
+        theme.grid_lines = display.grid_lines;
+        theme.well_background = display.well_background;
+        theme
+    }
+
+    pub fn background_color(&self) -> [f32; 4] {
+        to_color(self.background)
+    }
+
+    pub fn ui_color(&self) -> [f32; 4] {
+        to_color(self.ui)
+    }
+
+    pub fn well_color(&self) -> [f32; 4] {
+        to_color(self.well)
+    }
+
+    // Shape colors come from the theme when it has one for the letter, otherwise from the piece set
+    pub fn piece_color(&self, pieces: &PieceSet, shape: TetrominoShape) -> [f32; 4] {
+        let rgb = self.pieces.get(&pieces.letter(shape)).copied().unwrap_or_else(|| pieces.color(shape));
+        to_color(rgb)
+    }
+
+    // The color of a locked grid cell
+    pub fn cell_color(&self, pieces: &PieceSet, cell: u8) -> [f32; 4] {
+        match cell {
+            GARBAGE_CELL => to_color(self.garbage),
+            shape => self.piece_color(pieces, TetrominoShape::from_index(shape as usize - 1)),
+        }
+    }
+}
+
+// The pattern drawn on a shape's blocks, as dots on a 3x3 grid (bit = row * 3 + column).
+// The 7 standard shapes get one that hints at the piece, other shapes one made from their index.
+pub fn pattern(shape: TetrominoShape) -> u16 {
+    match shape {
+        TetrominoShape::I => 0b000_111_000, // a bar
+        TetrominoShape::O => 0b000_010_000, // a dot
+        TetrominoShape::T => 0b000_010_111, // a T
+        TetrominoShape::S => 0b001_010_100, // a / diagonal
+        TetrominoShape::Z => 0b100_010_001, // a \ diagonal
+        TetrominoShape::J => 0b001_001_001, // the left column
+        TetrominoShape::L => 0b100_100_100, // the right column
+        _ => 0b111_101_111 ^ (shape.to_index() as u16 + 1),
+    }
+}
+
+fn to_color([r, g, b]: [u8; 3]) -> [f32; 4] {
+    [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0]
+}
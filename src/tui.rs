@@ -0,0 +1,43 @@
+use learning1::game::{GARBAGE_CELL, HEIGHT, HIDDEN_ROWS, VISIBLE_HEIGHT, WIDTH};
+use learning1::piece_set::PieceSet;
+use learning1::snapshot::BoardSnapshot;
+use learning1::tetromino::TetrominoShape;
+
+// Character used for each kind of cell in the text frontend
+pub const EMPTY_CHAR: char = '.';
+pub const GARBAGE_CHAR: char = '#';
+pub const FALLING_CHAR: char = '@';
+
+// The text frontend: draws a board snapshot as one string per row.
+// Locked cells show the letter of the shape they came from, the falling piece is '@'.
+// It reads nothing but the snapshot, exactly like the GPU renderer in vertex_data.rs,
+// and likewise only draws the visible rows.
+pub fn board_lines(snapshot: &BoardSnapshot) -> Vec<String> {
+    let mut rows = [[EMPTY_CHAR; WIDTH]; VISIBLE_HEIGHT];
+
+    for (row, grid_row) in rows.iter_mut().zip(&snapshot.grid[HIDDEN_ROWS..]) {
+        for (cell, &grid_cell) in row.iter_mut().zip(grid_row) {
+            *cell = cell_char(grid_cell, &snapshot.piece_set);
+        }
+    }
+
+    if let Some((_, cells)) = &snapshot.current_piece {
+        for &(x, y) in cells {
+            // Pieces spawn in the hidden rows, only the part that came down is drawn
+            if (0..WIDTH as i32).contains(&x) && (HIDDEN_ROWS as i32..HEIGHT as i32).contains(&y) {
+                rows[y as usize - HIDDEN_ROWS][x as usize] = FALLING_CHAR;
+            }
+        }
+    }
+
+    rows.iter().map(|row| row.iter().collect()).collect()
+}
+
+// The character for a locked grid cell
+pub fn cell_char(cell: u8, pieces: &PieceSet) -> char {
+    match cell {
+        0 => EMPTY_CHAR,
+        GARBAGE_CELL => GARBAGE_CHAR,
+        shape => pieces.letter(TetrominoShape::from_index(shape as usize - 1)),
+    }
+}
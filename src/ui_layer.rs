@@ -0,0 +1,67 @@
+use learning1::session::PlayMetrics;
+use learning1::snapshot::BoardSnapshot;
+
+use crate::graphic_context::TextEntry;
+use crate::theme::Theme;
+use crate::vertex_data::{stat_y, stats_fit, HOLD_X, PANEL_X, PREVIEW_Y, SCORE_Y, STATS_Y};
+
+const VALUE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// The text of a board's side panel, in board coordinates: box labels, score and the
+// piece statistics. The mesh builder draws the boxes and icons these go with.
+pub fn board_text(game: &BoardSnapshot, theme: &Theme) -> Vec<TextEntry> {
+    let ui_color = theme.ui_color();
+    let label = |text: &str, x: f32, y: f32| TextEntry { text: text.to_string(), x, y, color: ui_color, scale: 0.8 };
+
+    let mut text = vec![
+        label("NEXT", PANEL_X, PREVIEW_Y - 1.5),
+        label("HOLD", HOLD_X, PREVIEW_Y - 1.5),
+        label("SCORE", PANEL_X, SCORE_Y),
+        TextEntry { text: game.score.to_string(), x: PANEL_X, y: SCORE_Y + 1.0, color: VALUE_COLOR, scale: 1.0 },
+        label("STATS", PANEL_X, STATS_Y - 1.2),
+    ];
+    text.extend(stats_text(game, ui_color));
+    if let Some(metrics) = &game.metrics {
+        text.extend(metrics_text(metrics, ui_color));
+    }
+    text
+}
+
+// Play rates, in a column next to the score
+fn metrics_text(metrics: &PlayMetrics, ui_color: [f32; 4]) -> Vec<TextEntry> {
+    let rows = [
+        ("PPS", format!("{:.2}", metrics.pps)),
+        ("LPM", format!("{:.1}", metrics.lpm)),
+        ("APM", format!("{:.0}", metrics.apm)),
+    ];
+    let mut text = Vec::new();
+    for (i, (label, value)) in rows.into_iter().enumerate() {
+        let y = SCORE_Y + i as f32 * 0.8;
+        text.push(TextEntry { text: label.to_string(), x: HOLD_X, y, color: ui_color, scale: 0.55 });
+        text.push(TextEntry { text: value, x: HOLD_X + 2.5, y, color: VALUE_COLOR, scale: 0.55 });
+    }
+    text
+}
+
+// Count and share of every shape dealt, next to its icon
+fn stats_text(game: &BoardSnapshot, ui_color: [f32; 4]) -> Vec<TextEntry> {
+    let mut text = Vec::new();
+    let total_pieces: u32 = game.piece_stats.iter().sum();
+    let fit = stats_fit(game.piece_set.pieces.len());
+    let scale = 0.7 * fit;
+
+    for i in 0..game.piece_set.pieces.len() {
+        let count = game.piece_stats.get(i).copied().unwrap_or(0);
+        let x = PANEL_X + 3.5;
+        let y = stat_y(i, fit) + 0.2 * fit; // Adjust for font baseline
+        text.push(TextEntry { text: count.to_string(), x, y, color: VALUE_COLOR, scale });
+
+        if total_pieces > 0 {
+            let pct = (count as f32 / total_pieces as f32 * 100.0) as u32;
+            text.push(TextEntry { text: "-".to_string(), x: x + 2.5, y, color: ui_color, scale });
+            text.push(TextEntry { text: format!("{}%", pct), x: x + 4.0, y, color: VALUE_COLOR, scale });
+        }
+    }
+
+    text
+}
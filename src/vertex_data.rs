@@ -1,387 +1,375 @@
-use crate::graphic_context::{Vertex, TextEntry};
-use crate::game::{Game, WIDTH, HEIGHT};
-use crate::tetromino::TetrominoShape;
-
-const COLORS: [[f32; 4]; 7] = [
-    [0.0, 1.0, 1.0, 1.0], // I - Cyan
-    [1.0, 1.0, 0.0, 1.0], // O - Yellow
-    [0.5, 0.0, 0.5, 1.0], // T - Purple
-    [0.0, 1.0, 0.0, 1.0], // S - Green
-    [1.0, 0.0, 0.0, 1.0], // Z - Red
-    [0.0, 0.0, 1.0, 1.0], // J - Blue
-    [1.0, 0.5, 0.0, 1.0], // L - Orange
-];
-
-const UI_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0]; // Light grey for UI elements
-
-pub fn get_color(index: usize) -> [f32; 4] {
-    if index < 7 {
-        COLORS[index]
-    } else {
-        [1.0, 1.0, 1.0, 1.0] // Fallback white
-    }
+use crate::effects::EffectsFrame;
+use crate::graphic_context::{QuadInstance, TextEntry, QUAD_STYLE_BEVEL, QUAD_STYLE_GLOW, QUAD_STYLE_ROUNDED, QUAD_STYLE_TEXTURED};
+use crate::theme::{self, BlockStyle, Theme};
+use crate::ui_layer;
+use learning1::game::{GARBAGE_CELL, HIDDEN_ROWS, VISIBLE_HEIGHT, WIDTH, HEIGHT};
+use learning1::snapshot::BoardSnapshot;
+use learning1::tetromino::TetrominoShape;
+
+// Regions of the block atlas (assets/blocks.png, 64x32): a plain white tile on the left
+// for flat quads, and the block tile on the right. Texture coordinates stay half a texel
+// inside the tile so linear filtering doesn't pick up the neighbouring one.
+const WHITE_UV: [f32; 2] = [0.25, 0.5];
+const BLOCK_UV_LEFT: f32 = 32.5 / 64.0;
+const BLOCK_UV_RIGHT: f32 = 63.5 / 64.0;
+const BLOCK_UV_TOP: f32 = 0.5 / 32.0;
+const BLOCK_UV_BOTTOM: f32 = 31.5 / 32.0;
+
+const GLOW_SIZE: f32 = 2.2; // In cells, centered on each block of the falling piece
+const GLOW_ALPHA: f32 = 0.3;
+const PATTERN_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.55]; // Shape patterns, for telling pieces apart without color
+const GRID_LINE_WIDTH: f32 = 0.04; // In cells
+const GRID_LINE_ALPHA: f32 = 0.08; // Grid lines use the UI color, barely visible
+const DANGER_LINE_COLOR: [f32; 4] = [0.8, 0.2, 0.2, 0.6]; // Lock-out boundary at the top of the board
+const DANGER_TINT: [f32; 4] = [0.9, 0.1, 0.1, 0.25]; // Over the playfield at full danger, the alpha follows the pulse
+const BORDER_COLOR: [f32; 4] = [0.3, 0.3, 0.3, 1.0];
+const DANGER_BORDER_COLOR: [f32; 4] = [1.0, 0.2, 0.2, 1.0];
+
+// The row a grid cell is drawn on, None for cells off the board or in the hidden rows
+fn visible_row(x: i32, y: i32) -> Option<f32> {
+    let on_board = (0..WIDTH as i32).contains(&x) && (HIDDEN_ROWS as i32..HEIGHT as i32).contains(&y);
+    on_board.then(|| (y - HIDDEN_ROWS as i32) as f32)
+}
+
+// Logical size of the area a single board (grid + side panel) is laid out in
+pub const LOGICAL_WIDTH: f32 = WIDTH as f32 + 16.0; // 10 + padding/ui space (was +8.0)
+pub const LOGICAL_HEIGHT: f32 = 29.0; // Compacted height to zoom in
+
+// Side panel layout in board coordinates, shared with the UI text layer
+pub const PANEL_X: f32 = WIDTH as f32 + 2.0;
+pub const HOLD_X: f32 = PANEL_X + 6.0;
+// Top of the next and hold boxes, their labels sit above
+pub const PREVIEW_Y: f32 = 2.0;
+pub const SCORE_Y: f32 = 8.0;
+pub const STATS_Y: f32 = 12.0;
+// Each shape of the stats table takes this much height: roughly 2 for the icon, plus a gap
+const STAT_SPACING: f32 = 2.3;
+
+// Sets with more shapes than the 7 that fit below the score get a smaller table
+pub fn stats_fit(shape_count: usize) -> f32 {
+    ((LOGICAL_HEIGHT - STATS_Y - 0.5) / (shape_count as f32 * STAT_SPACING)).min(1.0)
+}
+
+// Top of a shape's line in the stats table
+pub fn stat_y(index: usize, fit: f32) -> f32 {
+    STATS_Y + index as f32 * STAT_SPACING * fit
+}
+
+// Where a board is drawn inside the logical area: an offset (in logical units) and a scale.
+// A single board uses the whole area, several boards are shrunk to sit side by side.
+#[derive(Clone, Copy)]
+pub struct BoardArea {
+    pub x: f32,
+    pub y: f32,
+    pub scale: f32,
+    // Multiplied into every color's alpha, to fade a board into the background
+    pub alpha: f32,
 }
 
-pub fn build_mesh(game: &Game, window_width: u32, window_height: u32) -> (Vec<Vertex>, Vec<TextEntry>) {
-    let mut vertices = Vec::new();
+// Draws one or more boards next to each other, each scaled down to fit the window
+pub fn build_multi_mesh(boards: &[(BoardSnapshot, EffectsFrame)], theme: &Theme) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+    let mut instances = Vec::new();
     let mut text_entries = Vec::new();
 
-    // Layout configuration
-    // Grid: 10 wide, 20 high.
-    // Side panel: starts at x=11, say 6 wide.
-    // Total logical area: 28x29 (Widened for Stats).
-    
-    let logical_width = WIDTH as f32 + 16.0; // 10 + padding/ui space (was +8.0)
-    let logical_height = 29.0; // Compacted height to zoom in
+    let count = boards.len().max(1) as f32;
+    let scale = 1.0 / count;
+    let y = (LOGICAL_HEIGHT - LOGICAL_HEIGHT * scale) / 2.0;
 
-    // Determine scale to fit logical area into window while maintaining aspect ratio
-    // We want 1 logical unit = N pixels, where N is same for X and Y.
-    
-    // Scale factor based on height (fit vertical)
-    let _scale_h = 2.0 / logical_height; 
-    // Normalized Device Coordinates are -1 to 1 (height 2).
-    // So if logical height is 20, we want 1 unit = 2/20 = 0.1 NDC height.
-
-    // Correction for aspect ratio
-    // NDC Width is 2.0 (-1 to 1).
-    // Physical Width / Physical Height = Aspect.
-    // To make a square, 1 unit X in NDC = (1 unit Y in NDC) / Aspect.
-    let aspect = window_width as f32 / window_height as f32;
-    let unit_size_y = 1.9 / logical_height; // Leave a little margin (1.9 instead of 2.0)
-    let unit_size_x = unit_size_y / aspect; // Correct for non-square window
-
-    // Center the content
-    // Total width in NDC = unit_size_x * logical_width
-    let total_ndc_width = unit_size_x * logical_width;
-    let start_x = -total_ndc_width / 2.0;
-    
-    // Total height in NDC = unit_size_y * logical_height
-    let total_ndc_height = unit_size_y * logical_height;
-    let start_y = total_ndc_height / 2.0;
-
-    let ctx = DrawContext {
-        unit_size_x,
-        unit_size_y,
-        start_x,
-        start_y,
+    for (i, (game, effects)) in boards.iter().enumerate() {
+        let area = BoardArea { x: i as f32 * LOGICAL_WIDTH * scale, y, scale, alpha: 1.0 };
+        let (board_instances, board_text) = build_board_mesh(game, effects, theme, area);
+        instances.extend(board_instances);
+        text_entries.extend(board_text);
+    }
+
+    (instances, text_entries)
+}
+
+pub fn build_board_mesh(game: &BoardSnapshot, effects: &EffectsFrame, theme: &Theme, area: BoardArea) -> (Vec<QuadInstance>, Vec<TextEntry>) {
+    let mut instances = Vec::new();
+    let mut text_entries = Vec::new();
+    let ui_color = theme.ui_color();
+
+    // Screen shake moves the whole board, panel included
+    let area = BoardArea {
+        x: area.x + effects.shake.0 * area.scale,
+        y: area.y + effects.shake.1 * area.scale,
+        ..area
     };
 
-    // 1. Render the Grid Background/Border (Optional - can be just empty space)
+    // Everything is drawn in logical units, the renderer's projection fits them into the window.
+    // Move and shrink everything into the board's area.
+    let ctx = DrawContext { x: area.x, y: area.y, scale: area.scale };
+
+    // 1. Render the Grid Background/Border
+    // The well background and grid lines are options, drawn first so everything else covers them
+    if theme.well_background {
+        draw_quad(
+            &mut instances,
+            ctx.x,
+            ctx.x + WIDTH as f32 * ctx.scale,
+            ctx.y,
+            ctx.y + VISIBLE_HEIGHT as f32 * ctx.scale,
+            theme.well_color(),
+        );
+    }
+    if theme.grid_lines {
+        let [r, g, b, _] = ui_color;
+        let line_color = [r, g, b, GRID_LINE_ALPHA];
+        let half = GRID_LINE_WIDTH / 2.0;
+        for x in 1..WIDTH {
+            let x = x as f32;
+            let (left, right) = (ctx.x + (x - half) * ctx.scale, ctx.x + (x + half) * ctx.scale);
+            draw_quad(&mut instances, left, right, ctx.y, ctx.y + VISIBLE_HEIGHT as f32 * ctx.scale, line_color);
+        }
+        for y in 1..VISIBLE_HEIGHT {
+            let y = y as f32;
+            let (top, bottom) = (ctx.y + (y - half) * ctx.scale, ctx.y + (y + half) * ctx.scale);
+            draw_quad(&mut instances, ctx.x, ctx.x + WIDTH as f32 * ctx.scale, top, bottom, line_color);
+        }
+    }
+    // The playfield turns red and its border pulses while the stack is close to the top
+    if effects.danger > 0.0 {
+        let [r, g, b, a] = DANGER_TINT;
+        draw_quad(
+            &mut instances,
+            ctx.x,
+            ctx.x + WIDTH as f32 * ctx.scale,
+            ctx.y,
+            ctx.y + VISIBLE_HEIGHT as f32 * ctx.scale,
+            [r, g, b, a * effects.danger],
+        );
+    }
     // Let's draw a border around the grid
-    draw_rect_outline(&mut vertices, ctx, 0.0, 0.0, WIDTH as f32, HEIGHT as f32, [0.3, 0.3, 0.3, 1.0]);
+    let border_color = std::array::from_fn(|i| BORDER_COLOR[i] + (DANGER_BORDER_COLOR[i] - BORDER_COLOR[i]) * effects.danger);
+    draw_rect_outline(&mut instances, ctx, 0.0, 0.0, WIDTH as f32, VISIBLE_HEIGHT as f32, border_color);
+
+    // Danger line, drawn first so blocks cover it.
+    // Pieces spawn in the hidden rows above it, and top out if they lock entirely up there.
+    draw_quad(
+        &mut instances,
+        ctx.x,
+        ctx.x + WIDTH as f32 * ctx.scale,
+        ctx.y + 0.05 * ctx.scale,
+        ctx.y + 0.12 * ctx.scale,
+        DANGER_LINE_COLOR,
+    );
 
     // 2. Render Existing Grid Blocks
-    for y in 0..HEIGHT {
+    // An invisible stack is only revealed once the game is over
+    let show_stack = !game.invisible || game.is_game_over;
+    // Only the visible rows are drawn, the hidden ones are clipped
+    for y in (HIDDEN_ROWS..HEIGHT).filter(|_| show_stack) {
         for x in 0..WIDTH {
             let cell = game.grid[y][x];
             if cell > 0 {
-                let color_idx = (cell - 1) as usize;
-                let color = get_color(color_idx);
-                add_block(&mut vertices, ctx, x as f32, y as f32, color);
+                let color = theme.cell_color(&game.piece_set, cell);
+                add_block(&mut instances, ctx, theme, x as f32, (y - HIDDEN_ROWS) as f32, color);
+                if cell != GARBAGE_CELL {
+                    add_pattern(&mut instances, ctx, theme, x as f32, (y - HIDDEN_ROWS) as f32, TetrominoShape::from_index(cell as usize - 1));
+                }
             }
         }
     }
     
     // Ghost Piece
-    if let Some(ghost) = game.get_ghost_piece_position() {
-        let color_idx = ghost.shape.to_index();
-        let base_color = get_color(color_idx);
+    if let (true, Some((shape, _)), Some(ghost)) = (theme.ghost, &game.current_piece, game.ghost_cells()) {
+        let base_color = theme.piece_color(&game.piece_set, *shape);
         let ghost_color = [base_color[0], base_color[1], base_color[2], 0.05]; // low alpha
 
-        for (cx, cy) in ghost.cells.iter() {
-            let x = ghost.x + cx;
-            let y = ghost.y + cy;
-
-            if x >= 0 && x < WIDTH as i32 && y >= 0 && y < HEIGHT as i32 {
-                add_block(&mut vertices, ctx, x as f32, y as f32, ghost_color);
+        for (x, y) in ghost {
+            if let Some(row) = visible_row(x, y) {
+                add_block(&mut instances, ctx, theme, x as f32, row, ghost_color);
             }
         }
     }
 
     // 3. Render Active Piece
-    if let Some(ref piece) = game.current_piece {
-        let color_idx = piece.shape.to_index();
-        let color = get_color(color_idx);
-        
-        for (cx, cy) in piece.cells.iter() {
-            let x = piece.x + cx;
-            let y = piece.y + cy;
-
-            if x >= 0 && x < WIDTH as i32 && y >= 0 && y < HEIGHT as i32 {
-                add_block(&mut vertices, ctx, x as f32, y as f32, color);
+    if let Some((shape, cells)) = &game.current_piece {
+        let color = theme.piece_color(&game.piece_set, *shape);
+
+        // The glow goes under all of the piece's blocks, overlapping ones add up into a halo
+        if theme.glow {
+            let glow_color = [color[0], color[1], color[2], GLOW_ALPHA];
+            for &(x, y) in cells {
+                if let Some(row) = visible_row(x, y) {
+                    add_glow(&mut instances, ctx, x as f32, row, glow_color);
+                }
+            }
+        }
+
+        for &(x, y) in cells {
+            if let Some(row) = visible_row(x, y) {
+                add_block(&mut instances, ctx, theme, x as f32, row, color);
+                add_pattern(&mut instances, ctx, theme, x as f32, row, *shape);
             }
         }
     }
 
-    // 4. Render UI - Next Piece
-    // Valid positions: x=11..
-    let ui_start_x = WIDTH as f32 + 2.0;
-    
-    // Label "NEXT":
-    text_entries.push(TextEntry {
-        text: "NEXT".to_string(),
-        x: ui_start_x,
-        y: 0.5,
-        color: UI_COLOR,
-        scale: 0.8,
-    });
-    
-    let next_piece_y = 2.0;
-    let next_color = get_color(game.next_piece.to_index());
-    
-    for (cx, cy) in game.next_piece.cells().iter() {
-         let px = ui_start_x + 2.0 + *cx as f32;
-         let py = next_piece_y + 2.0 + *cy as f32;
-         add_block(&mut vertices, ctx, px, py, next_color);
+    // HUD overlays from the effects layer (combo meter, hard drop trails...)
+    text_entries.extend(effects.text.iter().cloned());
+    for quad in &effects.quads {
+        draw_quad(
+            &mut instances,
+            ctx.x + quad.x * ctx.scale,
+            ctx.x + (quad.x + quad.w) * ctx.scale,
+            ctx.y + quad.y * ctx.scale,
+            ctx.y + (quad.y + quad.h) * ctx.scale,
+            quad.color,
+        );
     }
-    
-    // Draw box around next piece area
-    draw_rect_outline(&mut vertices, ctx, ui_start_x, next_piece_y, 5.0, 5.0, UI_COLOR);
 
+    // 4. Render UI - Next Piece and Hold boxes (their labels come from the UI text layer)
+    let next_color = theme.piece_color(&game.piece_set, game.next_piece);
+    for (cx, cy) in game.piece_set.cells(game.next_piece) {
+         let px = PANEL_X + 2.0 + *cx as f32;
+         let py = PREVIEW_Y + 2.0 + *cy as f32;
+         add_block(&mut instances, ctx, theme, px, py, next_color);
+         add_pattern(&mut instances, ctx, theme, px, py, game.next_piece);
+    }
+    draw_rect_outline(&mut instances, ctx, PANEL_X, PREVIEW_Y, 5.0, 5.0, ui_color);
 
-    // 5. Render Score
-    let score_y = 9.0;
-    let score_label_y = 8.0; 
-    
-    text_entries.push(TextEntry {
-        text: "SCORE".to_string(),
-        x: ui_start_x,
-        y: score_label_y,
-        color: UI_COLOR,
-        scale: 0.8,
-    });
-
-    let score_str = game.score.to_string();
-    text_entries.push(TextEntry {
-        text: score_str,
-        x: ui_start_x,
-        y: score_y,
-        color: [1.0, 1.0, 1.0, 1.0],
-        scale: 1.0, 
-    });
-
-    // 6. Render Statistics
-    // x = ui_start_x
-    // start y = 14.0 (Need more space below Score)
-    let stats_ptr_y = 12.0;
-
-    text_entries.push(TextEntry {
-        text: "STATS".to_string(),
-        x: ui_start_x,
-        y: stats_ptr_y - 1.2,
-        color: UI_COLOR,
-        scale: 0.8,
-    });
-    
-    // Calculate total for percentages
-    let total_pieces: u32 = game.piece_stats.iter().sum();
-
-    for i in 0..7 {
-        // Increase spacing to allow for the visual shape
-        let spacing = 2.3; // Shapes are roughly 2 high, plus gap
-        let shape_stat_y = stats_ptr_y + (i as f32 * spacing); 
-        
+    if let Some(held) = game.hold_piece {
+        let held_color = theme.piece_color(&game.piece_set, held);
+        for &(cx, cy) in game.piece_set.cells(held) {
+            add_block(&mut instances, ctx, theme, HOLD_X + 2.0 + cx as f32, PREVIEW_Y + 2.0 + cy as f32, held_color);
+            add_pattern(&mut instances, ctx, theme, HOLD_X + 2.0 + cx as f32, PREVIEW_Y + 2.0 + cy as f32, held);
+        }
+    }
+    draw_rect_outline(&mut instances, ctx, HOLD_X, PREVIEW_Y, 5.0, 5.0, ui_color);
+
+    // 5. Render Statistics: a small icon per shape, the counts are text
+    let fit = stats_fit(game.piece_set.pieces.len());
+    for i in 0..game.piece_set.pieces.len() {
         let shape = TetrominoShape::from_index(i);
-        let color = get_color(i);
+        let color = theme.piece_color(&game.piece_set, shape);
+        let mini_scale = 0.6 * fit;
 
-        // 1. Draw Visual Representation (Mini-Shape)
-        let mini_scale = 0.6;
-        
         // Base position for the shape
-        let icon_center_x = ui_start_x + 1.5; 
-        let icon_center_y = shape_stat_y + 0.5;
+        let icon_center_x = PANEL_X + 1.5;
+        let icon_center_y = stat_y(i, fit) + 0.5 * fit;
 
-        // Draw the 4 cells
-        for (cx, cy) in shape.cells().iter() {
+        for (cx, cy) in game.piece_set.cells(shape) {
             let mut cell_ctx = ctx;
-            cell_ctx.unit_size_x *= mini_scale; 
-            cell_ctx.unit_size_y *= mini_scale;
-            
+            cell_ctx.scale *= mini_scale;
+
             let effective_x = (icon_center_x / mini_scale) + (*cx as f32);
             let effective_y = (icon_center_y / mini_scale) + (*cy as f32);
-            
-            add_block(&mut vertices, cell_ctx, effective_x, effective_y, color);
-        }
 
-        // 2. Draw Count
-        let count = game.piece_stats[i];
-        let count_str = count.to_string();
-        
-        let text_start_x = ui_start_x + 3.5;
-        let text_y = shape_stat_y + 0.2; // Adjust for font baseline
-
-        text_entries.push(TextEntry {
-            text: count_str,
-            x: text_start_x,
-            y: text_y,
-            color: [1.0, 1.0, 1.0, 1.0],
-            scale: 0.7,
-        });
-
-        // 3. Draw Percentage
-        if total_pieces > 0 {
-            let pct = (count as f32 / total_pieces as f32) * 100.0;
-            let pct_val = pct as u32; // Integer part
-            
-            // Gap for percentage
-            let pct_start_x = text_start_x + 4.0;
-            
-            // Dash -
-            text_entries.push(TextEntry {
-                text: "-".to_string(),
-                x: text_start_x + 2.5,
-                y: text_y,
-                color: UI_COLOR,
-                scale: 0.7,
-            });
-
-            // "XX%"
-            let pct_text = format!("{}%", pct_val);
-             text_entries.push(TextEntry {
-                text: pct_text,
-                x: pct_start_x,
-                y: text_y,
-                color: [1.0, 1.0, 1.0, 1.0],
-                scale: 0.7,
-            });
+            add_block(&mut instances, cell_ctx, theme, effective_x, effective_y, color);
+            add_pattern(&mut instances, cell_ctx, theme, effective_x, effective_y, shape);
         }
     }
 
-    (vertices, text_entries)
+    // 6. Labels, score and counts
+    text_entries.extend(ui_layer::board_text(game, theme));
+
+    // Text was laid out in board coordinates, move it into the board's area too
+    for entry in &mut text_entries {
+        entry.x = area.x + entry.x * area.scale;
+        entry.y = area.y + entry.y * area.scale;
+        entry.scale *= area.scale;
+        entry.color[3] *= area.alpha;
+    }
+    for instance in &mut instances {
+        instance.color[3] *= area.alpha;
+    }
+
+    (instances, text_entries)
 }
 
 
 #[derive(Clone, Copy)]
 struct DrawContext {
-    unit_size_x: f32,
-    unit_size_y: f32,
-    start_x: f32,
-    start_y: f32,
+    // Logical position of the board's top left corner, and the size of one of its cells
+    x: f32,
+    y: f32,
+    scale: f32,
 }
 
-fn add_block(vertices: &mut Vec<Vertex>, ctx: DrawContext, x: f32, y: f32, color: [f32; 4]) {
-    // Bevel logic for 3D effect
+fn add_block(instances: &mut Vec<QuadInstance>, ctx: DrawContext, theme: &Theme, x: f32, y: f32, color: [f32; 4]) {
+    // A small gap between blocks, the bevel comes from the atlas tile or the shader
     let margin = 0.05;
     let block_size = 1.0 - (margin * 2.0);
-    
-    let sx = ctx.start_x + ((x + margin) * ctx.unit_size_x);
-    let sy = ctx.start_y - ((y + margin) * ctx.unit_size_y);
-    
-    let w = block_size * ctx.unit_size_x;
-    let h = block_size * ctx.unit_size_y;
-
-    // Bevel effect colors
-    let r = color[0];
-    let g = color[1];
-    let b = color[2];
-    let a = color[3];
-
-    // Lighter color for top/left
-    let light = [
-        (r + 0.3).min(1.0), 
-        (g + 0.3).min(1.0), 
-        (b + 0.3).min(1.0), 
-        a
-    ];
-    
-    // Darker color for bottom/right
-    let dark = [
-        (r * 0.6), 
-        (g * 0.6), 
-        (b * 0.6), 
-        a
+
+    let left = ctx.x + ((x + margin) * ctx.scale);
+    let top = ctx.y + ((y + margin) * ctx.scale);
+    let right = left + block_size * ctx.scale;
+    let bottom = top + block_size * ctx.scale;
+
+    let [u, v] = WHITE_UV;
+    let (uv, style) = match theme.block_style {
+        BlockStyle::Tiles => ([BLOCK_UV_LEFT, BLOCK_UV_RIGHT, BLOCK_UV_TOP, BLOCK_UV_BOTTOM], QUAD_STYLE_TEXTURED),
+        BlockStyle::Flat => ([u, u, v, v], QUAD_STYLE_TEXTURED),
+        BlockStyle::Bevel => ([u, u, v, v], QUAD_STYLE_BEVEL),
+        BlockStyle::Rounded => ([u, u, v, v], QUAD_STYLE_ROUNDED),
+    };
+    push_quad(instances, [left, right, top, bottom], uv, color, style);
+}
+
+// A soft light centered on a block, spilling over its neighbours
+fn add_glow(instances: &mut Vec<QuadInstance>, ctx: DrawContext, x: f32, y: f32, color: [f32; 4]) {
+    let half = GLOW_SIZE / 2.0;
+    let (cx, cy) = (x + 0.5, y + 0.5);
+    let rect = [
+        ctx.x + (cx - half) * ctx.scale,
+        ctx.x + (cx + half) * ctx.scale,
+        ctx.y + (cy - half) * ctx.scale,
+        ctx.y + (cy + half) * ctx.scale,
     ];
+    let [u, v] = WHITE_UV;
+    push_quad(instances, rect, [u, u, v, v], color, QUAD_STYLE_GLOW);
+}
 
-    let center_color = color;
-    
-    // Size of the bevel border (percentage of the block width/height)
-    let bevel_ratio = 0.15;
-    let bevel_size_x = w * bevel_ratio;
-    let bevel_size_y = h * bevel_ratio;
-
-    let left = sx;
-    let right = sx + w;
-    let top = sy;
-    let bottom = sy - h;
-
-    let inner_left = left + bevel_size_x;
-    let inner_right = right - bevel_size_x;
-    let inner_top = top - bevel_size_y;
-    let inner_bottom = bottom + bevel_size_y;
-
-    // 1. Center Rectangle (Original Color)
-    draw_quad_absolute(vertices, inner_left, inner_right, inner_top, inner_bottom, center_color);
-
-    // 2. Top Trapezoid (Light)
-    // TL, InnerTL, InnerTR, TR
-    vertices.push(Vertex { position: [left, top, 0.0], color: light }); 
-    vertices.push(Vertex { position: [inner_left, inner_top, 0.0], color: light });
-    vertices.push(Vertex { position: [inner_right, inner_top, 0.0], color: light });
-    vertices.push(Vertex { position: [left, top, 0.0], color: light }); 
-    vertices.push(Vertex { position: [inner_right, inner_top, 0.0], color: light });
-    vertices.push(Vertex { position: [right, top, 0.0], color: light });
-
-    // 3. Left Trapezoid (Light)
-    // TL, BL, InnerBL, InnerTL
-    vertices.push(Vertex { position: [left, top, 0.0], color: light }); 
-    vertices.push(Vertex { position: [left, bottom, 0.0], color: light });
-    vertices.push(Vertex { position: [inner_left, inner_bottom, 0.0], color: light });
-    vertices.push(Vertex { position: [left, top, 0.0], color: light });
-    vertices.push(Vertex { position: [inner_left, inner_bottom, 0.0], color: light });
-    vertices.push(Vertex { position: [inner_left, inner_top, 0.0], color: light });
-
-    // 4. Right Trapezoid (Dark)
-    // TR, InnerTR, InnerBR, BR
-    vertices.push(Vertex { position: [right, top, 0.0], color: dark });
-    vertices.push(Vertex { position: [inner_right, inner_top, 0.0], color: dark });
-    vertices.push(Vertex { position: [inner_right, inner_bottom, 0.0], color: dark });
-    vertices.push(Vertex { position: [right, top, 0.0], color: dark });
-    vertices.push(Vertex { position: [inner_right, inner_bottom, 0.0], color: dark });
-    vertices.push(Vertex { position: [right, bottom, 0.0], color: dark });
-
-    // 5. Bottom Trapezoid (Dark)
-    // BL, InnerBL, InnerBR, BR
-    vertices.push(Vertex { position: [left, bottom, 0.0], color: dark });
-    vertices.push(Vertex { position: [inner_left, inner_bottom, 0.0], color: dark });
-    vertices.push(Vertex { position: [inner_right, inner_bottom, 0.0], color: dark });
-    vertices.push(Vertex { position: [left, bottom, 0.0], color: dark });
-    vertices.push(Vertex { position: [inner_right, inner_bottom, 0.0], color: dark });
-    vertices.push(Vertex { position: [right, bottom, 0.0], color: dark });
+// Dots on a block that tell its shape apart without color, when the theme asks for them
+fn add_pattern(instances: &mut Vec<QuadInstance>, ctx: DrawContext, theme: &Theme, x: f32, y: f32, shape: TetrominoShape) {
+    if !theme.patterns {
+        return;
+    }
+
+    let mask = theme::pattern(shape);
+    let dot = 0.16;
+    for i in (0..9).filter(|i| mask & (1 << i) != 0) {
+        // Dots are centered on a quarter, half and three quarters of the block
+        let cx = x + 0.25 * (i % 3 + 1) as f32;
+        let cy = y + 0.25 * (i / 3 + 1) as f32;
+        draw_quad(
+            instances,
+            ctx.x + (cx - dot / 2.0) * ctx.scale,
+            ctx.x + (cx + dot / 2.0) * ctx.scale,
+            ctx.y + (cy - dot / 2.0) * ctx.scale,
+            ctx.y + (cy + dot / 2.0) * ctx.scale,
+            PATTERN_COLOR,
+        );
+    }
 }
 
-fn draw_rect_outline(vertices: &mut Vec<Vertex>, ctx: DrawContext, x: f32, y: f32, w: f32, h: f32, color: [f32; 4]) {
+fn draw_rect_outline(instances: &mut Vec<QuadInstance>, ctx: DrawContext, x: f32, y: f32, w: f32, h: f32, color: [f32; 4]) {
     // Simple 4 lines using thin quads
-    let ndc_x = ctx.start_x + (x * ctx.unit_size_x);
-    let ndc_y = ctx.start_y - (y * ctx.unit_size_y);
-    let ndc_w = w * ctx.unit_size_x;
-    let ndc_h = h * ctx.unit_size_y;
-    
-    let t_x = 0.05 * ctx.unit_size_x; // thickness
-    let t_y = 0.05 * ctx.unit_size_y;
+    let left = ctx.x + (x * ctx.scale);
+    let top = ctx.y + (y * ctx.scale);
+    let w = w * ctx.scale;
+    let h = h * ctx.scale;
+
+    let t = 0.05 * ctx.scale; // thickness
 
     // Top
-    draw_quad(vertices, ndc_x, ndc_x + ndc_w, ndc_y, ndc_y - t_y, color);
+    draw_quad(instances, left, left + w, top, top + t, color);
     // Bottom
-    draw_quad(vertices, ndc_x, ndc_x + ndc_w, ndc_y - ndc_h + t_y, ndc_y - ndc_h, color);
+    draw_quad(instances, left, left + w, top + h - t, top + h, color);
     // Left
-    draw_quad(vertices, ndc_x, ndc_x + t_x, ndc_y, ndc_y - ndc_h, color);
+    draw_quad(instances, left, left + t, top, top + h, color);
     // Right
-    draw_quad(vertices, ndc_x + ndc_w - t_x, ndc_x + ndc_w, ndc_y, ndc_y - ndc_h, color);
+    draw_quad(instances, left + w - t, left + w, top, top + h, color);
 }
 
-fn draw_quad(vertices: &mut Vec<Vertex>, left: f32, right: f32, top: f32, bottom: f32, color: [f32; 4]) {
-    draw_quad_absolute(vertices, left, right, top, bottom, color);
+fn draw_quad(instances: &mut Vec<QuadInstance>, left: f32, right: f32, top: f32, bottom: f32, color: [f32; 4]) {
+    let [u, v] = WHITE_UV;
+    push_quad(instances, [left, right, top, bottom], [u, u, v, v], color, QUAD_STYLE_TEXTURED);
 }
 
-fn draw_quad_absolute(vertices: &mut Vec<Vertex>, left: f32, right: f32, top: f32, bottom: f32, color: [f32; 4]) {
-    vertices.push(Vertex { position: [left, top, 0.0], color });
-    vertices.push(Vertex { position: [left, bottom, 0.0], color });
-    vertices.push(Vertex { position: [right, bottom, 0.0], color });
-
-    vertices.push(Vertex { position: [left, top, 0.0], color });
-    vertices.push(Vertex { position: [right, bottom, 0.0], color });
-    vertices.push(Vertex { position: [right, top, 0.0], color });
+// Everything is drawn as quads, each one instance of the renderer's unit quad.
+// `rect` and `uv` are left, right, top and bottom.
+fn push_quad(instances: &mut Vec<QuadInstance>, rect: [f32; 4], uv: [f32; 4], color: [f32; 4], style: u32) {
+    instances.push(QuadInstance { rect, color, uv, style });
 }
-
-
-// draw_digit removed